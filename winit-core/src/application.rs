@@ -1,7 +1,9 @@
+use std::any::Any;
 use std::time::Instant;
 
 use crate::dpi::PhysicalSize;
-use crate::event_loop::EventLoopHandle;
+use crate::error::RequestError;
+use crate::event_loop::{EventLoopHandle, TimerId};
 use crate::input::touch::TouchInputHandler;
 use crate::window::WindowId;
 
@@ -9,6 +11,24 @@ pub trait Application: ApplicationWindow {
     /// Wake up due to user request.
     fn user_wakeup(&mut self, loop_handle: &mut dyn EventLoopHandle);
 
+    /// The message sent with [`EventLoopProxy::send`] has arrived.
+    ///
+    /// [`EventLoopProxy::send`]: crate::event_loop::proxy::EventLoopProxy::send
+    fn user_message(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        message: Box<dyn Any + Send>,
+    ) {
+        let _ = loop_handle;
+        let _ = message;
+    }
+
+    /// The timer added with [`EventLoopHandle::add_timer`] has fired.
+    fn timer_fired(&mut self, loop_handle: &mut dyn EventLoopHandle, timer_id: TimerId) {
+        let _ = loop_handle;
+        let _ = timer_id;
+    }
+
     /// Emitted when new events arrive from the OS to be processed.
     fn new_events(&mut self, loop_handle: &mut dyn EventLoopHandle, start_cause: StartCause);
 
@@ -34,10 +54,29 @@ pub trait Application: ApplicationWindow {
     }
 }
 
+/// The callbacks of the windows, except the ones created with their own
+/// [`WindowHandler`].
 pub trait ApplicationWindow {
     /// The window with the given `window_id` was created.
     fn created(&mut self, loop_handle: &mut dyn EventLoopHandle, window_id: WindowId);
 
+    /// The window requested with [`EventLoopHandle::create_window`] couldn't
+    /// be created.
+    ///
+    /// Happens when the display server rejects the window, or doesn't
+    /// configure it in time. The window is gone, no other callbacks are
+    /// delivered for it.
+    fn create_failed(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        window_id: WindowId,
+        error: RequestError,
+    ) {
+        let _ = loop_handle;
+        let _ = window_id;
+        let _ = error;
+    }
+
     /// The size of the window has changed. Contains the client area's new
     /// dimensions.
     fn resized(
@@ -100,7 +139,27 @@ pub trait ApplicationWindow {
     }
 }
 
-pub trait DeviceEventsHandler: Application {}
+/// The handler of the window created with
+/// [`EventLoopHandle::create_window_with_handler`], getting the callbacks of
+/// that window only.
+///
+/// Implemented for any [`ApplicationWindow`].
+pub trait WindowHandler: ApplicationWindow {}
+
+impl<T: ApplicationWindow> WindowHandler for T {}
+
+/// The raw input, not tied to any window.
+pub trait DeviceEventsHandler: Application {
+    /// The pointer has moved by `delta`, without acceleration and without
+    /// being stopped by the edges of the screen.
+    ///
+    /// Reported even when the pointer is locked with
+    /// [`CursorGrabMode::Locked`], which is what the camera controls usually
+    /// want.
+    ///
+    /// [`CursorGrabMode::Locked`]: crate::cursor::CursorGrabMode::Locked
+    fn mouse_motion(&mut self, loop_handle: &mut dyn EventLoopHandle, delta: (f64, f64));
+}
 
 /// Describes the reason the event loop is resuming.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -0,0 +1,202 @@
+//! The cursor shown for the pointer over the window.
+
+use std::time::Duration;
+
+use crate::error::BadCursor;
+
+/// The cursor icon from the system theme, named after the CSS `cursor`
+/// property values.
+pub use cursor_icon::CursorIcon;
+
+/// The error when parsing the [`CursorIcon`] from its CSS name.
+pub use cursor_icon::ParseError as CursorIconParseError;
+
+/// The cursor to show over the window, set with [`Window::set_cursor`].
+///
+/// [`Window::set_cursor`]: crate::window::Window::set_cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cursor {
+    /// The cursor from the system theme.
+    Icon(CursorIcon),
+
+    /// The cursor registered with [`EventLoopHandle::create_custom_cursor`].
+    ///
+    /// [`EventLoopHandle::create_custom_cursor`]: crate::event_loop::EventLoopHandle::create_custom_cursor
+    Custom(CustomCursor),
+}
+
+impl Default for Cursor {
+    fn default() -> Self {
+        Self::Icon(CursorIcon::Default)
+    }
+}
+
+impl From<CursorIcon> for Cursor {
+    fn from(icon: CursorIcon) -> Self {
+        Self::Icon(icon)
+    }
+}
+
+impl From<CustomCursor> for Cursor {
+    fn from(cursor: CustomCursor) -> Self {
+        Self::Custom(cursor)
+    }
+}
+
+/// How the pointer is held by the window, set with
+/// [`Window::set_cursor_grab`].
+///
+/// [`Window::set_cursor_grab`]: crate::window::Window::set_cursor_grab
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CursorGrabMode {
+    /// The pointer moves freely.
+    #[default]
+    None,
+
+    /// The pointer can't leave the window while it's over it.
+    Confined,
+
+    /// The pointer stays in place while it's over the window, its motion is
+    /// only reported with [`DeviceEventsHandler::mouse_motion`].
+    ///
+    /// [`DeviceEventsHandler::mouse_motion`]: crate::application::DeviceEventsHandler::mouse_motion
+    Locked,
+}
+
+/// The identifier of the custom cursor registered with the event loop.
+///
+/// The cursor is created from the [`CustomCursorSource`] with
+/// [`EventLoopHandle::create_custom_cursor`], and could be used by any window
+/// of that event loop. The cursors of the other event loops are shown as
+/// [`CursorIcon::Default`].
+///
+/// [`EventLoopHandle::create_custom_cursor`]: crate::event_loop::EventLoopHandle::create_custom_cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CustomCursor(pub u128);
+
+impl CustomCursor {
+    /// The static cursor from the RGBA8 image.
+    ///
+    /// The `hotspot` is the pixel of the image pointing at the position of
+    /// the pointer.
+    pub fn from_rgba(
+        width: u16,
+        height: u16,
+        hotspot: (u16, u16),
+        rgba: Vec<u8>,
+    ) -> Result<CustomCursorSource, BadCursor> {
+        let image = CursorImage::from_rgba(width, height, hotspot, rgba)?;
+        Ok(CustomCursorSource { frames: vec![(image, Duration::ZERO)] })
+    }
+
+    /// The cursor cycling through the `frames`, showing each image for its
+    /// duration.
+    pub fn from_animation(
+        frames: Vec<(CursorImage, Duration)>,
+    ) -> Result<CustomCursorSource, BadCursor> {
+        if frames.is_empty() {
+            return Err(BadCursor::NoFrames);
+        }
+
+        if frames.iter().any(|(_, duration)| duration.is_zero()) {
+            return Err(BadCursor::ZeroFrameDuration);
+        }
+
+        Ok(CustomCursorSource { frames })
+    }
+}
+
+/// The images of the custom cursor, registered with
+/// [`EventLoopHandle::create_custom_cursor`].
+///
+/// [`EventLoopHandle::create_custom_cursor`]: crate::event_loop::EventLoopHandle::create_custom_cursor
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CustomCursorSource {
+    frames: Vec<(CursorImage, Duration)>,
+}
+
+impl CustomCursorSource {
+    /// The images with their durations, the only one of the static cursor has
+    /// zero duration.
+    pub fn frames(&self) -> &[(CursorImage, Duration)] {
+        &self.frames
+    }
+
+    /// Whether the cursor has more than one frame.
+    pub fn is_animated(&self) -> bool {
+        self.frames.len() > 1
+    }
+}
+
+/// The image of the custom cursor.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CursorImage {
+    rgba: Vec<u8>,
+    width: u16,
+    height: u16,
+    hotspot: (u16, u16),
+}
+
+impl CursorImage {
+    /// The image from the RGBA8 pixels, see [`CustomCursor::from_rgba`].
+    pub fn from_rgba(
+        width: u16,
+        height: u16,
+        hotspot: (u16, u16),
+        rgba: Vec<u8>,
+    ) -> Result<Self, BadCursor> {
+        if rgba.len() != width as usize * height as usize * 4 {
+            return Err(BadCursor::DimensionsVsPixelCount { width, height, len: rgba.len() });
+        }
+
+        if hotspot.0 >= width || hotspot.1 >= height {
+            return Err(BadCursor::HotspotOutOfBounds { width, height, hotspot });
+        }
+
+        Ok(Self { rgba, width, height, hotspot })
+    }
+
+    /// The pixels, as rows of RGBA8 without padding.
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    pub fn hotspot(&self) -> (u16, u16) {
+        self.hotspot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::error::BadCursor;
+
+    use super::{CursorImage, CustomCursor};
+
+    #[test]
+    fn custom_cursor_validation() {
+        let source = CustomCursor::from_rgba(2, 3, (1, 2), vec![0; 2 * 3 * 4]).unwrap();
+        assert!(!source.is_animated());
+        assert_eq!(source.frames()[0].0.hotspot(), (1, 2));
+
+        let err = CustomCursor::from_rgba(2, 3, (2, 0), vec![0; 2 * 3 * 4]).unwrap_err();
+        assert_eq!(err, BadCursor::HotspotOutOfBounds { width: 2, height: 3, hotspot: (2, 0) });
+
+        let err = CustomCursor::from_rgba(2, 3, (0, 0), vec![0; 5]).unwrap_err();
+        assert_eq!(err, BadCursor::DimensionsVsPixelCount { width: 2, height: 3, len: 5 });
+
+        let image = CursorImage::from_rgba(1, 1, (0, 0), vec![0; 4]).unwrap();
+        assert_eq!(CustomCursor::from_animation(Vec::new()).unwrap_err(), BadCursor::NoFrames);
+        let frames = vec![(image.clone(), Duration::from_millis(50)), (image, Duration::ZERO)];
+        assert_eq!(CustomCursor::from_animation(frames).unwrap_err(), BadCursor::ZeroFrameDuration);
+    }
+}
@@ -0,0 +1,441 @@
+//! Errors reported by the backends.
+
+use std::any::Any;
+use std::error::Error;
+use std::time::Duration;
+use std::{fmt, io, panic};
+
+/// The error which happened when creating or running the event loop.
+#[derive(Debug)]
+pub enum EventLoopError {
+    /// Failed to connect to the display server.
+    Connect(ConnectError),
+
+    /// The connection to the display server was lost while the loop was
+    /// running.
+    ConnectionLost(ConnectionLost),
+
+    /// The display server doesn't provide the functionality required by the
+    /// backend.
+    MissingGlobal(MissingGlobal),
+
+    /// The operation is not supported by the backend.
+    NotSupported(NotSupported),
+
+    /// The OS or the display server returned an error.
+    Os(OsError),
+
+    /// The application callback panicked, and the loop was torn down.
+    CallbackPanicked(CallbackPanicked),
+}
+
+impl fmt::Display for EventLoopError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Connect(err) => err.fmt(f),
+            Self::ConnectionLost(err) => err.fmt(f),
+            Self::MissingGlobal(err) => err.fmt(f),
+            Self::NotSupported(err) => err.fmt(f),
+            Self::Os(err) => err.fmt(f),
+            Self::CallbackPanicked(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for EventLoopError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Connect(err) => Some(err),
+            Self::ConnectionLost(err) => Some(err),
+            Self::MissingGlobal(err) => Some(err),
+            Self::NotSupported(err) => Some(err),
+            Self::Os(err) => Some(err),
+            Self::CallbackPanicked(err) => Some(err),
+        }
+    }
+}
+
+impl From<ConnectError> for EventLoopError {
+    fn from(err: ConnectError) -> Self {
+        Self::Connect(err)
+    }
+}
+
+impl From<ConnectionLost> for EventLoopError {
+    fn from(err: ConnectionLost) -> Self {
+        Self::ConnectionLost(err)
+    }
+}
+
+impl From<MissingGlobal> for EventLoopError {
+    fn from(err: MissingGlobal) -> Self {
+        Self::MissingGlobal(err)
+    }
+}
+
+impl From<NotSupported> for EventLoopError {
+    fn from(err: NotSupported) -> Self {
+        Self::NotSupported(err)
+    }
+}
+
+impl From<OsError> for EventLoopError {
+    fn from(err: OsError) -> Self {
+        Self::Os(err)
+    }
+}
+
+impl From<CallbackPanicked> for EventLoopError {
+    fn from(err: CallbackPanicked) -> Self {
+        Self::CallbackPanicked(err)
+    }
+}
+
+/// The error which happened when performing a request on the event loop, like
+/// creating a window.
+#[derive(Debug)]
+pub enum RequestError {
+    /// The request is not supported by the backend.
+    NotSupported(NotSupported),
+
+    /// The display server didn't complete the request in time.
+    TimedOut(TimedOut),
+
+    /// The OS or the display server returned an error.
+    Os(OsError),
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotSupported(err) => err.fmt(f),
+            Self::TimedOut(err) => err.fmt(f),
+            Self::Os(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for RequestError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::NotSupported(err) => Some(err),
+            Self::TimedOut(err) => Some(err),
+            Self::Os(err) => Some(err),
+        }
+    }
+}
+
+impl From<NotSupported> for RequestError {
+    fn from(err: NotSupported) -> Self {
+        Self::NotSupported(err)
+    }
+}
+
+impl From<TimedOut> for RequestError {
+    fn from(err: TimedOut) -> Self {
+        Self::TimedOut(err)
+    }
+}
+
+impl From<OsError> for RequestError {
+    fn from(err: OsError) -> Self {
+        Self::Os(err)
+    }
+}
+
+/// Failed to connect to the display server.
+///
+/// Usually means that the display server is not running or the environment,
+/// like `WAYLAND_DISPLAY` or `XDG_RUNTIME_DIR`, is not set up, thus the
+/// application could try a different backend.
+#[derive(Debug)]
+pub struct ConnectError {
+    error: Box<dyn Error + Send + Sync + 'static>,
+}
+
+impl ConnectError {
+    pub fn new(error: impl Into<Box<dyn Error + Send + Sync + 'static>>) -> Self {
+        Self { error: error.into() }
+    }
+}
+
+impl fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to connect to the display server: {}", self.error)
+    }
+}
+
+impl Error for ConnectError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.error.as_ref())
+    }
+}
+
+/// The connection to the display server was lost.
+///
+/// Happens when the display server crashes, restarts, or kills the client due
+/// to a protocol error.
+#[derive(Debug)]
+pub enum ConnectionLost {
+    /// The display server has sent a fatal protocol error.
+    Protocol(ProtocolError),
+
+    /// The connection was closed or failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for ConnectionLost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Protocol(err) => write!(f, "connection to the display server lost: {err}"),
+            Self::Io(err) => write!(f, "connection to the display server lost: {err}"),
+        }
+    }
+}
+
+impl Error for ConnectionLost {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Protocol(err) => Some(err),
+            Self::Io(err) => Some(err),
+        }
+    }
+}
+
+/// The fatal protocol error sent by the display server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtocolError {
+    /// The interface of the object which caused the error.
+    pub interface: String,
+
+    /// The id of the object which caused the error.
+    pub object_id: u32,
+
+    /// The error code, specific to the interface.
+    pub code: u32,
+
+    /// The message describing the error.
+    pub message: String,
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "protocol error {} on object {}@{}: {}",
+            self.code, self.interface, self.object_id, self.message
+        )
+    }
+}
+
+impl Error for ProtocolError {}
+
+/// The global required by the backend is not advertised by the display
+/// server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingGlobal {
+    /// The name of the missing interface.
+    pub interface: &'static str,
+
+    /// The minimum version of the interface required.
+    pub version: u32,
+}
+
+impl MissingGlobal {
+    pub fn new(interface: &'static str, version: u32) -> Self {
+        Self { interface, version }
+    }
+}
+
+impl fmt::Display for MissingGlobal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "missing required global {} version {}", self.interface, self.version)
+    }
+}
+
+impl Error for MissingGlobal {}
+
+/// The operation is not supported by the backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotSupported {
+    reason: &'static str,
+}
+
+impl NotSupported {
+    pub fn new(reason: &'static str) -> Self {
+        Self { reason }
+    }
+}
+
+impl fmt::Display for NotSupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "operation is not supported: {}", self.reason)
+    }
+}
+
+impl Error for NotSupported {}
+
+/// The display server didn't complete the request in time.
+///
+/// For example, the compositor didn't send the initial configure for the new
+/// window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimedOut {
+    request: &'static str,
+    timeout: Duration,
+}
+
+impl TimedOut {
+    pub fn new(request: &'static str, timeout: Duration) -> Self {
+        Self { request, timeout }
+    }
+
+    /// How long the request was waited for.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} timed out after {:?}", self.request, self.timeout)
+    }
+}
+
+impl Error for TimedOut {}
+
+/// The pixel data of the icon is invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BadIcon {
+    /// The length of the RGBA data isn't `width * height * 4`.
+    DimensionsVsPixelCount { width: u32, height: u32, len: usize },
+}
+
+impl fmt::Display for BadIcon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DimensionsVsPixelCount { width, height, len } => write!(
+                f,
+                "the icon of {width}x{height} pixels needs {} bytes of RGBA data, got {len}",
+                *width as usize * *height as usize * 4,
+            ),
+        }
+    }
+}
+
+impl Error for BadIcon {}
+
+/// The image of the custom cursor is invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BadCursor {
+    /// The length of the RGBA data isn't `width * height * 4`.
+    DimensionsVsPixelCount { width: u16, height: u16, len: usize },
+
+    /// The hotspot is outside of the image.
+    HotspotOutOfBounds { width: u16, height: u16, hotspot: (u16, u16) },
+
+    /// The animation has no frames.
+    NoFrames,
+
+    /// The frame of the animation has zero duration.
+    ZeroFrameDuration,
+}
+
+impl fmt::Display for BadCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DimensionsVsPixelCount { width, height, len } => write!(
+                f,
+                "the cursor of {width}x{height} pixels needs {} bytes of RGBA data, got {len}",
+                *width as usize * *height as usize * 4,
+            ),
+            Self::HotspotOutOfBounds { width, height, hotspot: (x, y) } => {
+                write!(f, "the hotspot {x}x{y} is outside of the {width}x{height} cursor")
+            },
+            Self::NoFrames => f.write_str("the animated cursor has no frames"),
+            Self::ZeroFrameDuration => {
+                f.write_str("the frame of the animated cursor has zero duration")
+            },
+        }
+    }
+}
+
+impl Error for BadCursor {}
+
+/// The error returned by the OS or the display server.
+#[derive(Debug)]
+pub struct OsError {
+    error: Box<dyn Error + Send + Sync + 'static>,
+}
+
+impl OsError {
+    pub fn new(error: impl Into<Box<dyn Error + Send + Sync + 'static>>) -> Self {
+        Self { error: error.into() }
+    }
+}
+
+impl fmt::Display for OsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "os error: {}", self.error)
+    }
+}
+
+impl Error for OsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.error.as_ref())
+    }
+}
+
+/// The panic of the application callback caught by the event loop.
+///
+/// The event loop catches the panic instead of unwinding through the display
+/// server connection, tears itself down, and hands the panic over. Use
+/// [`CallbackPanicked::resume`] to continue unwinding.
+pub struct CallbackPanicked {
+    payload: Box<dyn Any + Send + 'static>,
+}
+
+impl CallbackPanicked {
+    pub fn new(payload: Box<dyn Any + Send + 'static>) -> Self {
+        Self { payload }
+    }
+
+    /// The payload of the panic, as returned by [`std::panic::catch_unwind`].
+    pub fn payload(&self) -> &(dyn Any + Send + 'static) {
+        self.payload.as_ref()
+    }
+
+    pub fn into_payload(self) -> Box<dyn Any + Send + 'static> {
+        self.payload
+    }
+
+    /// Resume the unwinding with the original payload.
+    pub fn resume(self) -> ! {
+        panic::resume_unwind(self.payload)
+    }
+
+    /// The panic message, when the payload is a string.
+    pub fn message(&self) -> Option<&str> {
+        match self.payload.downcast_ref::<&'static str>() {
+            Some(message) => Some(message),
+            None => self.payload.downcast_ref::<String>().map(String::as_str),
+        }
+    }
+}
+
+impl fmt::Debug for CallbackPanicked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallbackPanicked").field("message", &self.message()).finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for CallbackPanicked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.message() {
+            Some(message) => write!(f, "application callback panicked: {message}"),
+            None => write!(f, "application callback panicked"),
+        }
+    }
+}
+
+impl Error for CallbackPanicked {}
@@ -0,0 +1,216 @@
+//! Typed senders on top of [`EventLoopProxy::send`].
+//!
+//! The messages are delivered to [`Application::user_message`] as
+//! `Box<dyn Any + Send>`, which should be downcasted back to the sent type.
+//!
+//! [`Application::user_message`]: crate::application::Application::user_message
+
+use std::any::Any;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::event_loop::proxy::{EventLoopProxy, Message};
+
+/// Unbounded sender of the messages of type `M`.
+pub struct Sender<M> {
+    proxy: Arc<dyn EventLoopProxy>,
+    _marker: PhantomData<fn(M)>,
+}
+
+impl<M: Any + Send> Sender<M> {
+    pub fn new(proxy: Arc<dyn EventLoopProxy>) -> Self {
+        Self { proxy, _marker: PhantomData }
+    }
+
+    /// Send the message to the event loop.
+    pub fn send(&self, message: M) {
+        self.proxy.send(Message::new(message));
+    }
+}
+
+impl<M> Clone for Sender<M> {
+    fn clone(&self) -> Self {
+        Self { proxy: self.proxy.clone(), _marker: PhantomData }
+    }
+}
+
+impl<M> fmt::Debug for Sender<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+/// Sender which allows at most `capacity` undelivered messages of type `M`.
+///
+/// The capacity is shared between the clones of the sender.
+pub struct BoundedSender<M> {
+    proxy: Arc<dyn EventLoopProxy>,
+    capacity: usize,
+    pending: Arc<AtomicUsize>,
+    _marker: PhantomData<fn(M)>,
+}
+
+impl<M: Any + Send> BoundedSender<M> {
+    pub fn new(proxy: Arc<dyn EventLoopProxy>, capacity: usize) -> Self {
+        Self { proxy, capacity, pending: Default::default(), _marker: PhantomData }
+    }
+
+    /// Try to send the message to the event loop.
+    ///
+    /// The message is given back when the capacity is reached.
+    pub fn try_send(&self, message: M) -> Result<(), M> {
+        let reserved = self.pending.fetch_update(Ordering::AcqRel, Ordering::Acquire, |pending| {
+            (pending < self.capacity).then_some(pending + 1)
+        });
+
+        if reserved.is_err() {
+            return Err(message);
+        }
+
+        let pending = self.pending.clone();
+        self.proxy.send(Message::from_fn(move || {
+            pending.fetch_sub(1, Ordering::AcqRel);
+            Some(Box::new(message))
+        }));
+
+        Ok(())
+    }
+
+    /// The maximum number of undelivered messages.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl<M> Clone for BoundedSender<M> {
+    fn clone(&self) -> Self {
+        Self {
+            proxy: self.proxy.clone(),
+            capacity: self.capacity,
+            pending: self.pending.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<M> fmt::Debug for BoundedSender<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoundedSender").field("capacity", &self.capacity).finish_non_exhaustive()
+    }
+}
+
+/// Sender which keeps only the latest undelivered message of type `M`.
+///
+/// Useful for state updates, like progress, where only the most recent value
+/// matters. The message is delivered at the position of the first send since
+/// the last delivery.
+pub struct CoalescingSender<M> {
+    proxy: Arc<dyn EventLoopProxy>,
+    slot: Arc<Mutex<Option<M>>>,
+}
+
+impl<M: Any + Send> CoalescingSender<M> {
+    pub fn new(proxy: Arc<dyn EventLoopProxy>) -> Self {
+        Self { proxy, slot: Default::default() }
+    }
+
+    /// Send the message to the event loop replacing the undelivered one.
+    pub fn send(&self, message: M) {
+        if self.slot.lock().unwrap().replace(message).is_some() {
+            return;
+        }
+
+        let slot = self.slot.clone();
+        self.proxy.send(Message::from_fn(move || {
+            let message = slot.lock().unwrap().take()?;
+            Some(Box::new(message))
+        }));
+    }
+}
+
+impl<M> Clone for CoalescingSender<M> {
+    fn clone(&self) -> Self {
+        Self { proxy: self.proxy.clone(), slot: self.slot.clone() }
+    }
+}
+
+impl<M> fmt::Debug for CoalescingSender<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CoalescingSender").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{BoundedSender, CoalescingSender, Sender};
+    use crate::event_loop::proxy::{EventLoopProxy, Message, MessageQueue};
+
+    #[derive(Default)]
+    struct Proxy {
+        queue: MessageQueue,
+    }
+
+    impl EventLoopProxy for Proxy {
+        fn wakeup(&self) {}
+
+        fn send(&self, message: Message) {
+            self.queue.push(message);
+        }
+    }
+
+    fn drain(proxy: &Proxy) -> Vec<u32> {
+        std::iter::from_fn(|| proxy.queue.pop())
+            .map(|message| *message.downcast::<u32>().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn sender_keeps_order() {
+        let proxy = Arc::new(Proxy::default());
+        let sender = Sender::new(proxy.clone());
+        let other = sender.clone();
+
+        sender.send(1u32);
+        other.send(2u32);
+        sender.send(3u32);
+
+        assert_eq!(drain(&proxy), [1, 2, 3]);
+    }
+
+    #[test]
+    fn bounded_sender_rejects_over_capacity() {
+        let proxy = Arc::new(Proxy::default());
+        let sender = BoundedSender::new(proxy.clone(), 2);
+
+        assert_eq!(sender.try_send(1u32), Ok(()));
+        assert_eq!(sender.clone().try_send(2u32), Ok(()));
+        assert_eq!(sender.try_send(3u32), Err(3));
+
+        assert_eq!(drain(&proxy), [1, 2]);
+
+        // Delivery frees the capacity.
+        assert_eq!(sender.try_send(4u32), Ok(()));
+        assert_eq!(drain(&proxy), [4]);
+    }
+
+    #[test]
+    fn coalescing_sender_keeps_latest() {
+        let proxy = Arc::new(Proxy::default());
+        let sender = Sender::new(proxy.clone());
+        let coalescing = CoalescingSender::new(proxy.clone());
+
+        coalescing.send(1u32);
+        sender.send(10u32);
+        coalescing.send(2u32);
+        coalescing.send(3u32);
+
+        assert_eq!(drain(&proxy), [3, 10]);
+
+        coalescing.send(4u32);
+        assert_eq!(drain(&proxy), [4]);
+    }
+}
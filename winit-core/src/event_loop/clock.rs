@@ -0,0 +1,100 @@
+//! The source of time used by the event loop.
+//!
+//! The backends read the time only through the [`Clock`], which makes
+//! [`StartCause`], [`ControlFlow::WaitUntil`] and the timers deterministic
+//! under the [`ManualClock`].
+//!
+//! [`StartCause`]: crate::application::StartCause
+//! [`ControlFlow::WaitUntil`]: crate::event_loop::ControlFlow::WaitUntil
+
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The source of time.
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// The current time.
+    ///
+    /// Must never go backwards.
+    fn now(&self) -> Instant;
+}
+
+/// The clock following the system monotonic clock.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// The clock which only moves when told to.
+///
+/// The event loop still waits for real time, so after advancing the clock
+/// the loop should be pumped with `Some(Duration::ZERO)` to observe the
+/// expired deadlines.
+#[derive(Debug)]
+pub struct ManualClock {
+    start: Instant,
+    elapsed: Mutex<Duration>,
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ManualClock {
+    /// Create the clock stopped at the current time.
+    pub fn new() -> Self {
+        Self { start: Instant::now(), elapsed: Mutex::new(Duration::ZERO) }
+    }
+
+    /// Move the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+
+    /// Move the clock forward to `instant`, does nothing if it's in the past.
+    pub fn advance_to(&self, instant: Instant) {
+        let mut elapsed = self.elapsed.lock().unwrap();
+        *elapsed = (*elapsed).max(instant.saturating_duration_since(self.start));
+    }
+
+    /// The time passed since the clock was created.
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Clock, ManualClock};
+
+    #[test]
+    fn manual_clock() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+        assert_eq!(clock.now(), start);
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(clock.now(), start + Duration::from_secs(1));
+
+        clock.advance_to(start + Duration::from_millis(500));
+        assert_eq!(clock.elapsed(), Duration::from_secs(1));
+
+        clock.advance_to(start + Duration::from_secs(3));
+        assert_eq!(clock.now(), start + Duration::from_secs(3));
+    }
+}
@@ -1,15 +1,22 @@
+use std::process::ExitCode;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use raw_window_handle::HasDisplayHandle;
 use raw_window_handle_05::HasRawDisplayHandle as HasRawDisplayHandle05;
 
-use crate::application::Application;
+use crate::application::{Application, WindowHandler};
+use crate::cursor::{CustomCursor, CustomCursorSource};
+use crate::error::{EventLoopError, RequestError};
 use crate::monitor::{Monitor, MonitorId};
 use crate::window::{Window, WindowAttributes, WindowId};
 
 use self::proxy::EventLoopProxy;
 
+pub mod channel;
+pub mod clock;
 pub mod proxy;
+pub mod pump_events;
 
 /// API to run the event loop.
 pub struct EventLoop {}
@@ -18,32 +25,250 @@ pub trait EventLoopRequests<T>: HasDisplayHandle + HasRawDisplayHandle05 + Sized
 where
     T: Application + 'static,
 {
-    fn new() -> Result<Self, ()>;
+    fn new() -> Result<Self, EventLoopError>;
 
-    /// Run the event loop.
-    fn run(self, state: T);
+    /// Run the event loop until [`EventLoopHandle::exit`] is called.
+    ///
+    /// Returns the code passed to [`EventLoopHandle::exit_with_code`]. Like on
+    /// unix platforms, only the lowest 8 bits of it are preserved.
+    fn run(self, state: T) -> Result<ExitCode, EventLoopError>;
 
     /// Get the proxy to wakeup the event loop.
     fn proxy(&self) -> Arc<dyn EventLoopProxy>;
 }
 
+/// Set through [`EventLoopHandle::set_control_flow`].
+///
+/// Indicates the desired behavior of the event loop after
+/// [`Application::about_to_wait`] is called.
+///
+/// Defaults to [`Wait`].
+///
+/// [`Wait`]: Self::Wait
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ControlFlow {
+    /// When the current loop iteration finishes, immediately begin a new
+    /// iteration regardless of whether or not new events are available to
+    /// process.
+    Poll,
+
+    /// When the current loop iteration finishes, suspend the thread until
+    /// another event arrives.
+    #[default]
+    Wait,
+
+    /// When the current loop iteration finishes, suspend the thread until
+    /// either another event arrives or the given time is reached.
+    ///
+    /// Useful for implementing efficient timers. Applications which want to
+    /// render at the display's native refresh rate should instead use
+    /// [`Poll`] and the VSync functionality of a graphics API to reduce odds
+    /// of missed frames.
+    ///
+    /// [`Poll`]: Self::Poll
+    WaitUntil(Instant),
+}
+
+impl ControlFlow {
+    /// Creates a [`ControlFlow`] that waits until a timeout counted from `now`
+    /// has expired.
+    ///
+    /// Pass [`EventLoopHandle::now`] as `now`, so the deadline follows the
+    /// clock of the event loop.
+    ///
+    /// In most cases, this is set to [`WaitUntil`]. However, if the timeout
+    /// overflows, it is instead set to [`Wait`].
+    ///
+    /// [`WaitUntil`]: Self::WaitUntil
+    /// [`Wait`]: Self::Wait
+    pub fn wait_duration(now: Instant, timeout: Duration) -> Self {
+        match now.checked_add(timeout) {
+            Some(instant) => Self::WaitUntil(instant),
+            None => Self::Wait,
+        }
+    }
+}
+
+/// What the event loop should do once there're no windows left.
+///
+/// Set through [`EventLoopHandle::set_shutdown_policy`].
+///
+/// Defaults to [`KeepRunning`].
+///
+/// [`KeepRunning`]: Self::KeepRunning
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShutdownPolicy {
+    /// Keep running with zero windows until [`EventLoopHandle::exit`] is
+    /// called.
+    #[default]
+    KeepRunning,
+
+    /// Exit with code `0` once the last window is destroyed.
+    ExitOnLastWindowDestroyed,
+}
+
+/// Identifier of the timer created with [`EventLoopHandle::add_timer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimerId(pub u64);
+
+/// When the timer created with [`EventLoopHandle::add_timer`] should fire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Timer {
+    /// Fire once the given time is reached. The timer is removed afterwards.
+    Deadline(Instant),
+
+    /// Fire repeatedly with the given interval, starting one interval from
+    /// now, until the timer is cancelled.
+    ///
+    /// Intervals shorter than [`Timer::MIN_INTERVAL`] are clamped to it.
+    Interval(Duration),
+}
+
+impl Timer {
+    /// The shortest interval of the repeating timer.
+    pub const MIN_INTERVAL: Duration = Duration::from_millis(1);
+}
+
 /// Handle for the event loop.
 pub trait EventLoopHandle: HasDisplayHandle {
     /// Get the proxy to wakeup the event loop.
     fn proxy(&self) -> Arc<dyn EventLoopProxy>;
 
     /// Request to create a window.
-    fn create_window(&mut self, attributes: &WindowAttributes) -> Result<(), ()>;
+    ///
+    /// Returns the id the window is reported with by either
+    /// [`ApplicationWindow::created`], once the display server configures it,
+    /// or [`ApplicationWindow::create_failed`]. Neither happens during the
+    /// callback requesting the window, so it's fine to create windows from
+    /// any callback.
+    ///
+    /// [`ApplicationWindow::created`]: crate::application::ApplicationWindow::created
+    /// [`ApplicationWindow::create_failed`]: crate::application::ApplicationWindow::create_failed
+    fn create_window(&mut self, attributes: &WindowAttributes) -> Result<WindowId, RequestError>;
+
+    /// Request to create a window, delivering its callbacks to `handler`
+    /// instead of the application.
+    ///
+    /// The handler lives as long as the window, and is dropped after
+    /// [`ApplicationWindow::destroyed`] or
+    /// [`ApplicationWindow::create_failed`] is delivered to it.
+    ///
+    /// [`ApplicationWindow::destroyed`]: crate::application::ApplicationWindow::destroyed
+    /// [`ApplicationWindow::create_failed`]: crate::application::ApplicationWindow::create_failed
+    fn create_window_with_handler(
+        &mut self,
+        attributes: &WindowAttributes,
+        handler: Box<dyn WindowHandler>,
+    ) -> Result<WindowId, RequestError>;
+
+    /// Request to destroy the window.
+    ///
+    /// The window is gone right away: [`get_window`] returns `None` for it,
+    /// and no more callbacks are delivered for it, including the ones already
+    /// pending. The window is dropped and [`ApplicationWindow::destroyed`] is
+    /// called once the callback requesting the destruction returns, thus it's
+    /// fine to destroy the window whose callback is running.
+    ///
+    /// The windows not yet created are destroyed silently.
+    ///
+    /// [`get_window`]: Self::get_window
+    /// [`ApplicationWindow::destroyed`]: crate::application::ApplicationWindow::destroyed
+    fn destroy_window(&mut self, window_id: WindowId);
 
     fn num_windows(&self) -> usize;
 
+    /// Get the created window.
+    ///
+    /// Returns `None` for the window not yet reported with
+    /// [`ApplicationWindow::created`], see [`get_pending_window`].
+    ///
+    /// [`ApplicationWindow::created`]: crate::application::ApplicationWindow::created
+    /// [`get_pending_window`]: Self::get_pending_window
     fn get_window(&self, window_id: WindowId) -> Option<&dyn Window>;
 
     fn get_window_mut(&mut self, window_id: WindowId) -> Option<&mut dyn Window>;
 
+    /// Get the window, including the one requested, but not yet created.
+    ///
+    /// Use [`Window::is_configured`] to tell them apart.
+    fn get_pending_window(&self, window_id: WindowId) -> Option<&dyn Window>;
+
+    fn get_pending_window_mut(&mut self, window_id: WindowId) -> Option<&mut dyn Window>;
+
     fn get_monitor(&self, monitor_id: MonitorId) -> Option<&dyn Monitor>;
 
     fn monitors(&self) -> Vec<&dyn Monitor>;
 
-    fn exit(&mut self);
+    /// Register the custom cursor to show with [`Window::set_cursor`].
+    ///
+    /// The cursor is registered once and could be used by any window of the
+    /// event loop, the images are uploaded to the display server lazily.
+    fn create_custom_cursor(&mut self, source: CustomCursorSource) -> CustomCursor;
+
+    /// Set the [`ControlFlow`] the event loop will use once the current
+    /// iteration finishes.
+    fn set_control_flow(&mut self, control_flow: ControlFlow);
+
+    /// Get the current [`ControlFlow`].
+    fn control_flow(&self) -> ControlFlow;
+
+    /// Add the timer which will result in [`Application::timer_fired`] with
+    /// the returned id.
+    fn add_timer(&mut self, timer: Timer) -> TimerId;
+
+    /// Cancel the timer, so it won't fire anymore.
+    ///
+    /// Cancelling already fired [`Timer::Deadline`] does nothing.
+    fn cancel_timer(&mut self, timer_id: TimerId);
+
+    /// Set the [`ShutdownPolicy`] of the event loop.
+    fn set_shutdown_policy(&mut self, policy: ShutdownPolicy);
+
+    /// Get the current [`ShutdownPolicy`].
+    fn shutdown_policy(&self) -> ShutdownPolicy;
+
+    /// The current time according to the [`Clock`] of the event loop.
+    ///
+    /// Should be preferred over [`Instant::now`] when computing deadlines
+    /// for [`ControlFlow::WaitUntil`] and [`Timer::Deadline`], so they
+    /// follow the clock the loop uses.
+    ///
+    /// [`Clock`]: self::clock::Clock
+    fn now(&self) -> Instant;
+
+    /// Request the event loop to exit with code `0`.
+    ///
+    /// See [`EventLoopHandle::exit_with_code`] for details.
+    fn exit(&mut self) {
+        self.exit_with_code(0);
+    }
+
+    /// Request the event loop to exit with the given `code` once the current
+    /// iteration finishes.
+    ///
+    /// [`Application::loop_exiting`] is called while the windows are still
+    /// alive, they're destroyed right after it. When called multiple times,
+    /// the last code is used.
+    fn exit_with_code(&mut self, code: i32);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::clock::{Clock, ManualClock};
+    use super::ControlFlow;
+
+    #[test]
+    fn wait_duration() {
+        let clock = ManualClock::new();
+        clock.advance(Duration::from_secs(5));
+        let now = clock.now();
+        assert_eq!(
+            ControlFlow::wait_duration(now, Duration::from_secs(1)),
+            ControlFlow::WaitUntil(now + Duration::from_secs(1))
+        );
+
+        assert_eq!(ControlFlow::wait_duration(now, Duration::MAX), ControlFlow::Wait);
+    }
 }
@@ -1,5 +1,97 @@
+use std::any::Any;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Mutex;
+
 /// Proxy to wake up the event loop from non-main thread.
 pub trait EventLoopProxy: Send + Sync {
     /// Wakeup the event loop.
     fn wakeup(&self);
+
+    /// Send the message to the event loop.
+    ///
+    /// Messages are delivered to [`Application::user_message`] in the order
+    /// they were sent. Unlike [`EventLoopProxy::wakeup`], they're never
+    /// squashed, however sending a message doesn't result in
+    /// [`Application::user_wakeup`].
+    ///
+    /// For typed messages see [`Sender`].
+    ///
+    /// [`Application::user_message`]: crate::application::Application::user_message
+    /// [`Application::user_wakeup`]: crate::application::Application::user_wakeup
+    /// [`Sender`]: crate::event_loop::channel::Sender
+    fn send(&self, message: Message);
+}
+
+type Thunk = Box<dyn FnOnce() -> Option<Box<dyn Any + Send>> + Send>;
+
+/// The message sent to the event loop with [`EventLoopProxy::send`].
+pub struct Message {
+    thunk: Thunk,
+}
+
+impl Message {
+    /// Wrap the value to send it to the event loop.
+    pub fn new<M: Any + Send>(message: M) -> Self {
+        Self { thunk: Box::new(move || Some(Box::new(message) as Box<dyn Any + Send>)) }
+    }
+
+    /// Create the message which is resolved once the event loop delivers it.
+    ///
+    /// The message is skipped when `f` returns `None`.
+    pub fn from_fn<F>(f: F) -> Self
+    where
+        F: FnOnce() -> Option<Box<dyn Any + Send>> + Send + 'static,
+    {
+        Self { thunk: Box::new(f) }
+    }
+
+    /// Resolve the message into the value the user has sent.
+    pub fn into_inner(self) -> Option<Box<dyn Any + Send>> {
+        (self.thunk)()
+    }
+}
+
+impl From<Box<dyn Any + Send>> for Message {
+    fn from(message: Box<dyn Any + Send>) -> Self {
+        Self { thunk: Box::new(move || Some(message)) }
+    }
+}
+
+impl fmt::Debug for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Message").finish_non_exhaustive()
+    }
+}
+
+/// The queue of the messages sent to the event loop.
+///
+/// Backends keep it next to their [`EventLoopProxy`] and drain it once the
+/// loop is woken up.
+#[derive(Debug, Default)]
+pub struct MessageQueue {
+    messages: Mutex<VecDeque<Message>>,
+}
+
+impl MessageQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the message to the end of the queue.
+    pub fn push(&self, message: Message) {
+        self.messages.lock().unwrap().push_back(message);
+    }
+
+    /// Take the next message to deliver to the user.
+    pub fn pop(&self) -> Option<Box<dyn Any + Send>> {
+        loop {
+            // NOTE: don't hold the lock while resolving the message, since it may send
+            // messages on its own.
+            let message = self.messages.lock().unwrap().pop_front()?;
+            if let Some(message) = message.into_inner() {
+                return Some(message);
+            }
+        }
+    }
 }
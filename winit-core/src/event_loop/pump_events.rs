@@ -0,0 +1,67 @@
+use std::process::ExitCode;
+use std::time::Duration;
+
+use crate::application::Application;
+use crate::error::EventLoopError;
+use crate::event_loop::EventLoopRequests;
+
+/// The status of the event loop after [`EventLoopPumpEvents::pump_events`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PumpStatus {
+    /// The event loop is still running and the application should keep
+    /// pumping it.
+    Continue,
+
+    /// The event loop has exited with the given code,
+    /// [`Application::loop_exiting`] was already called.
+    Exit(i32),
+}
+
+/// Drive the event loop from the application instead of giving up control
+/// of the thread to [`EventLoopRequests::run`].
+pub trait EventLoopPumpEvents<T>: EventLoopRequests<T>
+where
+    T: Application + 'static,
+{
+    /// Run a single iteration of the event loop.
+    ///
+    /// The first call after creating the loop, or after it exited, sends
+    /// [`StartCause::Init`]. Every call then waits for new events for at most
+    /// `timeout`, with the [`ControlFlow`] shortening the wait further, and
+    /// ends with [`Application::about_to_wait`].
+    ///
+    /// Passing `Some(Duration::ZERO)` never blocks, which is what should be
+    /// used when the loop is driven from an external reactor.
+    ///
+    /// Once [`EventLoopHandle::exit`] is called, [`Application::loop_exiting`]
+    /// is sent and [`PumpStatus::Exit`] is returned. Pumping the loop after
+    /// that starts it from the beginning.
+    ///
+    /// An error is returned when the loop can't continue, for example due to
+    /// the connection to the display server being lost. The loop is shut down
+    /// the same way as on exit, so [`Application::loop_exiting`] is still
+    /// called.
+    ///
+    /// [`StartCause::Init`]: crate::application::StartCause::Init
+    /// [`ControlFlow`]: crate::event_loop::ControlFlow
+    /// [`EventLoopHandle::exit`]: crate::event_loop::EventLoopHandle::exit
+    fn pump_events(
+        &mut self,
+        timeout: Option<Duration>,
+        state: &mut T,
+    ) -> Result<PumpStatus, EventLoopError>;
+
+    /// Run the event loop until [`EventLoopHandle::exit`] is called.
+    ///
+    /// Unlike [`EventLoopRequests::run`] the loop is not consumed, so this
+    /// could be called repeatedly.
+    ///
+    /// [`EventLoopHandle::exit`]: crate::event_loop::EventLoopHandle::exit
+    fn run_on_demand(&mut self, state: &mut T) -> Result<ExitCode, EventLoopError> {
+        loop {
+            if let PumpStatus::Exit(code) = self.pump_events(None, state)? {
+                return Ok(ExitCode::from(code as u8));
+            }
+        }
+    }
+}
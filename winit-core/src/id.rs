@@ -0,0 +1,83 @@
+//! Allocation of the window, monitor and custom cursor ids.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::cursor::CustomCursor;
+use crate::monitor::MonitorId;
+use crate::window::WindowId;
+
+/// The source of the generations, one per [`IdGenerator`].
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// The generator of the [`WindowId`], [`MonitorId`] and [`CustomCursor`].
+///
+/// Each generator is tagged with its own generation, which forms the upper
+/// half of the ids it hands out, while the lower half comes from a
+/// monotonically increasing counter. Thus the ids are never reused for the
+/// lifetime of the process, even by the different event loops.
+#[derive(Debug)]
+pub struct IdGenerator {
+    generation: u64,
+    next: u64,
+}
+
+impl IdGenerator {
+    pub fn new() -> Self {
+        Self { generation: NEXT_GENERATION.fetch_add(1, Ordering::Relaxed), next: 0 }
+    }
+
+    /// The id for the new window.
+    pub fn next_window_id(&mut self) -> WindowId {
+        WindowId(self.next())
+    }
+
+    /// The id for the new monitor.
+    pub fn next_monitor_id(&mut self) -> MonitorId {
+        MonitorId(self.next())
+    }
+
+    /// The id for the new custom cursor.
+    pub fn next_custom_cursor_id(&mut self) -> CustomCursor {
+        CustomCursor(self.next())
+    }
+
+    fn next(&mut self) -> u128 {
+        let id = (self.generation as u128) << 64 | self.next as u128;
+        self.next = self.next.checked_add(1).expect("ran out of ids");
+        id
+    }
+}
+
+impl Default for IdGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::IdGenerator;
+
+    #[test]
+    fn unique_ids() {
+        let mut first = IdGenerator::new();
+        let mut second = IdGenerator::new();
+
+        let mut ids = HashSet::new();
+        for _ in 0..100 {
+            assert!(ids.insert(first.next_window_id().0));
+            assert!(ids.insert(first.next_monitor_id().0));
+            assert!(ids.insert(second.next_window_id().0));
+            assert!(ids.insert(second.next_monitor_id().0));
+            assert!(ids.insert(second.next_custom_cursor_id().0));
+        }
+
+        // The generators created later don't take the ids of the previous ones.
+        let mut third = IdGenerator::new();
+        for _ in 0..100 {
+            assert!(ids.insert(third.next_window_id().0));
+        }
+    }
+}
@@ -1,9 +1,16 @@
 //! The next generation of the winit.
 
 pub mod application;
+pub mod cursor;
 pub mod dpi;
+pub mod error;
 pub mod event_loop;
+pub mod id;
 pub mod monitor;
 pub mod input;
+pub mod record;
 pub mod view;
 pub mod window;
+
+#[cfg(feature = "test-support")]
+pub mod test_support;
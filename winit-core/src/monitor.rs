@@ -1,5 +1,12 @@
 use crate::dpi::{PhysicalPosition, PhysicalSize};
 
+/// The identifier of the monitor.
+///
+/// The ids are unique for the lifetime of the process, the monitor which
+/// was unplugged and plugged back gets a new id. The backends allocate them
+/// with [`IdGenerator`].
+///
+/// [`IdGenerator`]: crate::id::IdGenerator
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct MonitorId(pub u128);
 
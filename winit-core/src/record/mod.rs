@@ -0,0 +1,750 @@
+//! Record the callbacks of the application and replay them later.
+//!
+//! [`Recorder`] wraps the application and writes every callback it forwards
+//! to a trace, one line per callback:
+//!
+//! ```text
+//! winit-trace 1
+//! 0.000012000 new_events init
+//! 0.004501000 created 42
+//! 0.004503000 resized 42 800 600
+//! ```
+//!
+//! The first column is the time since the recording has started. The trace
+//! is read back with [`Trace::parse`] and fed into the application with
+//! [`Replay`], no backend involved.
+
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::application::{Application, ApplicationWindow, DeviceEventsHandler, StartCause};
+use crate::dpi::PhysicalSize;
+use crate::error::RequestError;
+use crate::event_loop::clock::{Clock, SystemClock};
+use crate::event_loop::{EventLoopHandle, TimerId};
+use crate::input::touch::TouchInputHandler;
+use crate::window::WindowId;
+
+mod replay;
+
+pub use self::replay::{Replay, ReplayHandle, ReplayWindow};
+
+/// The first line of every trace.
+const HEADER: &str = "winit-trace 1";
+
+/// A callback of [`Application`] or [`ApplicationWindow`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Callback {
+    NewEvents(StartCause),
+    UserWakeup,
+    /// The message itself can't be recorded, it's replayed as `()`.
+    UserMessage,
+    TimerFired(TimerId),
+    AboutToWait,
+    LoopExiting,
+    Created(WindowId),
+    /// The error itself can't be recorded, it's replayed as [`OsError`].
+    ///
+    /// [`OsError`]: crate::error::OsError
+    CreateFailed(WindowId),
+    Resized(WindowId, PhysicalSize<u32>),
+    ScaleFactorChanged(WindowId, f64),
+    RedrawRequested(WindowId),
+    /// The request to close, along with the answer of the application.
+    CloseRequested(WindowId, bool),
+    Focused(WindowId, bool),
+    Occluded(WindowId, bool),
+    Destroyed(WindowId),
+    TouchDown,
+    TouchUp,
+    MouseMotion((f64, f64)),
+}
+
+impl Callback {
+    /// The window the callback is for.
+    pub fn window_id(&self) -> Option<WindowId> {
+        match *self {
+            Self::Created(window_id)
+            | Self::CreateFailed(window_id)
+            | Self::Resized(window_id, _)
+            | Self::ScaleFactorChanged(window_id, _)
+            | Self::RedrawRequested(window_id)
+            | Self::CloseRequested(window_id, _)
+            | Self::Focused(window_id, _)
+            | Self::Occluded(window_id, _)
+            | Self::Destroyed(window_id) => Some(window_id),
+            _ => None,
+        }
+    }
+}
+
+/// The callback along with the time it was received at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    /// The time since the start of the recording.
+    pub timestamp: Duration,
+
+    pub callback: Callback,
+}
+
+impl Record {
+    /// Write the record as a line of the trace, with the instants relative to
+    /// `start`.
+    fn write<W: Write>(&self, writer: &mut W, start: Instant) -> io::Result<()> {
+        let instant = |instant: Instant| Seconds(instant.saturating_duration_since(start));
+        write!(writer, "{}", Seconds(self.timestamp))?;
+        match self.callback {
+            Callback::NewEvents(StartCause::Init) => write!(writer, " new_events init"),
+            Callback::NewEvents(StartCause::Poll) => write!(writer, " new_events poll"),
+            Callback::NewEvents(StartCause::ResumeTimeReached { start, requested_resume }) => {
+                write!(
+                    writer,
+                    " new_events resume_time_reached {} {}",
+                    instant(start),
+                    instant(requested_resume)
+                )
+            },
+            Callback::NewEvents(StartCause::WaitCancelled { start, requested_resume }) => {
+                write!(writer, " new_events wait_cancelled {}", instant(start))?;
+                match requested_resume {
+                    Some(requested_resume) => write!(writer, " {}", instant(requested_resume)),
+                    None => write!(writer, " -"),
+                }
+            },
+            Callback::UserWakeup => write!(writer, " user_wakeup"),
+            Callback::UserMessage => write!(writer, " user_message"),
+            Callback::TimerFired(TimerId(timer_id)) => write!(writer, " timer_fired {timer_id}"),
+            Callback::AboutToWait => write!(writer, " about_to_wait"),
+            Callback::LoopExiting => write!(writer, " loop_exiting"),
+            Callback::Created(WindowId(window_id)) => write!(writer, " created {window_id}"),
+            Callback::CreateFailed(WindowId(window_id)) => {
+                write!(writer, " create_failed {window_id}")
+            },
+            Callback::Resized(WindowId(window_id), size) => {
+                write!(writer, " resized {window_id} {} {}", size.width, size.height)
+            },
+            Callback::ScaleFactorChanged(WindowId(window_id), scale_factor) => {
+                write!(writer, " scale_factor_changed {window_id} {scale_factor}")
+            },
+            Callback::RedrawRequested(WindowId(window_id)) => {
+                write!(writer, " redraw_requested {window_id}")
+            },
+            Callback::CloseRequested(WindowId(window_id), accepted) => {
+                write!(writer, " close_requested {window_id} {accepted}")
+            },
+            Callback::Focused(WindowId(window_id), focused) => {
+                write!(writer, " focused {window_id} {focused}")
+            },
+            Callback::Occluded(WindowId(window_id), occluded) => {
+                write!(writer, " occluded {window_id} {occluded}")
+            },
+            Callback::Destroyed(WindowId(window_id)) => write!(writer, " destroyed {window_id}"),
+            Callback::TouchDown => write!(writer, " touch_down"),
+            Callback::TouchUp => write!(writer, " touch_up"),
+            Callback::MouseMotion((dx, dy)) => write!(writer, " mouse_motion {dx} {dy}"),
+        }?;
+        writeln!(writer)
+    }
+
+    /// Parse the line of the trace, with the instants relative to `start`.
+    fn parse(line: &str, start: Instant) -> Result<Self, String> {
+        let mut fields = Fields { fields: line.split_ascii_whitespace().peekable(), start };
+        let timestamp = fields.duration()?;
+        let callback = match fields.next()? {
+            "new_events" => Callback::NewEvents(match fields.next()? {
+                "init" => StartCause::Init,
+                "poll" => StartCause::Poll,
+                "resume_time_reached" => StartCause::ResumeTimeReached {
+                    start: fields.instant()?,
+                    requested_resume: fields.instant()?,
+                },
+                "wait_cancelled" => StartCause::WaitCancelled {
+                    start: fields.instant()?,
+                    requested_resume: match fields.peek() {
+                        Some("-") => {
+                            fields.next()?;
+                            None
+                        },
+                        _ => Some(fields.instant()?),
+                    },
+                },
+                cause => return Err(format!("unknown start cause `{cause}`")),
+            }),
+            "user_wakeup" => Callback::UserWakeup,
+            "user_message" => Callback::UserMessage,
+            "timer_fired" => Callback::TimerFired(TimerId(fields.parse()?)),
+            "about_to_wait" => Callback::AboutToWait,
+            "loop_exiting" => Callback::LoopExiting,
+            "created" => Callback::Created(fields.window_id()?),
+            "create_failed" => Callback::CreateFailed(fields.window_id()?),
+            "resized" => {
+                let window_id = fields.window_id()?;
+                Callback::Resized(window_id, PhysicalSize::new(fields.parse()?, fields.parse()?))
+            },
+            "scale_factor_changed" => {
+                Callback::ScaleFactorChanged(fields.window_id()?, fields.parse()?)
+            },
+            "redraw_requested" => Callback::RedrawRequested(fields.window_id()?),
+            "close_requested" => Callback::CloseRequested(fields.window_id()?, fields.parse()?),
+            "focused" => Callback::Focused(fields.window_id()?, fields.parse()?),
+            "occluded" => Callback::Occluded(fields.window_id()?, fields.parse()?),
+            "destroyed" => Callback::Destroyed(fields.window_id()?),
+            "touch_down" => Callback::TouchDown,
+            "touch_up" => Callback::TouchUp,
+            "mouse_motion" => Callback::MouseMotion((fields.parse()?, fields.parse()?)),
+            callback => return Err(format!("unknown callback `{callback}`")),
+        };
+
+        match fields.peek() {
+            Some(field) => Err(format!("unexpected `{field}`")),
+            None => Ok(Self { timestamp, callback }),
+        }
+    }
+}
+
+/// The duration written as seconds with nanosecond precision.
+struct Seconds(Duration);
+
+impl fmt::Display for Seconds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:09}", self.0.as_secs(), self.0.subsec_nanos())
+    }
+}
+
+/// The fields of the line in the trace.
+struct Fields<'a> {
+    fields: std::iter::Peekable<std::str::SplitAsciiWhitespace<'a>>,
+    start: Instant,
+}
+
+impl<'a> Fields<'a> {
+    fn peek(&mut self) -> Option<&'a str> {
+        self.fields.peek().copied()
+    }
+
+    fn next(&mut self) -> Result<&'a str, String> {
+        self.fields.next().ok_or_else(|| String::from("unexpected end of line"))
+    }
+
+    fn parse<F: FromStr>(&mut self) -> Result<F, String> {
+        let field = self.next()?;
+        field.parse().map_err(|_| format!("invalid value `{field}`"))
+    }
+
+    fn duration(&mut self) -> Result<Duration, String> {
+        let field = self.next()?;
+        let invalid = || format!("invalid duration `{field}`");
+        let (secs, nanos) = field.split_once('.').ok_or_else(invalid)?;
+        // Always written with all the 9 digits, `0.5` is not 5ns.
+        if nanos.len() != 9 || !nanos.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(invalid());
+        }
+
+        let secs = secs.parse().map_err(|_| invalid())?;
+        let nanos = nanos.parse().map_err(|_| invalid())?;
+        Ok(Duration::new(secs, nanos))
+    }
+
+    fn instant(&mut self) -> Result<Instant, String> {
+        Ok(self.start + self.duration()?)
+    }
+
+    fn window_id(&mut self) -> Result<WindowId, String> {
+        Ok(WindowId(self.parse()?))
+    }
+}
+
+/// The recorded callbacks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace {
+    /// The instant the recording has started at.
+    ///
+    /// When parsing, the current instant is used, so the instants in
+    /// [`StartCause`] keep their distance from it.
+    pub start: Instant,
+
+    pub records: Vec<Record>,
+}
+
+impl Trace {
+    /// Read the trace written by [`Recorder`].
+    pub fn parse<R: BufRead>(reader: R) -> Result<Self, TraceError> {
+        let start = Instant::now();
+        let mut lines = reader.lines();
+        match lines.next().transpose()? {
+            Some(header) if header == HEADER => (),
+            _ => {
+                return Err(TraceError::Parse { line: 1, message: String::from("missing header") })
+            },
+        }
+
+        let mut records = Vec::new();
+        for (index, line) in lines.enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let record = Record::parse(&line, start)
+                .map_err(|message| TraceError::Parse { line: index + 2, message })?;
+            records.push(record);
+        }
+
+        Ok(Self { start, records })
+    }
+
+    /// Read the trace from the file at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, TraceError> {
+        Self::parse(BufReader::new(File::open(path)?))
+    }
+}
+
+/// The error which happened when reading the [`Trace`].
+#[derive(Debug)]
+pub enum TraceError {
+    /// Failed to read the trace.
+    Io(io::Error),
+
+    /// The trace is malformed.
+    Parse {
+        /// The line number, starting from `1`.
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read the trace: {err}"),
+            Self::Parse { line, message } => write!(f, "malformed trace at line {line}: {message}"),
+        }
+    }
+}
+
+impl Error for TraceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for TraceError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// The application wrapper writing every callback to the trace before
+/// forwarding it.
+///
+/// Writing never interrupts the application, the first error stops the
+/// recording and is returned from [`Recorder::finish`].
+///
+/// The callbacks of the windows created with
+/// [`EventLoopHandle::create_window_with_handler`] go straight to their
+/// handlers, so they aren't recorded.
+pub struct Recorder<A, W: Write> {
+    app: A,
+    writer: W,
+    clock: Arc<dyn Clock>,
+    start: Instant,
+    error: Option<io::Error>,
+}
+
+impl<A: Application> Recorder<A, BufWriter<File>> {
+    /// Record the callbacks of `app` into the file at `path`.
+    pub fn create<P: AsRef<Path>>(app: A, path: P) -> io::Result<Self> {
+        Self::new(app, BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<A: Application, W: Write> Recorder<A, W> {
+    /// Record the callbacks of `app` into `writer`.
+    pub fn new(app: A, writer: W) -> io::Result<Self> {
+        Self::with_clock(app, writer, Arc::new(SystemClock))
+    }
+
+    /// Record the callbacks of `app` into `writer`, taking the timestamps
+    /// from `clock`.
+    ///
+    /// Should be the clock used by the event loop.
+    pub fn with_clock(app: A, mut writer: W, clock: Arc<dyn Clock>) -> io::Result<Self> {
+        writeln!(writer, "{HEADER}")?;
+        let start = clock.now();
+        Ok(Self { app, writer, clock, start, error: None })
+    }
+
+    /// The wrapped application.
+    pub fn app(&self) -> &A {
+        &self.app
+    }
+
+    /// The wrapped application.
+    pub fn app_mut(&mut self) -> &mut A {
+        &mut self.app
+    }
+
+    /// Flush the trace, returning the application and the writer.
+    pub fn finish(mut self) -> io::Result<(A, W)> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+
+        self.writer.flush()?;
+        Ok((self.app, self.writer))
+    }
+
+    fn record(&mut self, callback: Callback) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let timestamp = self.clock.now().saturating_duration_since(self.start);
+        let record = Record { timestamp, callback };
+        if let Err(err) = record.write(&mut self.writer, self.start) {
+            self.error = Some(err);
+        }
+    }
+}
+
+impl<A: Application, W: Write> Application for Recorder<A, W> {
+    fn user_wakeup(&mut self, loop_handle: &mut dyn EventLoopHandle) {
+        self.record(Callback::UserWakeup);
+        self.app.user_wakeup(loop_handle);
+    }
+
+    fn user_message(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        message: Box<dyn Any + Send>,
+    ) {
+        self.record(Callback::UserMessage);
+        self.app.user_message(loop_handle, message);
+    }
+
+    fn timer_fired(&mut self, loop_handle: &mut dyn EventLoopHandle, timer_id: TimerId) {
+        self.record(Callback::TimerFired(timer_id));
+        self.app.timer_fired(loop_handle, timer_id);
+    }
+
+    fn new_events(&mut self, loop_handle: &mut dyn EventLoopHandle, start_cause: StartCause) {
+        self.record(Callback::NewEvents(start_cause));
+        self.app.new_events(loop_handle, start_cause);
+    }
+
+    fn about_to_wait(&mut self, loop_handle: &mut dyn EventLoopHandle) {
+        self.record(Callback::AboutToWait);
+        self.app.about_to_wait(loop_handle);
+    }
+
+    fn loop_exiting(&mut self, loop_handle: &mut dyn EventLoopHandle) {
+        self.record(Callback::LoopExiting);
+        self.app.loop_exiting(loop_handle);
+        if let Err(err) = self.writer.flush() {
+            self.error.get_or_insert(err);
+        }
+    }
+
+    fn touch_handler(&mut self) -> Option<&mut dyn TouchInputHandler> {
+        match self.app.touch_handler() {
+            Some(_) => Some(self),
+            None => None,
+        }
+    }
+
+    fn device_events_handelr(&mut self) -> Option<&mut dyn DeviceEventsHandler> {
+        match self.app.device_events_handelr() {
+            Some(_) => Some(self),
+            None => None,
+        }
+    }
+}
+
+impl<A: Application, W: Write> ApplicationWindow for Recorder<A, W> {
+    fn created(&mut self, loop_handle: &mut dyn EventLoopHandle, window_id: WindowId) {
+        self.record(Callback::Created(window_id));
+        self.app.created(loop_handle, window_id);
+    }
+
+    fn create_failed(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        window_id: WindowId,
+        error: RequestError,
+    ) {
+        self.record(Callback::CreateFailed(window_id));
+        self.app.create_failed(loop_handle, window_id, error);
+    }
+
+    fn resized(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        window_id: WindowId,
+        size: PhysicalSize<u32>,
+    ) {
+        self.record(Callback::Resized(window_id, size));
+        self.app.resized(loop_handle, window_id, size);
+    }
+
+    fn scale_factor_changed(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        window_id: WindowId,
+        scale_factor: f64,
+    ) {
+        self.record(Callback::ScaleFactorChanged(window_id, scale_factor));
+        self.app.scale_factor_changed(loop_handle, window_id, scale_factor);
+    }
+
+    fn redraw_requested(&mut self, loop_handle: &mut dyn EventLoopHandle, window_id: WindowId) {
+        self.record(Callback::RedrawRequested(window_id));
+        self.app.redraw_requested(loop_handle, window_id);
+    }
+
+    fn close_requested(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        window_id: WindowId,
+    ) -> bool {
+        // The answer is only known after forwarding.
+        let accepted = self.app.close_requested(loop_handle, window_id);
+        self.record(Callback::CloseRequested(window_id, accepted));
+        accepted
+    }
+
+    fn focused(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        window_id: WindowId,
+        focused: bool,
+    ) {
+        self.record(Callback::Focused(window_id, focused));
+        self.app.focused(loop_handle, window_id, focused);
+    }
+
+    fn occluded(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        window_id: WindowId,
+        occluded: bool,
+    ) {
+        self.record(Callback::Occluded(window_id, occluded));
+        self.app.occluded(loop_handle, window_id, occluded);
+    }
+
+    fn destroyed(&mut self, loop_handle: &mut dyn EventLoopHandle, window_id: WindowId) {
+        self.record(Callback::Destroyed(window_id));
+        self.app.destroyed(loop_handle, window_id);
+    }
+}
+
+impl<A: Application, W: Write> TouchInputHandler for Recorder<A, W> {
+    fn touch_down(&mut self) {
+        self.record(Callback::TouchDown);
+        if let Some(handler) = self.app.touch_handler() {
+            handler.touch_down();
+        }
+    }
+
+    fn touch_up(&mut self) {
+        self.record(Callback::TouchUp);
+        if let Some(handler) = self.app.touch_handler() {
+            handler.touch_up();
+        }
+    }
+}
+
+impl<A: Application, W: Write> DeviceEventsHandler for Recorder<A, W> {
+    fn mouse_motion(&mut self, loop_handle: &mut dyn EventLoopHandle, delta: (f64, f64)) {
+        self.record(Callback::MouseMotion(delta));
+        if let Some(handler) = self.app.device_events_handelr() {
+            handler.mouse_motion(loop_handle, delta);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::time::{Duration, Instant};
+
+    use crate::application::{Application, ApplicationWindow, DeviceEventsHandler, StartCause};
+    use crate::dpi::PhysicalSize;
+    use crate::event_loop::{EventLoopHandle, TimerId};
+    use crate::window::{WindowAttributes, WindowId};
+
+    use super::{Callback, Record, Recorder, Replay, Trace, TraceError};
+
+    /// Creates a window on init and closes it when asked.
+    #[derive(Default)]
+    struct App {
+        window_id: Option<WindowId>,
+        sizes: Vec<PhysicalSize<u32>>,
+        motions: Vec<(f64, f64)>,
+        exited: bool,
+    }
+
+    impl Application for App {
+        fn user_wakeup(&mut self, _: &mut dyn EventLoopHandle) {}
+
+        fn new_events(&mut self, loop_handle: &mut dyn EventLoopHandle, start_cause: StartCause) {
+            if start_cause == StartCause::Init {
+                let attributes = WindowAttributes::default().with_title("Recorded");
+                self.window_id = Some(loop_handle.create_window(&attributes).unwrap());
+            }
+        }
+
+        fn about_to_wait(&mut self, _: &mut dyn EventLoopHandle) {}
+
+        fn loop_exiting(&mut self, _: &mut dyn EventLoopHandle) {
+            self.exited = true;
+        }
+
+        fn device_events_handelr(&mut self) -> Option<&mut dyn DeviceEventsHandler> {
+            Some(self)
+        }
+    }
+
+    impl DeviceEventsHandler for App {
+        fn mouse_motion(&mut self, _: &mut dyn EventLoopHandle, delta: (f64, f64)) {
+            self.motions.push(delta);
+        }
+    }
+
+    impl ApplicationWindow for App {
+        fn created(&mut self, loop_handle: &mut dyn EventLoopHandle, window_id: WindowId) {
+            assert_eq!(self.window_id, Some(window_id));
+            assert_eq!(loop_handle.get_window(window_id).unwrap().title(), "Recorded");
+        }
+
+        fn resized(
+            &mut self,
+            loop_handle: &mut dyn EventLoopHandle,
+            window_id: WindowId,
+            size: PhysicalSize<u32>,
+        ) {
+            assert_eq!(loop_handle.get_window(window_id).unwrap().inner_size(), size);
+            self.sizes.push(size);
+        }
+
+        fn scale_factor_changed(&mut self, _: &mut dyn EventLoopHandle, _: WindowId, _: f64) {}
+
+        fn redraw_requested(&mut self, _: &mut dyn EventLoopHandle, _: WindowId) {}
+
+        fn close_requested(&mut self, loop_handle: &mut dyn EventLoopHandle, _: WindowId) -> bool {
+            loop_handle.exit();
+            true
+        }
+    }
+
+    fn trace(callbacks: Vec<Callback>) -> Trace {
+        let records = callbacks
+            .into_iter()
+            .enumerate()
+            .map(|(index, callback)| Record {
+                timestamp: Duration::from_millis(index as u64),
+                callback,
+            })
+            .collect();
+        Trace { start: Instant::now(), records }
+    }
+
+    #[test]
+    fn record_replay() {
+        let window_id = WindowId(42);
+        let callbacks = vec![
+            Callback::NewEvents(StartCause::Init),
+            Callback::Created(window_id),
+            Callback::ScaleFactorChanged(window_id, 1.5),
+            Callback::Resized(window_id, PhysicalSize::new(800, 600)),
+            Callback::RedrawRequested(window_id),
+            Callback::AboutToWait,
+            Callback::NewEvents(StartCause::Poll),
+            Callback::TimerFired(TimerId(3)),
+            Callback::Focused(window_id, true),
+            Callback::MouseMotion((1.5, -2.)),
+            Callback::CloseRequested(window_id, true),
+            Callback::Destroyed(window_id),
+            Callback::AboutToWait,
+            Callback::LoopExiting,
+        ];
+
+        // Record the replayed trace, so the recording could be compared with it.
+        let mut recorder = Recorder::new(App::default(), Vec::new()).unwrap();
+        let mut replay = Replay::new(trace(callbacks.clone()));
+        replay.run(&mut recorder);
+        assert_eq!(replay.handle().exit_code(), Some(0));
+
+        let (app, trace) = recorder.finish().unwrap();
+        assert!(app.exited);
+        assert_eq!(app.sizes, [PhysicalSize::new(800, 600)]);
+        assert_eq!(app.motions, [(1.5, -2.)]);
+
+        let trace = Trace::parse(Cursor::new(trace)).unwrap();
+        let recorded: Vec<_> = trace.records.into_iter().map(|record| record.callback).collect();
+        assert_eq!(recorded, callbacks);
+    }
+
+    #[test]
+    fn start_cause_instants() {
+        let trace = "winit-trace 1\n0.500000000 new_events wait_cancelled 0.250000000 \
+                     -\n1.000000000 new_events resume_time_reached 0.500000000 0.750000000\n";
+        let trace = Trace::parse(Cursor::new(trace)).unwrap();
+        let start = trace.start;
+        assert_eq!(trace.records[0].timestamp, Duration::from_millis(500));
+        assert_eq!(
+            trace.records[0].callback,
+            Callback::NewEvents(StartCause::WaitCancelled {
+                start: start + Duration::from_millis(250),
+                requested_resume: None,
+            })
+        );
+        assert_eq!(
+            trace.records[1].callback,
+            Callback::NewEvents(StartCause::ResumeTimeReached {
+                start: start + Duration::from_millis(500),
+                requested_resume: start + Duration::from_millis(750),
+            })
+        );
+
+        let mut written = Vec::new();
+        for record in &trace.records {
+            record.write(&mut written, start).unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(written).unwrap(),
+            "0.500000000 new_events wait_cancelled 0.250000000 -\n1.000000000 new_events \
+             resume_time_reached 0.500000000 0.750000000\n"
+        );
+    }
+
+    #[test]
+    fn malformed_trace() {
+        let trace = "winit-trace 1\n0.000000000 resized 1 800\n";
+        match Trace::parse(Cursor::new(trace)) {
+            Err(TraceError::Parse { line: 2, .. }) => (),
+            result => panic!("unexpected result {result:?}"),
+        }
+
+        assert!(Trace::parse(Cursor::new("0.000000000 about_to_wait\n")).is_err());
+    }
+
+    #[test]
+    fn short_fraction() {
+        let trace = "winit-trace 1\n0.5 about_to_wait\n";
+        match Trace::parse(Cursor::new(trace)) {
+            Err(TraceError::Parse { line: 2, .. }) => (),
+            result => panic!("unexpected result {result:?}"),
+        }
+    }
+}
@@ -0,0 +1,510 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+
+use raw_window_handle::{
+    DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, WindowHandle,
+};
+use raw_window_handle_05::HasRawWindowHandle as HasRawWindowHandle05;
+
+use crate::application::{Application, WindowHandler};
+use crate::cursor::{Cursor, CursorGrabMode, CustomCursor, CustomCursorSource};
+use crate::dpi::{PhysicalSize, Position};
+use crate::error::{OsError, RequestError};
+use crate::event_loop::proxy::{EventLoopProxy, Message};
+use crate::event_loop::{ControlFlow, EventLoopHandle, ShutdownPolicy, Timer, TimerId};
+use crate::id::IdGenerator;
+use crate::monitor::{Monitor, MonitorId};
+use crate::window::{Fullscreen, Icon, Theme, Window, WindowAttributes, WindowId};
+
+use super::{Callback, Record, Trace};
+
+/// Feeds the recorded [`Trace`] into the application.
+///
+/// The callbacks are replayed in order as fast as possible, the timestamps
+/// are only informative. The trace is replayed as recorded, even when the
+/// application answers [`ApplicationWindow::close_requested`] differently.
+///
+/// The windows requested by the application get the ids from the trace, in
+/// the order they first appear in it.
+///
+/// [`ApplicationWindow::close_requested`]: crate::application::ApplicationWindow::close_requested
+pub struct Replay {
+    start: Instant,
+    records: VecDeque<Record>,
+    handle: ReplayHandle,
+}
+
+impl Replay {
+    pub fn new(trace: Trace) -> Self {
+        let mut window_ids = VecDeque::new();
+        for record in &trace.records {
+            if let Callback::Created(window_id) | Callback::CreateFailed(window_id) =
+                record.callback
+            {
+                if !window_ids.contains(&window_id) {
+                    window_ids.push_back(window_id);
+                }
+            }
+        }
+
+        let handle = ReplayHandle {
+            windows: Vec::new(),
+            handlers: HashMap::new(),
+            window_ids,
+            ids: IdGenerator::new(),
+            control_flow: ControlFlow::default(),
+            shutdown_policy: ShutdownPolicy::default(),
+            next_timer_id: 0,
+            exit_code: None,
+            now: trace.start,
+        };
+
+        Self { start: trace.start, records: trace.records.into(), handle }
+    }
+
+    /// The handle passed to the application.
+    pub fn handle(&self) -> &ReplayHandle {
+        &self.handle
+    }
+
+    /// The records which are not replayed yet.
+    pub fn remaining(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Replay the next record, returning it.
+    ///
+    /// Returns `None` once the whole trace was replayed.
+    pub fn step<A: Application>(&mut self, app: &mut A) -> Option<Record> {
+        let record = self.records.pop_front()?;
+        let handle = &mut self.handle;
+        handle.now = self.start + record.timestamp;
+        match record.callback {
+            Callback::NewEvents(start_cause) => app.new_events(handle, start_cause),
+            Callback::UserWakeup => app.user_wakeup(handle),
+            Callback::UserMessage => app.user_message(handle, Box::new(())),
+            Callback::TimerFired(timer_id) => app.timer_fired(handle, timer_id),
+            Callback::AboutToWait => app.about_to_wait(handle),
+            Callback::LoopExiting => {
+                app.loop_exiting(handle);
+                handle.windows.clear();
+                handle.handlers.clear();
+            },
+            Callback::Created(window_id) => {
+                match handle.windows.iter_mut().find(|window| window.id == window_id) {
+                    Some(window) => window.configured = true,
+                    None => {
+                        let mut window = ReplayWindow::new(window_id, &Default::default());
+                        window.configured = true;
+                        handle.windows.push(window);
+                    },
+                }
+                handle.with_handler(app, window_id, |handler, handle| {
+                    handler.created(handle, window_id)
+                });
+            },
+            Callback::CreateFailed(window_id) => {
+                handle.windows.retain(|window| window.id != window_id);
+                let error = OsError::new("the recorded window creation failed");
+                handle.with_handler(app, window_id, |handler, handle| {
+                    handler.create_failed(handle, window_id, error.into())
+                });
+            },
+            Callback::Resized(window_id, size) => {
+                if let Some(window) = handle.window_mut(window_id) {
+                    window.size = size;
+                }
+                handle.with_handler(app, window_id, |handler, handle| {
+                    handler.resized(handle, window_id, size)
+                });
+            },
+            Callback::ScaleFactorChanged(window_id, scale_factor) => {
+                if let Some(window) = handle.window_mut(window_id) {
+                    window.scale_factor = scale_factor;
+                }
+                handle.with_handler(app, window_id, |handler, handle| {
+                    handler.scale_factor_changed(handle, window_id, scale_factor)
+                });
+            },
+            Callback::RedrawRequested(window_id) => {
+                if let Some(window) = handle.window_mut(window_id) {
+                    window.redraw_requested = false;
+                }
+                handle.with_handler(app, window_id, |handler, handle| {
+                    handler.redraw_requested(handle, window_id)
+                });
+            },
+            Callback::CloseRequested(window_id, _) => {
+                handle.with_handler(app, window_id, |handler, handle| {
+                    handler.close_requested(handle, window_id);
+                });
+            },
+            Callback::Focused(window_id, focused) => {
+                handle.with_handler(app, window_id, |handler, handle| {
+                    handler.focused(handle, window_id, focused)
+                });
+            },
+            Callback::Occluded(window_id, occluded) => {
+                handle.with_handler(app, window_id, |handler, handle| {
+                    handler.occluded(handle, window_id, occluded)
+                });
+            },
+            Callback::Destroyed(window_id) => {
+                handle.windows.retain(|window| window.id != window_id);
+                handle.with_handler(app, window_id, |handler, handle| {
+                    handler.destroyed(handle, window_id)
+                });
+            },
+            Callback::TouchDown => {
+                if let Some(handler) = app.touch_handler() {
+                    handler.touch_down();
+                }
+            },
+            Callback::TouchUp => {
+                if let Some(handler) = app.touch_handler() {
+                    handler.touch_up();
+                }
+            },
+            Callback::MouseMotion(delta) => {
+                if let Some(handler) = app.device_events_handelr() {
+                    handler.mouse_motion(handle, delta);
+                }
+            },
+        }
+
+        Some(record)
+    }
+
+    /// Replay the whole trace.
+    pub fn run<A: Application>(&mut self, app: &mut A) {
+        while self.step(app).is_some() {}
+    }
+}
+
+/// The [`EventLoopHandle`] used during the replay.
+///
+/// The windows appear and disappear as the trace says, the requests of the
+/// application are only remembered. The time is the one of the callback
+/// being replayed.
+pub struct ReplayHandle {
+    windows: Vec<ReplayWindow>,
+
+    /// The handlers of the windows created with one.
+    handlers: HashMap<WindowId, Box<dyn WindowHandler>>,
+
+    /// The ids of the windows in the trace, given to the windows requested.
+    window_ids: VecDeque<WindowId>,
+
+    /// The ids of the windows requested beyond the ones in the trace.
+    ids: IdGenerator,
+
+    control_flow: ControlFlow,
+    shutdown_policy: ShutdownPolicy,
+    next_timer_id: u64,
+    exit_code: Option<i32>,
+    now: Instant,
+}
+
+impl ReplayHandle {
+    /// The code the application has requested to exit with.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    fn window_mut(&mut self, window_id: WindowId) -> Option<&mut ReplayWindow> {
+        self.windows.iter_mut().find(|window| window.id == window_id)
+    }
+
+    fn window(&self, window_id: WindowId) -> Option<&ReplayWindow> {
+        self.windows.iter().find(|window| window.id == window_id)
+    }
+
+    /// Run `f` with the handler of the window, or the application when the
+    /// window has none.
+    ///
+    /// The handler is dropped once the window is gone.
+    fn with_handler<A, F>(&mut self, app: &mut A, window_id: WindowId, f: F)
+    where
+        A: Application,
+        F: FnOnce(&mut dyn WindowHandler, &mut Self),
+    {
+        match self.handlers.remove(&window_id) {
+            Some(mut handler) => {
+                f(handler.as_mut(), self);
+                if self.window(window_id).is_some() {
+                    self.handlers.insert(window_id, handler);
+                }
+            },
+            None => f(app, self),
+        }
+    }
+}
+
+impl fmt::Debug for ReplayHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReplayHandle")
+            .field("windows", &self.windows)
+            .field("window_ids", &self.window_ids)
+            .field("ids", &self.ids)
+            .field("control_flow", &self.control_flow)
+            .field("shutdown_policy", &self.shutdown_policy)
+            .field("next_timer_id", &self.next_timer_id)
+            .field("exit_code", &self.exit_code)
+            .field("now", &self.now)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EventLoopHandle for ReplayHandle {
+    fn proxy(&self) -> Arc<dyn EventLoopProxy> {
+        Arc::new(ReplayProxy)
+    }
+
+    fn create_window(&mut self, attributes: &WindowAttributes) -> Result<WindowId, RequestError> {
+        let window_id = self.window_ids.pop_front().unwrap_or_else(|| self.ids.next_window_id());
+        self.windows.push(ReplayWindow::new(window_id, attributes));
+        Ok(window_id)
+    }
+
+    fn create_window_with_handler(
+        &mut self,
+        attributes: &WindowAttributes,
+        handler: Box<dyn WindowHandler>,
+    ) -> Result<WindowId, RequestError> {
+        let window_id = self.create_window(attributes)?;
+        self.handlers.insert(window_id, handler);
+        Ok(window_id)
+    }
+
+    fn destroy_window(&mut self, _: WindowId) {
+        // The window is destroyed when the trace says so.
+    }
+
+    fn num_windows(&self) -> usize {
+        self.windows.len()
+    }
+
+    fn get_window(&self, window_id: WindowId) -> Option<&dyn Window> {
+        self.window(window_id).filter(|window| window.configured).map(|window| window as _)
+    }
+
+    fn get_window_mut(&mut self, window_id: WindowId) -> Option<&mut dyn Window> {
+        self.window_mut(window_id).filter(|window| window.configured).map(|window| window as _)
+    }
+
+    fn get_pending_window(&self, window_id: WindowId) -> Option<&dyn Window> {
+        self.window(window_id).map(|window| window as _)
+    }
+
+    fn get_pending_window_mut(&mut self, window_id: WindowId) -> Option<&mut dyn Window> {
+        self.window_mut(window_id).map(|window| window as _)
+    }
+
+    fn get_monitor(&self, _: MonitorId) -> Option<&dyn Monitor> {
+        None
+    }
+
+    fn monitors(&self) -> Vec<&dyn Monitor> {
+        Vec::new()
+    }
+
+    fn create_custom_cursor(&mut self, _: CustomCursorSource) -> CustomCursor {
+        // Nothing is shown, thus there's nothing to keep around.
+        self.ids.next_custom_cursor_id()
+    }
+
+    fn set_control_flow(&mut self, control_flow: ControlFlow) {
+        self.control_flow = control_flow;
+    }
+
+    fn control_flow(&self) -> ControlFlow {
+        self.control_flow
+    }
+
+    fn add_timer(&mut self, _: Timer) -> TimerId {
+        // The timers fire when the trace says so.
+        let timer_id = TimerId(self.next_timer_id);
+        self.next_timer_id += 1;
+        timer_id
+    }
+
+    fn cancel_timer(&mut self, _: TimerId) {}
+
+    fn set_shutdown_policy(&mut self, policy: ShutdownPolicy) {
+        self.shutdown_policy = policy;
+    }
+
+    fn shutdown_policy(&self) -> ShutdownPolicy {
+        self.shutdown_policy
+    }
+
+    fn now(&self) -> Instant {
+        self.now
+    }
+
+    fn exit_with_code(&mut self, code: i32) {
+        self.exit_code = Some(code);
+    }
+}
+
+impl HasDisplayHandle for ReplayHandle {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        Err(HandleError::NotSupported)
+    }
+}
+
+/// The proxy doing nothing, since the user events come from the trace.
+struct ReplayProxy;
+
+impl EventLoopProxy for ReplayProxy {
+    fn wakeup(&self) {}
+
+    fn send(&self, _: Message) {}
+}
+
+/// The window created by [`Replay`].
+#[derive(Debug, Clone)]
+pub struct ReplayWindow {
+    id: WindowId,
+    title: String,
+    theme: Option<Theme>,
+    scale_factor: f64,
+    size: PhysicalSize<u32>,
+    minimized: bool,
+    maximized: bool,
+    fullscreen: Option<Fullscreen>,
+    icon: Option<Icon>,
+    cursor: Cursor,
+    cursor_visible: bool,
+    cursor_grab: CursorGrabMode,
+    redraw_requested: bool,
+    configured: bool,
+}
+
+impl ReplayWindow {
+    fn new(id: WindowId, attributes: &WindowAttributes) -> Self {
+        Self {
+            id,
+            title: attributes.title().to_owned(),
+            theme: attributes.theme(),
+            scale_factor: 1.,
+            size: attributes.inner_size().to_physical(1.),
+            minimized: false,
+            maximized: attributes.maximized(),
+            fullscreen: attributes.fullscreen(),
+            icon: attributes.window_icon().cloned(),
+            cursor: attributes.cursor(),
+            cursor_visible: true,
+            cursor_grab: CursorGrabMode::None,
+            redraw_requested: false,
+            configured: false,
+        }
+    }
+
+    /// Whether the application has requested a redraw, which wasn't replayed
+    /// yet.
+    pub fn redraw_requested(&self) -> bool {
+        self.redraw_requested
+    }
+}
+
+impl Window for ReplayWindow {
+    fn id(&self) -> WindowId {
+        self.id
+    }
+
+    fn is_configured(&self) -> bool {
+        self.configured
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.title = title.to_owned();
+    }
+
+    fn theme(&self) -> Option<Theme> {
+        self.theme
+    }
+
+    fn set_theme(&mut self, theme: Option<Theme>) {
+        self.theme = theme;
+    }
+
+    fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    fn request_redraw(&mut self) {
+        self.redraw_requested = true;
+    }
+
+    fn pre_present_notify(&mut self) {}
+
+    fn inner_size(&self) -> PhysicalSize<u32> {
+        self.size
+    }
+
+    fn set_minimized(&mut self, minimize: bool) {
+        self.minimized = minimize;
+    }
+
+    fn set_maximized(&mut self, maximized: bool) {
+        self.maximized = maximized;
+    }
+
+    fn set_fullscreen(&mut self, fullscreen: Option<Fullscreen>) {
+        self.fullscreen = fullscreen;
+    }
+
+    fn fullscreen(&self) -> Option<Fullscreen> {
+        self.fullscreen
+    }
+
+    fn set_window_icon(&mut self, icon: Option<Icon>) {
+        self.icon = icon;
+    }
+
+    fn set_cursor(&mut self, cursor: Cursor) {
+        self.cursor = cursor;
+    }
+
+    fn set_cursor_visible(&mut self, visible: bool) {
+        self.cursor_visible = visible;
+    }
+
+    fn set_cursor_grab(&mut self, mode: CursorGrabMode) -> Result<(), RequestError> {
+        self.cursor_grab = mode;
+        Ok(())
+    }
+
+    fn set_cursor_position(&mut self, _: Position) -> Result<(), RequestError> {
+        Ok(())
+    }
+
+    fn current_monitor(&self) -> Option<MonitorId> {
+        None
+    }
+
+    fn primary_monitor(&self) -> Option<MonitorId> {
+        None
+    }
+}
+
+impl HasWindowHandle for ReplayWindow {
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        Err(HandleError::NotSupported)
+    }
+}
+
+unsafe impl HasRawWindowHandle05 for ReplayWindow {
+    fn raw_window_handle(&self) -> raw_window_handle_05::RawWindowHandle {
+        // There's no native window, the web handle is the only one not pointing to
+        // anything.
+        raw_window_handle_05::RawWindowHandle::Web(raw_window_handle_05::WebWindowHandle::empty())
+    }
+}
@@ -0,0 +1,447 @@
+//! Lifecycle conformance suite for the backends.
+//!
+//! The backend implements [`Harness`] on top of its event loop and the
+//! compositor it scripts in tests, then calls [`run`]. The suite drives the
+//! loop with [`ConformanceApp`], which validates every callback against the
+//! documented lifecycle:
+//!
+//! - [`Application::new_events`] with [`StartCause::Init`] comes first, and
+//!   every iteration starts with [`Application::new_events`];
+//! - [`ApplicationWindow::created`] comes before any other callback for the
+//!   window, like [`ApplicationWindow::resized`] and
+//!   [`ApplicationWindow::scale_factor_changed`];
+//! - [`ApplicationWindow::redraw_requested`] is only sent for configured
+//!   windows;
+//! - [`ApplicationWindow::created`] and [`ApplicationWindow::create_failed`]
+//!   are only sent for the ids returned by [`EventLoopHandle::create_window`];
+//! - [`ApplicationWindow::destroyed`] only comes after
+//!   [`ApplicationWindow::close_requested`] returned `true`;
+//! - [`Application::loop_exiting`] comes last;
+//! - [`EventLoopHandle::get_window`] returns `None` before the first configure
+//!   and after the window is destroyed, while
+//!   [`EventLoopHandle::get_pending_window`] returns the requested window until
+//!   it's destroyed.
+
+use std::any::Any;
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::application::{Application, ApplicationWindow, StartCause};
+use crate::dpi::PhysicalSize;
+use crate::error::RequestError;
+use crate::event_loop::pump_events::{EventLoopPumpEvents, PumpStatus};
+use crate::event_loop::{EventLoopHandle, TimerId};
+use crate::record::Callback;
+use crate::window::{WindowAttributes, WindowId};
+
+/// The number of windows created by [`ConformanceApp`].
+pub const NUM_WINDOWS: usize = 2;
+
+/// The backend side of the suite.
+///
+/// The compositor must not configure the windows on its own, only when
+/// [`Harness::configure`] is called.
+pub trait Harness {
+    type EventLoop: EventLoopPumpEvents<ConformanceApp>;
+
+    /// The event loop under test.
+    fn event_loop(&mut self) -> &mut Self::EventLoop;
+
+    /// The id of the `index`-th window created by the application, whether it
+    /// was configured or not.
+    fn window_id(&mut self, index: usize) -> WindowId;
+
+    /// Configure the `index`-th window, letting the client pick the size.
+    fn configure(&mut self, index: usize);
+
+    /// Ask the `index`-th window to close.
+    fn close(&mut self, index: usize);
+}
+
+/// The application validating the callbacks as they arrive.
+///
+/// It creates [`NUM_WINDOWS`] windows on [`StartCause::Init`] and requests a
+/// redraw of every live window on each [`Application::about_to_wait`].
+#[derive(Debug)]
+pub struct ConformanceApp {
+    /// Every callback received so far.
+    pub callbacks: Vec<Callback>,
+
+    /// The lifecycle violations found so far.
+    pub violations: Vec<String>,
+
+    /// What to answer to [`ApplicationWindow::close_requested`].
+    pub accept_close: bool,
+
+    /// Exit on the next [`Application::about_to_wait`].
+    pub exit: bool,
+
+    /// The windows to check [`EventLoopHandle::get_window`] for.
+    watched: Vec<WindowId>,
+
+    /// The windows returned by [`EventLoopHandle::create_window`].
+    requested: Vec<WindowId>,
+
+    /// The windows which were destroyed or failed to be created.
+    gone: Vec<WindowId>,
+
+    /// The windows which were created and not yet destroyed.
+    live: Vec<WindowId>,
+
+    /// The windows which were ever created.
+    created: Vec<WindowId>,
+
+    /// The windows for which the close was accepted.
+    close_accepted: Vec<WindowId>,
+
+    /// Whether the current iteration was started with `new_events`.
+    in_iteration: bool,
+}
+
+impl Default for ConformanceApp {
+    fn default() -> Self {
+        Self {
+            callbacks: Vec::new(),
+            violations: Vec::new(),
+            accept_close: true,
+            exit: false,
+            watched: Vec::new(),
+            requested: Vec::new(),
+            gone: Vec::new(),
+            live: Vec::new(),
+            created: Vec::new(),
+            close_accepted: Vec::new(),
+            in_iteration: false,
+        }
+    }
+}
+
+impl ConformanceApp {
+    /// Check [`EventLoopHandle::get_window`] for the window on every callback,
+    /// even before it's created.
+    pub fn watch(&mut self, window_id: WindowId) {
+        if !self.watched.contains(&window_id) {
+            self.watched.push(window_id);
+        }
+    }
+
+    /// The ids returned by [`EventLoopHandle::create_window`], in order.
+    pub fn requested(&self) -> &[WindowId] {
+        &self.requested
+    }
+
+    /// Whether the window was created and not yet destroyed.
+    pub fn is_live(&self, window_id: WindowId) -> bool {
+        self.live.contains(&window_id)
+    }
+
+    fn violation(&mut self, message: String) {
+        self.violations.push(message);
+    }
+
+    fn record(&mut self, loop_handle: &mut dyn EventLoopHandle, callback: Callback) {
+        if self.callbacks.last() == Some(&Callback::LoopExiting) {
+            self.violation(format!("{callback:?} after loop_exiting"));
+        }
+
+        match callback {
+            Callback::NewEvents(start_cause) => {
+                if self.callbacks.is_empty() != (start_cause == StartCause::Init) {
+                    self.violation(format!(
+                        "new_events({start_cause:?}) at {}",
+                        self.callbacks.len()
+                    ));
+                }
+                if self.in_iteration {
+                    self.violation(String::from("new_events twice in the same iteration"));
+                }
+                self.in_iteration = true;
+            },
+            Callback::LoopExiting => self.in_iteration = false,
+            _ if !self.in_iteration => {
+                self.violation(format!("{callback:?} before new_events"));
+            },
+            _ => (),
+        }
+
+        match callback {
+            Callback::AboutToWait => self.in_iteration = false,
+            Callback::Created(window_id) | Callback::CreateFailed(window_id)
+                if !self.requested.contains(&window_id) =>
+            {
+                self.violation(format!("{callback:?} for the window which wasn't requested"));
+            },
+            Callback::CreateFailed(window_id) => {
+                if self.created.contains(&window_id) {
+                    self.violation(format!("window {window_id:?} failed after being created"));
+                }
+                self.gone.push(window_id);
+            },
+            Callback::Created(window_id) => {
+                if self.created.contains(&window_id) {
+                    self.violation(format!("window {window_id:?} created twice"));
+                }
+                self.created.push(window_id);
+                self.live.push(window_id);
+                self.watch(window_id);
+            },
+            Callback::Destroyed(window_id) => {
+                if !self.close_accepted.contains(&window_id) {
+                    self.violation(format!(
+                        "window {window_id:?} destroyed without close_requested returning true"
+                    ));
+                }
+                self.live.retain(|&live| live != window_id);
+                self.gone.push(window_id);
+            },
+            _ => (),
+        }
+
+        if let Some(window_id) = callback.window_id() {
+            let gone = matches!(callback, Callback::Destroyed(_) | Callback::CreateFailed(_));
+            if !gone && !self.is_live(window_id) {
+                self.violation(format!("{callback:?} for the window which is not live"));
+            }
+        }
+
+        if let Callback::CloseRequested(window_id, true) = callback {
+            self.close_accepted.push(window_id);
+        }
+
+        for index in 0..self.watched.len() {
+            let window_id = self.watched[index];
+            let exists = loop_handle.get_window(window_id).is_some();
+            if exists != self.is_live(window_id) {
+                self.violation(format!(
+                    "get_window({window_id:?}) returned {} during {callback:?}",
+                    if exists { "Some" } else { "None" },
+                ));
+            }
+
+            let pending =
+                loop_handle.get_pending_window(window_id).map(|window| window.is_configured());
+            let expected = match self.gone.contains(&window_id) {
+                true => None,
+                false => Some(self.is_live(window_id)),
+            };
+            if pending != expected {
+                self.violation(format!(
+                    "get_pending_window({window_id:?}) returned {pending:?} as configured during \
+                     {callback:?}"
+                ));
+            }
+        }
+
+        self.callbacks.push(callback);
+    }
+
+    fn count(&self, callback: &Callback) -> usize {
+        self.callbacks.iter().filter(|recorded| *recorded == callback).count()
+    }
+
+    fn window_callbacks(&self, window_id: WindowId) -> impl Iterator<Item = &Callback> {
+        self.callbacks.iter().filter(move |callback| callback.window_id() == Some(window_id))
+    }
+
+    /// Panic with the full log if there were any violations.
+    fn assert_conforms(&self, step: &str) {
+        if self.violations.is_empty() {
+            return;
+        }
+
+        let mut message = format!("lifecycle violated after {step}:\n");
+        for violation in &self.violations {
+            let _ = writeln!(message, "  - {violation}");
+        }
+        let _ = write!(message, "callbacks: {:#?}", self.callbacks);
+        panic!("{message}");
+    }
+}
+
+impl Application for ConformanceApp {
+    fn user_wakeup(&mut self, loop_handle: &mut dyn EventLoopHandle) {
+        self.record(loop_handle, Callback::UserWakeup);
+    }
+
+    fn user_message(&mut self, loop_handle: &mut dyn EventLoopHandle, _: Box<dyn Any + Send>) {
+        self.record(loop_handle, Callback::UserMessage);
+    }
+
+    fn timer_fired(&mut self, loop_handle: &mut dyn EventLoopHandle, timer_id: TimerId) {
+        self.record(loop_handle, Callback::TimerFired(timer_id));
+    }
+
+    fn new_events(&mut self, loop_handle: &mut dyn EventLoopHandle, start_cause: StartCause) {
+        self.record(loop_handle, Callback::NewEvents(start_cause));
+
+        if start_cause == StartCause::Init {
+            for index in 0..NUM_WINDOWS {
+                let attributes =
+                    WindowAttributes::default().with_title(format!("Conformance {index}"));
+                match loop_handle.create_window(&attributes) {
+                    Ok(window_id) => {
+                        self.requested.push(window_id);
+                        self.watch(window_id);
+                    },
+                    Err(err) => self.violation(format!("failed to create window {index}: {err}")),
+                }
+            }
+        }
+    }
+
+    fn about_to_wait(&mut self, loop_handle: &mut dyn EventLoopHandle) {
+        self.record(loop_handle, Callback::AboutToWait);
+
+        for index in 0..self.live.len() {
+            if let Some(window) = loop_handle.get_window_mut(self.live[index]) {
+                window.request_redraw();
+            }
+        }
+
+        if self.exit {
+            loop_handle.exit();
+        }
+    }
+
+    fn loop_exiting(&mut self, loop_handle: &mut dyn EventLoopHandle) {
+        self.record(loop_handle, Callback::LoopExiting);
+    }
+}
+
+impl ApplicationWindow for ConformanceApp {
+    fn created(&mut self, loop_handle: &mut dyn EventLoopHandle, window_id: WindowId) {
+        self.record(loop_handle, Callback::Created(window_id));
+    }
+
+    fn create_failed(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        window_id: WindowId,
+        _: RequestError,
+    ) {
+        self.record(loop_handle, Callback::CreateFailed(window_id));
+    }
+
+    fn resized(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        window_id: WindowId,
+        size: PhysicalSize<u32>,
+    ) {
+        self.record(loop_handle, Callback::Resized(window_id, size));
+    }
+
+    fn scale_factor_changed(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        window_id: WindowId,
+        scale_factor: f64,
+    ) {
+        self.record(loop_handle, Callback::ScaleFactorChanged(window_id, scale_factor));
+    }
+
+    fn redraw_requested(&mut self, loop_handle: &mut dyn EventLoopHandle, window_id: WindowId) {
+        self.record(loop_handle, Callback::RedrawRequested(window_id));
+    }
+
+    fn close_requested(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        window_id: WindowId,
+    ) -> bool {
+        let accept = self.accept_close;
+        self.record(loop_handle, Callback::CloseRequested(window_id, accept));
+        accept
+    }
+
+    fn focused(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        window_id: WindowId,
+        focused: bool,
+    ) {
+        self.record(loop_handle, Callback::Focused(window_id, focused));
+    }
+
+    fn occluded(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        window_id: WindowId,
+        occluded: bool,
+    ) {
+        self.record(loop_handle, Callback::Occluded(window_id, occluded));
+    }
+
+    fn destroyed(&mut self, loop_handle: &mut dyn EventLoopHandle, window_id: WindowId) {
+        self.record(loop_handle, Callback::Destroyed(window_id));
+    }
+}
+
+/// Pump the loop once without blocking.
+fn pump<H: Harness>(harness: &mut H, app: &mut ConformanceApp) -> PumpStatus {
+    match harness.event_loop().pump_events(Some(Duration::ZERO), app) {
+        Ok(status) => status,
+        Err(err) => panic!("failed to pump the event loop: {err}"),
+    }
+}
+
+/// Run the whole suite, panicking on the first step which violated the
+/// lifecycle.
+pub fn run<H: Harness>(harness: &mut H) {
+    let mut app = ConformanceApp::default();
+
+    // The windows are requested on init, but nothing is configured yet.
+    assert_eq!(pump(harness, &mut app), PumpStatus::Continue);
+    let window_ids: Vec<WindowId> =
+        (0..NUM_WINDOWS).map(|index| harness.window_id(index)).collect();
+    assert_eq!(app.requested(), window_ids, "create_window returned different ids");
+    pump(harness, &mut app);
+    app.assert_conforms("init");
+    assert_eq!(app.callbacks.first(), Some(&Callback::NewEvents(StartCause::Init)));
+    for &window_id in &window_ids {
+        assert_eq!(
+            app.window_callbacks(window_id).count(),
+            0,
+            "window {window_id:?} is unconfigured"
+        );
+    }
+
+    // Only the configured window is created and redrawn.
+    harness.configure(0);
+    pump(harness, &mut app);
+    pump(harness, &mut app);
+    app.assert_conforms("configuring the first window");
+    assert_eq!(app.count(&Callback::Created(window_ids[0])), 1);
+    assert!(app.count(&Callback::RedrawRequested(window_ids[0])) > 0);
+    assert!(app
+        .window_callbacks(window_ids[0])
+        .any(|callback| matches!(callback, Callback::Resized(..))));
+    assert_eq!(app.window_callbacks(window_ids[1]).count(), 0);
+
+    harness.configure(1);
+    pump(harness, &mut app);
+    app.assert_conforms("configuring the second window");
+    assert_eq!(app.count(&Callback::Created(window_ids[1])), 1);
+    assert!(app.count(&Callback::RedrawRequested(window_ids[1])) > 0);
+
+    // Refusing to close keeps the window alive.
+    app.accept_close = false;
+    harness.close(1);
+    pump(harness, &mut app);
+    app.assert_conforms("refusing to close");
+    assert_eq!(app.count(&Callback::CloseRequested(window_ids[1], false)), 1);
+    assert!(app.is_live(window_ids[1]));
+
+    app.accept_close = true;
+    harness.close(0);
+    pump(harness, &mut app);
+    app.assert_conforms("closing the first window");
+    assert_eq!(app.count(&Callback::Destroyed(window_ids[0])), 1);
+    assert!(!app.is_live(window_ids[0]));
+
+    app.exit = true;
+    assert_eq!(pump(harness, &mut app), PumpStatus::Exit(0));
+    app.assert_conforms("exiting");
+    assert_eq!(app.callbacks.last(), Some(&Callback::LoopExiting));
+    assert_eq!(app.count(&Callback::LoopExiting), 1);
+}
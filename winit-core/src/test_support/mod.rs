@@ -0,0 +1,6 @@
+//! Utilities to test the backends against the `winit-core` contract.
+//!
+//! Enabled with the `test-support` feature, which backends should only use
+//! in their `dev-dependencies`.
+
+pub mod conformance;
@@ -1,11 +1,18 @@
-// TODO figure out how to do WindowId.
-
 pub use raw_window_handle::HasWindowHandle;
 pub use raw_window_handle_05::HasRawWindowHandle as HasRawWindowHandle05;
 
+use crate::cursor::{Cursor, CursorGrabMode, CursorIcon};
 use crate::dpi::{LogicalSize, PhysicalSize, Position, Size};
+use crate::error::{BadIcon, RequestError};
 use crate::monitor::MonitorId;
 
+/// The identifier of the window.
+///
+/// The ids are unique for the lifetime of the process, the id of the
+/// destroyed window is never given to another one. The backends allocate
+/// them with [`IdGenerator`].
+///
+/// [`IdGenerator`]: crate::id::IdGenerator
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct WindowId(pub u128);
 
@@ -13,6 +20,12 @@ pub struct WindowId(pub u128);
 pub trait Window: HasWindowHandle + HasRawWindowHandle05 {
     fn id(&self) -> WindowId;
 
+    /// Whether the window was configured by the display server and reported
+    /// with [`ApplicationWindow::created`].
+    ///
+    /// [`ApplicationWindow::created`]: crate::application::ApplicationWindow::created
+    fn is_configured(&self) -> bool;
+
     /// Gets the current title of the window.
     fn title(&self) -> &str;
 
@@ -24,14 +37,67 @@ pub trait Window: HasWindowHandle + HasRawWindowHandle05 {
 
     fn scale_factor(&self) -> f64;
 
+    /// Request [`ApplicationWindow::redraw_requested`] for this window.
+    ///
+    /// Multiple requests are merged into a single redraw. When
+    /// [`Window::pre_present_notify`] was used for the previous frame, the
+    /// redraw is delivered only once the system is ready for the next one.
+    ///
+    /// [`ApplicationWindow::redraw_requested`]: crate::application::ApplicationWindow::redraw_requested
     fn request_redraw(&mut self);
 
+    /// Notify the windowing system before presenting to the window.
+    ///
+    /// You should call this after your drawing operations, but before you
+    /// submit the buffer to the display or commit your drawings. Doing so
+    /// lets the backend throttle [`Window::request_redraw`] to the rate the
+    /// window is actually presented at, so hidden windows don't render in
+    /// vain.
+    fn pre_present_notify(&mut self);
+
     fn inner_size(&self) -> PhysicalSize<u32>;
 
     fn set_minimized(&mut self, minimize: bool);
 
     fn set_maximized(&mut self, maximized: bool);
 
+    /// Request the window to enter or, with `None`, leave fullscreen.
+    ///
+    /// The display server could refuse the request or pick another monitor,
+    /// see [`Window::fullscreen`] for the state it has applied.
+    fn set_fullscreen(&mut self, fullscreen: Option<Fullscreen>);
+
+    /// The fullscreen state of the window as reported by the display server.
+    fn fullscreen(&self) -> Option<Fullscreen>;
+
+    /// Set the icon shown for the window by the taskbar, the window switcher
+    /// and alike, or reset it to the default with `None`.
+    ///
+    /// Silently ignored when the display server has no way to set the icon.
+    fn set_window_icon(&mut self, icon: Option<Icon>);
+
+    /// Set the cursor shown while the pointer is over the window.
+    ///
+    /// When the display server doesn't know the icon, the closest one is used,
+    /// usually [`CursorIcon::Default`].
+    fn set_cursor(&mut self, cursor: Cursor);
+
+    /// Show or hide the cursor while the pointer is over the window.
+    fn set_cursor_visible(&mut self, visible: bool);
+
+    /// Confine or lock the pointer while it's over the window.
+    ///
+    /// Returns [`RequestError::NotSupported`] when the display server can't
+    /// hold the pointer, keeping the previous mode.
+    fn set_cursor_grab(&mut self, mode: CursorGrabMode) -> Result<(), RequestError>;
+
+    /// Move the pointer to the `position` within the window.
+    ///
+    /// On Wayland, the pointer can't be moved by the clients, the position is
+    /// only a hint where to put the pointer once unlocked, thus works only
+    /// with [`CursorGrabMode::Locked`].
+    fn set_cursor_position(&mut self, position: Position) -> Result<(), RequestError>;
+
     fn current_monitor(&self) -> Option<MonitorId>;
 
     fn primary_monitor(&self) -> Option<MonitorId>;
@@ -52,16 +118,16 @@ pub struct WindowAttributes {
     pub transparent: bool,
     pub blur: bool,
     pub decorations: bool,
-    // pub window_icon: Option<Icon>,
+    pub window_icon: Option<Icon>,
     pub theme: Option<Theme>,
     pub resize_increments: Option<Size>,
     pub content_protected: bool,
     pub window_level: WindowLevel,
     pub active: bool,
-    // pub cursor: Cursor,
+    pub cursor: Cursor,
     #[cfg(feature = "rwh_06")]
     pub(crate) parent_window: Option<SendSyncRawWindowHandle>,
-    // pub fullscreen: Option<Fullscreen>,
+    pub fullscreen: Option<Fullscreen>,
 }
 
 impl Default for WindowAttributes {
@@ -84,6 +150,9 @@ impl Default for WindowAttributes {
             theme: None,
             blur: false,
             window_level: Default::default(),
+            fullscreen: None,
+            window_icon: None,
+            cursor: Cursor::Icon(CursorIcon::Default),
         }
     }
 }
@@ -172,6 +241,22 @@ impl WindowAttributes {
         self
     }
 
+    #[inline]
+    pub fn fullscreen(&self) -> Option<Fullscreen> {
+        self.fullscreen
+    }
+
+    /// Request that the window is fullscreen upon creation.
+    ///
+    /// The default is `None`.
+    ///
+    /// See [`Window::set_fullscreen`] for details.
+    #[inline]
+    pub fn with_fullscreen(mut self, fullscreen: Option<Fullscreen>) -> Self {
+        self.fullscreen = fullscreen;
+        self
+    }
+
     #[inline]
     pub fn visible(&self) -> bool {
         self.visible
@@ -256,6 +341,38 @@ impl WindowAttributes {
         self
     }
 
+    #[inline]
+    pub fn window_icon(&self) -> Option<&Icon> {
+        self.window_icon.as_ref()
+    }
+
+    /// Sets the window icon.
+    ///
+    /// The default is `None`.
+    ///
+    /// See [`Window::set_window_icon`] for details.
+    #[inline]
+    pub fn with_window_icon(mut self, window_icon: Option<Icon>) -> Self {
+        self.window_icon = window_icon;
+        self
+    }
+
+    #[inline]
+    pub fn cursor(&self) -> Cursor {
+        self.cursor
+    }
+
+    /// Sets the cursor shown over the window.
+    ///
+    /// The default is [`CursorIcon::Default`].
+    ///
+    /// See [`Window::set_cursor`] for details.
+    #[inline]
+    pub fn with_cursor<C: Into<Cursor>>(mut self, cursor: C) -> Self {
+        self.cursor = cursor.into();
+        self
+    }
+
     #[inline]
     pub fn window_level(&self) -> WindowLevel {
         self.window_level
@@ -291,6 +408,87 @@ impl WindowAttributes {
     }
 }
 
+/// The icon of the window.
+///
+/// The icon is either a name from the freedesktop icon theme, or the RGBA
+/// images at several sizes, the display server picks the one which fits best.
+/// When both are given, the name is preferred and the images are the
+/// fallback.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Icon {
+    name: Option<String>,
+    images: Vec<IconImage>,
+}
+
+impl Icon {
+    /// Create the icon from the freedesktop icon theme, like `text-editor`.
+    pub fn from_name<S: Into<String>>(name: S) -> Self {
+        Self { name: Some(name.into()), images: Vec::new() }
+    }
+
+    /// Create the icon from the RGBA8 image.
+    pub fn from_rgba(rgba: Vec<u8>, width: u32, height: u32) -> Result<Self, BadIcon> {
+        Self::default().with_rgba(rgba, width, height)
+    }
+
+    /// Add the RGBA8 image at another size.
+    pub fn with_rgba(mut self, rgba: Vec<u8>, width: u32, height: u32) -> Result<Self, BadIcon> {
+        self.images.push(IconImage::new(rgba, width, height)?);
+        Ok(self)
+    }
+
+    /// The name of the icon in the freedesktop icon theme.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The images of the icon, in the order they were added.
+    pub fn images(&self) -> &[IconImage] {
+        &self.images
+    }
+}
+
+/// The image of the [`Icon`] at one size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IconImage {
+    rgba: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+impl IconImage {
+    fn new(rgba: Vec<u8>, width: u32, height: u32) -> Result<Self, BadIcon> {
+        if rgba.len() as u64 != width as u64 * height as u64 * 4 {
+            return Err(BadIcon::DimensionsVsPixelCount { width, height, len: rgba.len() });
+        }
+
+        Ok(Self { rgba, width, height })
+    }
+
+    /// The pixels, as rows of RGBA8 without padding.
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+/// The fullscreen mode of the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fullscreen {
+    /// Cover the monitor without changing its video mode.
+    ///
+    /// When the monitor is `None`, the display server picks one, usually the
+    /// one the window is on.
+    Borderless(Option<MonitorId>),
+}
+
 /// A window level groups windows with respect to their z-position.
 ///
 /// The relative ordering between windows in different window levels is fixed.
@@ -333,3 +531,19 @@ pub enum Theme {
     /// Use the dark variant.
     Dark,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::error::BadIcon;
+
+    use super::Icon;
+
+    #[test]
+    fn icon_pixel_count() {
+        let icon = Icon::from_rgba(vec![0; 2 * 3 * 4], 2, 3).unwrap();
+        assert_eq!((icon.images()[0].width(), icon.images()[0].height()), (2, 3));
+
+        let err = icon.with_rgba(vec![0; 7], 2, 1).unwrap_err();
+        assert_eq!(err, BadIcon::DimensionsVsPixelCount { width: 2, height: 1, len: 7 });
+    }
+}
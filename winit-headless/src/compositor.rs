@@ -0,0 +1,154 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use winit_core::dpi::Size;
+use winit_core::window::WindowId;
+
+/// The event the headless compositor sends to the event loop.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompositorEvent {
+    /// Configure the window with the given size, or with the size requested
+    /// by the client when `None`. The first configure creates the window.
+    Configure { window_id: WindowId, size: Option<Size> },
+
+    /// Change the scale factor of the window.
+    ScaleFactor { window_id: WindowId, scale_factor: f64 },
+
+    /// Ask the window to close.
+    Close { window_id: WindowId },
+
+    /// Refuse to map the window which was not configured yet, failing its
+    /// creation.
+    Reject { window_id: WindowId },
+
+    /// Change the focus of the window.
+    Focus { window_id: WindowId, focused: bool },
+
+    /// Change whether the window is occluded.
+    Occluded { window_id: WindowId, occluded: bool },
+
+    /// Put the finger down on the touch screen.
+    TouchDown,
+
+    /// Lift the finger off the touch screen.
+    TouchUp,
+
+    /// Move the mouse by the given delta.
+    MouseMotion { delta: (f64, f64) },
+}
+
+/// Handle to script the headless compositor.
+///
+/// The events are delivered in the order they were sent on the next
+/// iteration of the event loop. The handle could be used from any thread,
+/// including from inside the application callbacks.
+#[derive(Clone)]
+pub struct Compositor {
+    shared: Arc<Shared>,
+}
+
+impl Compositor {
+    pub(crate) fn new(shared: Arc<Shared>) -> Self {
+        Self { shared }
+    }
+
+    /// Send the event to the event loop, waking it up.
+    pub fn send(&self, event: CompositorEvent) {
+        self.shared.update(|pending| pending.events.push_back(event));
+    }
+
+    pub fn configure(&self, window_id: WindowId, size: Option<Size>) {
+        self.send(CompositorEvent::Configure { window_id, size });
+    }
+
+    pub fn set_scale_factor(&self, window_id: WindowId, scale_factor: f64) {
+        self.send(CompositorEvent::ScaleFactor { window_id, scale_factor });
+    }
+
+    pub fn close(&self, window_id: WindowId) {
+        self.send(CompositorEvent::Close { window_id });
+    }
+
+    pub fn reject(&self, window_id: WindowId) {
+        self.send(CompositorEvent::Reject { window_id });
+    }
+
+    pub fn set_focus(&self, window_id: WindowId, focused: bool) {
+        self.send(CompositorEvent::Focus { window_id, focused });
+    }
+
+    pub fn set_occluded(&self, window_id: WindowId, occluded: bool) {
+        self.send(CompositorEvent::Occluded { window_id, occluded });
+    }
+
+    pub fn touch_down(&self) {
+        self.send(CompositorEvent::TouchDown);
+    }
+
+    pub fn touch_up(&self) {
+        self.send(CompositorEvent::TouchUp);
+    }
+
+    pub fn mouse_motion(&self, delta: (f64, f64)) {
+        self.send(CompositorEvent::MouseMotion { delta });
+    }
+}
+
+/// The state shared between the event loop, its proxies and the compositor
+/// handles.
+#[derive(Default)]
+pub(crate) struct Shared {
+    pending: Mutex<Pending>,
+    condvar: Condvar,
+}
+
+/// Everything which wakes the event loop up.
+#[derive(Default)]
+pub(crate) struct Pending {
+    /// The user has requested a wakeup.
+    pub wakeup: bool,
+
+    /// The user has sent messages.
+    pub messages: bool,
+
+    /// The events sent by the compositor.
+    pub events: VecDeque<CompositorEvent>,
+}
+
+impl Pending {
+    fn is_empty(&self) -> bool {
+        !self.wakeup && !self.messages && self.events.is_empty()
+    }
+}
+
+impl Shared {
+    /// Update the pending state and wake up the loop.
+    pub(crate) fn update<F: FnOnce(&mut Pending)>(&self, f: F) {
+        f(&mut self.pending.lock().unwrap());
+        self.condvar.notify_all();
+    }
+
+    /// Wait until something is pending or the `timeout` passes, and take it.
+    pub(crate) fn wait(&self, timeout: Option<Duration>) -> Pending {
+        // The timeout is computed from the loop's clock, but the waiting itself is
+        // always done in real time.
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        let mut pending = self.pending.lock().unwrap();
+        while pending.is_empty() {
+            pending = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+
+                    self.condvar.wait_timeout(pending, deadline - now).unwrap().0
+                },
+                None => self.condvar.wait(pending).unwrap(),
+            };
+        }
+
+        std::mem::take(&mut pending)
+    }
+}
@@ -0,0 +1,853 @@
+use std::marker::PhantomData;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use raw_window_handle::{DisplayHandle, HandleError, HasDisplayHandle};
+use raw_window_handle_05::HasRawDisplayHandle as HasRawDisplayHandle05;
+
+use winit_core::application::{Application, StartCause};
+use winit_core::error::{EventLoopError, OsError, TimedOut};
+use winit_core::event_loop::clock::Clock;
+use winit_core::event_loop::proxy::{EventLoopProxy as CoreEventLoopProxy, Message, MessageQueue};
+use winit_core::event_loop::pump_events::{EventLoopPumpEvents, PumpStatus};
+use winit_core::event_loop::{ControlFlow, EventLoopHandle, EventLoopRequests};
+use winit_core::id::IdGenerator;
+use winit_core::window::{Window as CoreWindow, WindowId};
+
+use crate::compositor::{Compositor, CompositorEvent, Shared};
+use crate::monitor::{Monitor, VirtualMonitor};
+use crate::state::HeadlessState;
+use crate::window::{Window, CONFIGURE_TIMEOUT};
+
+/// Configuration of the headless compositor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// The monitors advertised by the compositor.
+    pub monitors: Vec<VirtualMonitor>,
+
+    /// Whether new windows are configured right away, using the size
+    /// requested by the client.
+    pub auto_configure: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self { monitors: vec![VirtualMonitor::default()], auto_configure: true }
+    }
+}
+
+impl Config {
+    /// Set the monitors advertised by the compositor.
+    ///
+    /// The first monitor is used to place the windows.
+    #[inline]
+    pub fn with_monitors(mut self, monitors: Vec<VirtualMonitor>) -> Self {
+        self.monitors = monitors;
+        self
+    }
+
+    /// Set whether new windows are configured right away.
+    ///
+    /// When disabled, the windows stay unconfigured until
+    /// [`Compositor::configure`] is used.
+    ///
+    /// The default is `true`.
+    #[inline]
+    pub fn with_auto_configure(mut self, auto_configure: bool) -> Self {
+        self.auto_configure = auto_configure;
+        self
+    }
+}
+
+pub struct EventLoop<T: Application + 'static> {
+    state: HeadlessState,
+
+    shared: Arc<Shared>,
+
+    /// Whether `StartCause::Init` was already sent.
+    loop_running: bool,
+
+    _marker: PhantomData<fn(&mut T)>,
+}
+
+impl<T: Application + 'static> EventLoopRequests<T> for EventLoop<T> {
+    fn new() -> Result<Self, EventLoopError> {
+        Ok(Self::with_config(Config::default()))
+    }
+
+    fn run(mut self, mut state: T) -> Result<ExitCode, EventLoopError> {
+        self.run_on_demand(&mut state)
+    }
+
+    fn proxy(&self) -> Arc<dyn CoreEventLoopProxy> {
+        self.state.proxy.clone()
+    }
+}
+
+impl<T: Application + 'static> EventLoopPumpEvents<T> for EventLoop<T> {
+    fn pump_events(
+        &mut self,
+        timeout: Option<Duration>,
+        state: &mut T,
+    ) -> Result<PumpStatus, EventLoopError> {
+        if !self.loop_running {
+            self.loop_running = true;
+            state.new_events(&mut self.state, StartCause::Init);
+            self.state.flush_destroyed(state);
+            self.finish_iteration(state);
+        }
+
+        if self.state.exit.is_none() {
+            self.dispatch_with_timeout(timeout, state);
+            self.finish_iteration(state);
+        }
+
+        match self.state.exit.take() {
+            Some(code) => {
+                self.shutdown(state);
+                Ok(PumpStatus::Exit(code))
+            },
+            None => Ok(PumpStatus::Continue),
+        }
+    }
+}
+
+impl<T: Application + 'static> EventLoop<T> {
+    /// Create the event loop with the given compositor configuration.
+    pub fn with_config(config: Config) -> Self {
+        let shared = Arc::new(Shared::default());
+        let proxy = Arc::new(EventLoopProxy::new(shared.clone()));
+        let compositor = Compositor::new(shared.clone());
+
+        let mut ids = IdGenerator::new();
+        let monitors = config
+            .monitors
+            .into_iter()
+            .map(|monitor| Monitor::new(ids.next_monitor_id(), monitor))
+            .collect();
+
+        let state = HeadlessState::new(ids, monitors, proxy, compositor, config.auto_configure);
+
+        Self { state, shared, loop_running: false, _marker: PhantomData }
+    }
+
+    /// Set the clock used for the control flow and the timers.
+    ///
+    /// Should be set before adding any timers, since their deadlines are
+    /// computed using the clock.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.state.clock = clock;
+    }
+
+    /// Get the handle to script the compositor.
+    pub fn compositor(&self) -> Compositor {
+        self.state.compositor.clone()
+    }
+
+    /// Get the window, including the one not configured yet.
+    pub fn window(&self, window_id: WindowId) -> Option<&Window> {
+        self.state.window(window_id)
+    }
+
+    /// The windows alive, including the ones not configured yet.
+    pub fn windows(&self) -> &[Window] {
+        &self.state.windows
+    }
+
+    /// Let the user know that the loop is exiting and tear it down.
+    fn shutdown(&mut self, user: &mut T) {
+        user.loop_exiting(&mut self.state);
+
+        // Tear down the windows only after the user had a chance to use them.
+        self.state.windows.clear();
+        self.state.destroyed.clear();
+        self.state.handlers.clear();
+        self.state.timers.clear();
+
+        self.loop_running = false;
+    }
+
+    /// Wait for new events for at most `timeout` and dispatch them.
+    fn dispatch_with_timeout(&mut self, timeout: Option<Duration>, user: &mut T) {
+        let clock = self.state.clock.clone();
+        let start = clock.now();
+        let control_flow = self.state.control_flow;
+
+        let mut deadline = timeout.and_then(|timeout| start.checked_add(timeout));
+        let mut shorten = |other: Instant| {
+            deadline = Some(deadline.map_or(other, |deadline| deadline.min(other)));
+        };
+
+        match control_flow {
+            ControlFlow::Poll => shorten(start),
+            ControlFlow::Wait => (),
+            ControlFlow::WaitUntil(control_flow_deadline) => shorten(control_flow_deadline),
+        }
+
+        if let Some(timer_deadline) = self.state.next_timer_deadline() {
+            shorten(timer_deadline);
+        }
+
+        if let Some(configure_deadline) = self.state.next_configure_deadline() {
+            shorten(configure_deadline);
+        }
+
+        // Don't block when there're redraws we could deliver right away, for example
+        // requested from `about_to_wait`.
+        if self.state.windows.iter().any(Window::redraw_ready) {
+            shorten(start);
+        }
+
+        let pending =
+            self.shared.wait(deadline.map(|deadline| deadline.saturating_duration_since(start)));
+
+        let start_cause = match control_flow {
+            ControlFlow::Poll => StartCause::Poll,
+            ControlFlow::Wait => StartCause::WaitCancelled { start, requested_resume: None },
+            ControlFlow::WaitUntil(deadline) if clock.now() < deadline => {
+                StartCause::WaitCancelled { start, requested_resume: Some(deadline) }
+            },
+            ControlFlow::WaitUntil(deadline) => {
+                StartCause::ResumeTimeReached { start, requested_resume: deadline }
+            },
+        };
+
+        user.new_events(&mut self.state, start_cause);
+        self.state.flush_destroyed(user);
+
+        for event in pending.events {
+            self.dispatch_compositor_event(event, user);
+            self.state.flush_destroyed(user);
+        }
+
+        if pending.messages {
+            let proxy = self.state.proxy.clone();
+            while let Some(message) = proxy.messages.pop() {
+                user.user_message(&mut self.state, message);
+                self.state.flush_destroyed(user);
+            }
+        }
+
+        if pending.wakeup {
+            user.user_wakeup(&mut self.state);
+            self.state.flush_destroyed(user);
+        }
+
+        let now = clock.now();
+        for timer_id in self.state.expired_timers(now) {
+            // The timer could be cancelled by the previous callback.
+            if self.state.fire_timer(timer_id, now) {
+                user.timer_fired(&mut self.state, timer_id);
+                self.state.flush_destroyed(user);
+            }
+        }
+
+        for window_id in self.state.configure_timed_out(clock.now()) {
+            if self.state.remove_pending_window(window_id) {
+                let error = TimedOut::new("the initial window configure", CONFIGURE_TIMEOUT);
+                self.state.with_handler(user, window_id, |handler, state| {
+                    handler.create_failed(state, window_id, error.into())
+                });
+                self.state.flush_destroyed(user);
+            }
+        }
+    }
+
+    /// Forward the event sent by the compositor to the user.
+    fn dispatch_compositor_event(&mut self, event: CompositorEvent, user: &mut T) {
+        let state = &mut self.state;
+        if state.is_stale(&event) {
+            return;
+        }
+
+        match event {
+            CompositorEvent::Configure { window_id, size } => {
+                let window = state.window_mut(window_id).unwrap();
+                let initial_configure = !window.configured();
+                let size = window.configure(size);
+                let scale_factor = window.scale_factor();
+
+                // NOTE: like on Wayland, the window is considered as created once its
+                // initial configure arrives.
+                if initial_configure {
+                    state.with_handler(user, window_id, |handler, state| {
+                        handler.created(state, window_id)
+                    });
+                    if state.window(window_id).is_none() {
+                        return;
+                    }
+
+                    state.with_handler(user, window_id, |handler, state| {
+                        handler.scale_factor_changed(state, window_id, scale_factor)
+                    });
+                    if state.window(window_id).is_none() {
+                        return;
+                    }
+                }
+
+                state.with_handler(user, window_id, |handler, state| {
+                    handler.resized(state, window_id, size)
+                });
+
+                if initial_configure && state.window(window_id).is_some() {
+                    state.with_handler(user, window_id, |handler, state| {
+                        handler.redraw_requested(state, window_id)
+                    });
+                }
+            },
+            CompositorEvent::ScaleFactor { window_id, scale_factor } => {
+                let window = state.window_mut(window_id).unwrap();
+                let size = window.set_scale_factor(scale_factor);
+
+                // Only send scale for configured windows.
+                if !window.configured() {
+                    return;
+                }
+
+                state.with_handler(user, window_id, |handler, state| {
+                    handler.scale_factor_changed(state, window_id, scale_factor)
+                });
+                if let Some(size) = size.filter(|_| state.window(window_id).is_some()) {
+                    state.with_handler(user, window_id, |handler, state| {
+                        handler.resized(state, window_id, size)
+                    });
+                }
+            },
+            CompositorEvent::Close { window_id } => {
+                let close = state.with_handler(user, window_id, |handler, state| {
+                    handler.close_requested(state, window_id)
+                });
+                if close {
+                    state.destroy_window(window_id);
+                }
+            },
+            CompositorEvent::Reject { window_id } => {
+                if state.remove_pending_window(window_id) {
+                    let error = OsError::new("the compositor rejected the window");
+                    state.with_handler(user, window_id, |handler, state| {
+                        handler.create_failed(state, window_id, error.into())
+                    });
+                }
+            },
+            CompositorEvent::Focus { window_id, focused } => {
+                let window = state.window_mut(window_id).unwrap();
+                if window.configured() && window.set_focus(focused) {
+                    state.with_handler(user, window_id, |handler, state| {
+                        handler.focused(state, window_id, focused)
+                    });
+                }
+            },
+            CompositorEvent::Occluded { window_id, occluded } => {
+                let window = state.window_mut(window_id).unwrap();
+                if window.configured() && window.set_occluded(occluded) {
+                    state.with_handler(user, window_id, |handler, state| {
+                        handler.occluded(state, window_id, occluded)
+                    });
+                }
+            },
+            CompositorEvent::TouchDown => {
+                if let Some(handler) = user.touch_handler() {
+                    handler.touch_down();
+                }
+            },
+            CompositorEvent::TouchUp => {
+                if let Some(handler) = user.touch_handler() {
+                    handler.touch_up();
+                }
+            },
+            CompositorEvent::MouseMotion { delta } => {
+                if let Some(handler) = user.device_events_handelr() {
+                    handler.mouse_motion(state, delta);
+                }
+            },
+        }
+    }
+
+    /// Issue the pending redraws and let the user know that we're about to
+    /// wait.
+    fn finish_iteration(&mut self, user: &mut T) {
+        let redraw: Vec<_> = self
+            .state
+            .windows
+            .iter_mut()
+            .filter_map(|window| window.take_redraw().then(|| window.id()))
+            .collect();
+
+        for window_id in redraw {
+            // The window could be destroyed by the previous callback.
+            if self.state.window(window_id).is_some() {
+                self.state.with_handler(user, window_id, |handler, state| {
+                    handler.redraw_requested(state, window_id)
+                });
+                self.state.flush_destroyed(user);
+            }
+        }
+
+        user.about_to_wait(&mut self.state);
+        self.state.flush_destroyed(user);
+    }
+}
+
+impl<T: Application + 'static> HasDisplayHandle for EventLoop<T> {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        self.state.display_handle()
+    }
+}
+
+unsafe impl<T: Application + 'static> HasRawDisplayHandle05 for EventLoop<T> {
+    fn raw_display_handle(&self) -> raw_window_handle_05::RawDisplayHandle {
+        self.state.raw_display_handle()
+    }
+}
+
+pub struct EventLoopProxy {
+    shared: Arc<Shared>,
+
+    /// The messages sent by the user.
+    messages: MessageQueue,
+}
+
+impl EventLoopProxy {
+    fn new(shared: Arc<Shared>) -> Self {
+        Self { shared, messages: MessageQueue::new() }
+    }
+}
+
+impl CoreEventLoopProxy for EventLoopProxy {
+    fn wakeup(&self) {
+        self.shared.update(|pending| pending.wakeup = true);
+    }
+
+    fn send(&self, message: Message) {
+        self.messages.push(message);
+        self.shared.update(|pending| pending.messages = true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use winit_core::application::{Application, ApplicationWindow, StartCause};
+    use winit_core::dpi::{PhysicalSize, Size};
+    use winit_core::error::RequestError;
+    use winit_core::event_loop::clock::{Clock, ManualClock};
+    use winit_core::event_loop::pump_events::{EventLoopPumpEvents, PumpStatus};
+    use winit_core::event_loop::{ControlFlow, EventLoopHandle, ShutdownPolicy, Timer, TimerId};
+    use winit_core::test_support::conformance::{self, ConformanceApp, Harness};
+    use winit_core::window::{Window as CoreWindow, WindowAttributes, WindowId};
+
+    use crate::compositor::Compositor;
+    use crate::window::CONFIGURE_TIMEOUT;
+
+    use super::{Config, EventLoop};
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Event {
+        NewEvents(StartCause),
+        Created(WindowId),
+        CreateFailed(WindowId),
+        Resized(WindowId, PhysicalSize<u32>),
+        ScaleFactorChanged(WindowId, f64),
+        RedrawRequested(WindowId),
+        CloseRequested(WindowId),
+        Destroyed(WindowId),
+        TimerFired(TimerId),
+        LoopExiting,
+    }
+
+    #[derive(Default)]
+    struct App {
+        events: Vec<Event>,
+
+        /// Destroy the window from its `resized`.
+        destroy_on_resized: bool,
+
+        /// The number of windows to create from `created`.
+        create_on_created: usize,
+    }
+
+    impl App {
+        /// Take the recorded events, skipping the `new_events`.
+        fn take_window_events(&mut self) -> Vec<Event> {
+            let events = std::mem::take(&mut self.events);
+            events.into_iter().filter(|event| !matches!(event, Event::NewEvents(_))).collect()
+        }
+    }
+
+    impl Application for App {
+        fn user_wakeup(&mut self, _: &mut dyn EventLoopHandle) {}
+
+        fn new_events(&mut self, loop_handle: &mut dyn EventLoopHandle, start_cause: StartCause) {
+            if start_cause == StartCause::Init {
+                loop_handle.set_shutdown_policy(ShutdownPolicy::ExitOnLastWindowDestroyed);
+                let attributes = WindowAttributes::default()
+                    .with_inner_size(Size::Physical(PhysicalSize::new(640, 480)));
+                loop_handle.create_window(&attributes).unwrap();
+            }
+
+            self.events.push(Event::NewEvents(start_cause));
+        }
+
+        fn about_to_wait(&mut self, _: &mut dyn EventLoopHandle) {}
+
+        fn timer_fired(&mut self, _: &mut dyn EventLoopHandle, timer_id: TimerId) {
+            self.events.push(Event::TimerFired(timer_id));
+        }
+
+        fn loop_exiting(&mut self, _: &mut dyn EventLoopHandle) {
+            self.events.push(Event::LoopExiting);
+        }
+    }
+
+    impl ApplicationWindow for App {
+        fn created(&mut self, loop_handle: &mut dyn EventLoopHandle, window_id: WindowId) {
+            assert!(loop_handle.get_window(window_id).is_some());
+            self.events.push(Event::Created(window_id));
+
+            if self.create_on_created > 0 {
+                self.create_on_created -= 1;
+                loop_handle.create_window(&WindowAttributes::default()).unwrap();
+                loop_handle.create_window(&WindowAttributes::default()).unwrap();
+            }
+        }
+
+        fn create_failed(
+            &mut self,
+            loop_handle: &mut dyn EventLoopHandle,
+            window_id: WindowId,
+            _: RequestError,
+        ) {
+            assert!(loop_handle.get_pending_window(window_id).is_none());
+            self.events.push(Event::CreateFailed(window_id));
+        }
+
+        fn resized(
+            &mut self,
+            loop_handle: &mut dyn EventLoopHandle,
+            window_id: WindowId,
+            size: PhysicalSize<u32>,
+        ) {
+            self.events.push(Event::Resized(window_id, size));
+
+            if self.destroy_on_resized {
+                loop_handle.destroy_window(window_id);
+                assert!(loop_handle.get_window(window_id).is_none());
+            }
+        }
+
+        fn scale_factor_changed(
+            &mut self,
+            _: &mut dyn EventLoopHandle,
+            window_id: WindowId,
+            scale_factor: f64,
+        ) {
+            self.events.push(Event::ScaleFactorChanged(window_id, scale_factor));
+        }
+
+        fn redraw_requested(&mut self, _: &mut dyn EventLoopHandle, window_id: WindowId) {
+            self.events.push(Event::RedrawRequested(window_id));
+        }
+
+        fn close_requested(&mut self, _: &mut dyn EventLoopHandle, window_id: WindowId) -> bool {
+            self.events.push(Event::CloseRequested(window_id));
+            true
+        }
+
+        fn destroyed(&mut self, _: &mut dyn EventLoopHandle, window_id: WindowId) {
+            self.events.push(Event::Destroyed(window_id));
+        }
+    }
+
+    #[test]
+    fn window_lifecycle() {
+        let mut event_loop = EventLoop::with_config(Config::default());
+        let compositor = event_loop.compositor();
+        let mut app = App::default();
+
+        let status = event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap();
+        assert_eq!(status, PumpStatus::Continue);
+        assert_eq!(app.events[0], Event::NewEvents(StartCause::Init));
+
+        let window_id = event_loop.windows()[0].id();
+        let size = PhysicalSize::new(640, 480);
+        assert_eq!(app.take_window_events(), [
+            Event::Created(window_id),
+            Event::ScaleFactorChanged(window_id, 1.),
+            Event::Resized(window_id, size),
+            Event::RedrawRequested(window_id),
+        ]);
+
+        compositor.set_scale_factor(window_id, 2.);
+        event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap();
+        assert_eq!(app.take_window_events(), [
+            Event::ScaleFactorChanged(window_id, 2.),
+            Event::Resized(window_id, PhysicalSize::new(1280, 960)),
+        ]);
+
+        compositor.close(window_id);
+        let status = event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap();
+        assert_eq!(status, PumpStatus::Exit(0));
+        assert_eq!(app.take_window_events(), [
+            Event::CloseRequested(window_id),
+            Event::Destroyed(window_id),
+            Event::LoopExiting,
+        ]);
+    }
+
+    #[test]
+    fn destroy_running_window() {
+        let mut event_loop = EventLoop::with_config(Config::default());
+        let mut app = App { destroy_on_resized: true, ..Default::default() };
+
+        let status = event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap();
+        assert_eq!(status, PumpStatus::Exit(0));
+
+        // The rest of the window callbacks are dropped.
+        let events = app.take_window_events();
+        let window_id = match events[0] {
+            Event::Created(window_id) => window_id,
+            _ => panic!("unexpected events {events:?}"),
+        };
+        assert_eq!(events, [
+            Event::Created(window_id),
+            Event::ScaleFactorChanged(window_id, 1.),
+            Event::Resized(window_id, PhysicalSize::new(640, 480)),
+            Event::Destroyed(window_id),
+            Event::LoopExiting,
+        ]);
+    }
+
+    #[test]
+    fn create_from_callback() {
+        let mut event_loop = EventLoop::with_config(Config::default());
+        let mut app = App { create_on_created: 1, ..Default::default() };
+
+        event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap();
+        assert_eq!(event_loop.windows().len(), 3);
+
+        // The windows requested from `created` are created on the next iteration.
+        let created = |app: &mut App| {
+            let events = app.take_window_events();
+            events.iter().filter(|event| matches!(event, Event::Created(_))).count()
+        };
+        assert_eq!(created(&mut app), 1);
+        event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap();
+        assert_eq!(created(&mut app), 2);
+    }
+
+    #[test]
+    fn window_created_on_configure() {
+        let config = Config::default().with_auto_configure(false);
+        let mut event_loop = EventLoop::with_config(config);
+        let compositor = event_loop.compositor();
+        let mut app = App::default();
+
+        event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap();
+        event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap();
+
+        let window_id = event_loop.windows()[0].id();
+        assert!(!event_loop.window(window_id).unwrap().configured());
+        assert_eq!(app.take_window_events(), []);
+
+        compositor.configure(window_id, Some(Size::Physical(PhysicalSize::new(100, 200))));
+        event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap();
+        assert!(event_loop.window(window_id).unwrap().configured());
+        assert_eq!(app.take_window_events(), [
+            Event::Created(window_id),
+            Event::ScaleFactorChanged(window_id, 1.),
+            Event::Resized(window_id, PhysicalSize::new(100, 200)),
+            Event::RedrawRequested(window_id),
+        ]);
+    }
+
+    /// The window handler recording its events into the shared list.
+    struct Handler(Rc<RefCell<Vec<Event>>>);
+
+    impl ApplicationWindow for Handler {
+        fn created(&mut self, _: &mut dyn EventLoopHandle, window_id: WindowId) {
+            self.0.borrow_mut().push(Event::Created(window_id));
+        }
+
+        fn resized(
+            &mut self,
+            _: &mut dyn EventLoopHandle,
+            window_id: WindowId,
+            _: PhysicalSize<u32>,
+        ) {
+            self.0.borrow_mut().push(Event::Resized(window_id, PhysicalSize::default()));
+        }
+
+        fn scale_factor_changed(&mut self, _: &mut dyn EventLoopHandle, _: WindowId, _: f64) {}
+
+        fn redraw_requested(&mut self, _: &mut dyn EventLoopHandle, window_id: WindowId) {
+            self.0.borrow_mut().push(Event::RedrawRequested(window_id));
+        }
+
+        fn close_requested(&mut self, _: &mut dyn EventLoopHandle, window_id: WindowId) -> bool {
+            self.0.borrow_mut().push(Event::CloseRequested(window_id));
+            true
+        }
+
+        fn destroyed(&mut self, _: &mut dyn EventLoopHandle, window_id: WindowId) {
+            self.0.borrow_mut().push(Event::Destroyed(window_id));
+        }
+    }
+
+    #[test]
+    fn window_handler() {
+        let mut event_loop = EventLoop::with_config(Config::default());
+        let compositor = event_loop.compositor();
+        let mut app = App::default();
+
+        event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap();
+        app.events.clear();
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let handler = Box::new(Handler(events.clone()));
+        let attributes = WindowAttributes::default();
+        let window_id = event_loop.state.create_window_with_handler(&attributes, handler).unwrap();
+
+        event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap();
+        assert_eq!(events.take(), [
+            Event::Created(window_id),
+            Event::Resized(window_id, PhysicalSize::default()),
+            Event::RedrawRequested(window_id),
+        ]);
+
+        compositor.close(window_id);
+        event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap();
+        assert_eq!(events.take(), [Event::CloseRequested(window_id), Event::Destroyed(window_id)]);
+
+        // The application got none of the window events, and the handler is dropped
+        // with the window.
+        assert_eq!(app.take_window_events(), []);
+        assert_eq!(Rc::strong_count(&events), 1);
+    }
+
+    #[test]
+    fn window_rejected() {
+        let config = Config::default().with_auto_configure(false);
+        let mut event_loop = EventLoop::with_config(config);
+        let compositor = event_loop.compositor();
+        let mut app = App::default();
+
+        event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap();
+        let window_id = event_loop.windows()[0].id();
+        let window = event_loop.state.get_pending_window(window_id).unwrap();
+        assert!(!window.is_configured());
+        assert!(event_loop.state.get_window(window_id).is_none());
+
+        compositor.reject(window_id);
+        let status = event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap();
+        assert_eq!(app.take_window_events(), [Event::CreateFailed(window_id), Event::LoopExiting]);
+        assert!(event_loop.windows().is_empty());
+
+        // The app only had this window.
+        assert_eq!(status, PumpStatus::Exit(0));
+    }
+
+    #[test]
+    fn configure_timeout() {
+        let clock = Arc::new(ManualClock::new());
+        let config = Config::default().with_auto_configure(false);
+        let mut event_loop = EventLoop::with_config(config);
+        event_loop.set_clock(clock.clone());
+        let compositor = event_loop.compositor();
+        let mut app = App::default();
+
+        event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap();
+        let window_id = event_loop.windows()[0].id();
+
+        clock.advance(CONFIGURE_TIMEOUT - Duration::from_millis(1));
+        event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap();
+        assert_eq!(app.take_window_events(), []);
+
+        clock.advance(Duration::from_millis(1));
+        let status = event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap();
+        assert_eq!(app.take_window_events(), [Event::CreateFailed(window_id), Event::LoopExiting]);
+        assert!(event_loop.windows().is_empty());
+        assert_eq!(status, PumpStatus::Exit(0));
+
+        // The late configure is ignored.
+        compositor.configure(window_id, None);
+        event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap();
+        assert_eq!(app.take_window_events(), []);
+    }
+
+    #[test]
+    fn manual_clock() {
+        let clock = Arc::new(ManualClock::new());
+        let start = clock.now();
+        let mut event_loop = EventLoop::with_config(Config::default());
+        event_loop.set_clock(clock.clone());
+        let mut app = App::default();
+
+        event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap();
+        app.events.clear();
+
+        let timer_id = event_loop.state.add_timer(Timer::Interval(Duration::from_secs(1)));
+        let requested_resume = start + Duration::from_secs(2);
+        event_loop.state.set_control_flow(ControlFlow::WaitUntil(requested_resume));
+
+        // No time has passed.
+        event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap();
+        assert_eq!(app.events, [Event::NewEvents(StartCause::WaitCancelled {
+            start,
+            requested_resume: Some(requested_resume),
+        })]);
+        app.events.clear();
+
+        clock.advance(Duration::from_secs(1));
+        event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap();
+        assert_eq!(app.take_window_events(), [Event::TimerFired(timer_id)]);
+
+        clock.advance(Duration::from_secs(1));
+        event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap();
+        assert_eq!(app.events, [
+            Event::NewEvents(StartCause::ResumeTimeReached {
+                start: start + Duration::from_secs(2),
+                requested_resume,
+            }),
+            Event::TimerFired(timer_id),
+        ]);
+    }
+
+    struct HeadlessHarness {
+        event_loop: EventLoop<ConformanceApp>,
+        compositor: Compositor,
+    }
+
+    impl Harness for HeadlessHarness {
+        type EventLoop = EventLoop<ConformanceApp>;
+
+        fn event_loop(&mut self) -> &mut Self::EventLoop {
+            &mut self.event_loop
+        }
+
+        fn window_id(&mut self, index: usize) -> WindowId {
+            self.event_loop.windows()[index].id()
+        }
+
+        fn configure(&mut self, index: usize) {
+            let window_id = self.window_id(index);
+            self.compositor.configure(window_id, None);
+        }
+
+        fn close(&mut self, index: usize) {
+            let window_id = self.window_id(index);
+            self.compositor.close(window_id);
+        }
+    }
+
+    #[test]
+    fn conformance() {
+        let event_loop = EventLoop::with_config(Config::default().with_auto_configure(false));
+        let compositor = event_loop.compositor();
+        conformance::run(&mut HeadlessHarness { event_loop, compositor });
+    }
+}
@@ -0,0 +1,16 @@
+//! In-memory backend implementing `winit-core` without any display server.
+//!
+//! Windows and monitors only exist inside the [`EventLoop`], and everything a
+//! display server would normally send, like configures, scale changes, close
+//! requests, or input, is scripted through the [`Compositor`] handle. This
+//! allows testing [`Application`] logic on machines without a display.
+//!
+//! [`EventLoop`]: crate::event_loop::EventLoop
+//! [`Compositor`]: crate::compositor::Compositor
+//! [`Application`]: winit_core::application::Application
+
+pub mod compositor;
+pub mod event_loop;
+pub mod monitor;
+pub mod state;
+pub mod window;
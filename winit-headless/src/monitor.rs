@@ -0,0 +1,86 @@
+use winit_core::dpi::{PhysicalPosition, PhysicalSize};
+use winit_core::monitor::{Monitor as CoreMonitor, MonitorId};
+
+/// Description of the monitor advertised by the headless compositor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VirtualMonitor {
+    pub name: String,
+    pub size: PhysicalSize<u32>,
+    pub position: PhysicalPosition<i32>,
+    pub refresh_rate_millihertz: Option<u32>,
+    pub scale_factor: f64,
+}
+
+impl Default for VirtualMonitor {
+    fn default() -> Self {
+        Self {
+            name: String::from("HEADLESS-1"),
+            size: PhysicalSize::new(1920, 1080),
+            position: PhysicalPosition::new(0, 0),
+            refresh_rate_millihertz: Some(60000),
+            scale_factor: 1.,
+        }
+    }
+}
+
+impl VirtualMonitor {
+    pub fn new<N: Into<String>>(name: N, size: PhysicalSize<u32>) -> Self {
+        Self { name: name.into(), size, ..Default::default() }
+    }
+
+    #[inline]
+    pub fn with_position(mut self, position: PhysicalPosition<i32>) -> Self {
+        self.position = position;
+        self
+    }
+
+    #[inline]
+    pub fn with_refresh_rate_millihertz(mut self, refresh_rate: Option<u32>) -> Self {
+        self.refresh_rate_millihertz = refresh_rate;
+        self
+    }
+
+    #[inline]
+    pub fn with_scale_factor(mut self, scale_factor: f64) -> Self {
+        self.scale_factor = scale_factor;
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct Monitor {
+    id: MonitorId,
+    config: VirtualMonitor,
+}
+
+impl Monitor {
+    pub(crate) fn new(id: MonitorId, config: VirtualMonitor) -> Self {
+        Self { id, config }
+    }
+}
+
+impl CoreMonitor for Monitor {
+    fn id(&self) -> MonitorId {
+        self.id
+    }
+
+    fn name(&self) -> Option<String> {
+        Some(self.config.name.clone())
+    }
+
+    fn size(&self) -> PhysicalSize<u32> {
+        self.config.size
+    }
+
+    fn position(&self) -> PhysicalPosition<i32> {
+        self.config.position
+    }
+
+    fn refresh_rate_millihertz(&self) -> Option<u32> {
+        self.config.refresh_rate_millihertz
+    }
+
+    fn scale_factor(&self) -> f64 {
+        self.config.scale_factor
+    }
+}
@@ -0,0 +1,407 @@
+use std::collections::HashMap;
+use std::mem;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use raw_window_handle::{DisplayHandle, HandleError, HasDisplayHandle};
+use raw_window_handle_05::HasRawDisplayHandle as HasRawDisplayHandle05;
+
+use winit_core::application::{Application, WindowHandler};
+use winit_core::cursor::{CustomCursor, CustomCursorSource};
+use winit_core::error::RequestError;
+use winit_core::event_loop::clock::{Clock, SystemClock};
+use winit_core::event_loop::proxy::EventLoopProxy as CoreEventLoopProxy;
+use winit_core::event_loop::{ControlFlow, EventLoopHandle, ShutdownPolicy, Timer, TimerId};
+use winit_core::id::IdGenerator;
+use winit_core::monitor::{Monitor as CoreMonitor, MonitorId};
+use winit_core::window::{Window as CoreWindow, WindowAttributes, WindowId};
+
+use crate::compositor::{Compositor, CompositorEvent};
+use crate::event_loop::EventLoopProxy;
+use crate::monitor::Monitor;
+use crate::window::{Window, CONFIGURE_TIMEOUT};
+
+impl EventLoopHandle for HeadlessState {
+    fn proxy(&self) -> Arc<dyn CoreEventLoopProxy> {
+        self.proxy.clone()
+    }
+
+    fn create_window(&mut self, attributes: &WindowAttributes) -> Result<WindowId, RequestError> {
+        let window_id = self.ids.next_window_id();
+
+        let monitor = self.monitors.first();
+        let scale_factor = monitor.map(CoreMonitor::scale_factor).unwrap_or(1.);
+        let window = Window::new(
+            window_id,
+            monitor.map(CoreMonitor::id),
+            scale_factor,
+            attributes,
+            self.clock.now(),
+        );
+        self.windows.push(window);
+
+        if self.auto_configure {
+            self.compositor.configure(window_id, None);
+        }
+
+        Ok(window_id)
+    }
+
+    fn create_window_with_handler(
+        &mut self,
+        attributes: &WindowAttributes,
+        handler: Box<dyn WindowHandler>,
+    ) -> Result<WindowId, RequestError> {
+        let window_id = self.create_window(attributes)?;
+        self.handlers.insert(window_id, handler);
+        Ok(window_id)
+    }
+
+    fn destroy_window(&mut self, window_id: WindowId) {
+        if let Some(index) = self.windows.iter().position(|window| window.id() == window_id) {
+            let window = self.windows.remove(index);
+            self.destroyed.push(window);
+        }
+    }
+
+    fn num_windows(&self) -> usize {
+        self.windows.len()
+    }
+
+    fn get_window(&self, window_id: WindowId) -> Option<&dyn CoreWindow> {
+        match self.window(window_id) {
+            Some(window) if window.configured() => Some(window as &dyn CoreWindow),
+            _ => None,
+        }
+    }
+
+    fn get_window_mut(&mut self, window_id: WindowId) -> Option<&mut dyn CoreWindow> {
+        match self.window_mut(window_id) {
+            Some(window) if window.configured() => Some(window as &mut dyn CoreWindow),
+            _ => None,
+        }
+    }
+
+    fn get_pending_window(&self, window_id: WindowId) -> Option<&dyn CoreWindow> {
+        self.window(window_id).map(|window| window as &dyn CoreWindow)
+    }
+
+    fn get_pending_window_mut(&mut self, window_id: WindowId) -> Option<&mut dyn CoreWindow> {
+        self.window_mut(window_id).map(|window| window as &mut dyn CoreWindow)
+    }
+
+    fn get_monitor(&self, monitor_id: MonitorId) -> Option<&dyn CoreMonitor> {
+        self.monitors
+            .iter()
+            .find(|monitor| monitor.id() == monitor_id)
+            .map(|monitor| monitor as &dyn CoreMonitor)
+    }
+
+    fn monitors(&self) -> Vec<&dyn CoreMonitor> {
+        self.monitors.iter().map(|monitor| monitor as &dyn CoreMonitor).collect()
+    }
+
+    fn create_custom_cursor(&mut self, source: CustomCursorSource) -> CustomCursor {
+        let cursor = self.ids.next_custom_cursor_id();
+        self.custom_cursors.insert(cursor, source);
+        cursor
+    }
+
+    fn set_control_flow(&mut self, control_flow: ControlFlow) {
+        self.control_flow = control_flow;
+    }
+
+    fn control_flow(&self) -> ControlFlow {
+        self.control_flow
+    }
+
+    fn add_timer(&mut self, timer: Timer) -> TimerId {
+        let timer_id = TimerId(self.next_timer_id);
+        self.next_timer_id += 1;
+
+        let (deadline, interval) = match timer {
+            Timer::Deadline(deadline) => (deadline, None),
+            Timer::Interval(interval) => {
+                let interval = interval.max(Timer::MIN_INTERVAL);
+                (self.clock.now() + interval, Some(interval))
+            },
+        };
+
+        self.timers.push(HeadlessTimer { timer_id, deadline, interval });
+
+        timer_id
+    }
+
+    fn cancel_timer(&mut self, timer_id: TimerId) {
+        self.timers.retain(|timer| timer.timer_id != timer_id);
+    }
+
+    fn set_shutdown_policy(&mut self, policy: ShutdownPolicy) {
+        self.shutdown_policy = policy;
+    }
+
+    fn shutdown_policy(&self) -> ShutdownPolicy {
+        self.shutdown_policy
+    }
+
+    fn now(&self) -> Instant {
+        self.clock.now()
+    }
+
+    fn exit_with_code(&mut self, code: i32) {
+        self.exit = Some(code);
+    }
+}
+
+impl HasDisplayHandle for HeadlessState {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        Err(HandleError::NotSupported)
+    }
+}
+
+unsafe impl HasRawDisplayHandle05 for HeadlessState {
+    fn raw_display_handle(&self) -> raw_window_handle_05::RawDisplayHandle {
+        // There's no native display, the web handle is the only one not pointing to
+        // anything.
+        raw_window_handle_05::RawDisplayHandle::Web(raw_window_handle_05::WebDisplayHandle::empty())
+    }
+}
+
+/// The timer added by the user.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct HeadlessTimer {
+    pub timer_id: TimerId,
+
+    /// The next time the timer fires.
+    pub deadline: Instant,
+
+    /// The interval of the repeating timer.
+    pub interval: Option<Duration>,
+}
+
+/// Winit's headless state.
+pub struct HeadlessState {
+    pub windows: Vec<Window>,
+
+    /// The windows destroyed by the user, dropped once the callback requesting
+    /// that returns.
+    pub(crate) destroyed: Vec<Window>,
+
+    /// The handlers of the windows created with one.
+    pub(crate) handlers: HashMap<WindowId, Box<dyn WindowHandler>>,
+
+    pub monitors: Vec<Monitor>,
+
+    /// The custom cursors registered by the user.
+    custom_cursors: HashMap<CustomCursor, CustomCursorSource>,
+
+    pub proxy: Arc<EventLoopProxy>,
+
+    /// The handle used to configure newly created windows.
+    pub(crate) compositor: Compositor,
+
+    /// Whether new windows are configured by the compositor right away.
+    pub(crate) auto_configure: bool,
+
+    /// The timers added by the user.
+    pub(crate) timers: Vec<HeadlessTimer>,
+
+    /// The source of time for the timers and the control flow.
+    pub(crate) clock: Arc<dyn Clock>,
+
+    /// The generator of the window ids.
+    ids: IdGenerator,
+
+    /// The id of the next timer to add.
+    next_timer_id: u64,
+
+    /// The control flow requested by the user.
+    pub control_flow: ControlFlow,
+
+    /// What to do once the last window is destroyed.
+    pub shutdown_policy: ShutdownPolicy,
+
+    /// The exit code requested by the user.
+    pub exit: Option<i32>,
+}
+
+impl HeadlessState {
+    pub(crate) fn new(
+        ids: IdGenerator,
+        monitors: Vec<Monitor>,
+        proxy: Arc<EventLoopProxy>,
+        compositor: Compositor,
+        auto_configure: bool,
+    ) -> Self {
+        Self {
+            windows: Default::default(),
+            destroyed: Default::default(),
+            handlers: Default::default(),
+            monitors,
+            custom_cursors: Default::default(),
+            proxy,
+            compositor,
+            auto_configure,
+            timers: Default::default(),
+            clock: Arc::new(SystemClock),
+            ids,
+            next_timer_id: 0,
+            control_flow: Default::default(),
+            shutdown_policy: Default::default(),
+            exit: Default::default(),
+        }
+    }
+
+    /// Get the window, including the one not configured yet.
+    pub fn window(&self, window_id: WindowId) -> Option<&Window> {
+        self.windows.iter().find(|window| window.id() == window_id)
+    }
+
+    /// Get the custom cursor registered by the user.
+    pub fn custom_cursor(&self, cursor: CustomCursor) -> Option<&CustomCursorSource> {
+        self.custom_cursors.get(&cursor)
+    }
+
+    pub(crate) fn window_mut(&mut self, window_id: WindowId) -> Option<&mut Window> {
+        self.windows.iter_mut().find(|window| window.id() == window_id)
+    }
+
+    /// Run `f` with the handler of the window, or the user when the window has
+    /// none.
+    ///
+    /// The handler is dropped once the window is gone.
+    pub(crate) fn with_handler<T, F, R>(&mut self, user: &mut T, window_id: WindowId, f: F) -> R
+    where
+        T: Application,
+        F: FnOnce(&mut dyn WindowHandler, &mut Self) -> R,
+    {
+        let mut handler = match self.handlers.remove(&window_id) {
+            Some(handler) => handler,
+            None => return f(user, self),
+        };
+
+        let ret = f(handler.as_mut(), self);
+        let destroyed = self.destroyed.iter().any(|window| window.id() == window_id);
+        if self.window(window_id).is_some() || destroyed {
+            self.handlers.insert(window_id, handler);
+        }
+
+        ret
+    }
+
+    /// Drop the windows destroyed by the user and let it know about them.
+    pub(crate) fn flush_destroyed<T: Application>(&mut self, user: &mut T) {
+        if self.destroyed.is_empty() {
+            return;
+        }
+
+        // The user could destroy more windows from the `destroyed`.
+        while !self.destroyed.is_empty() {
+            for window in mem::take(&mut self.destroyed) {
+                let window_id = window.id();
+                if window.configured() {
+                    self.with_handler(user, window_id, |handler, state| {
+                        handler.destroyed(state, window_id)
+                    });
+                } else {
+                    self.handlers.remove(&window_id);
+                }
+            }
+        }
+
+        self.exit_if_no_windows();
+    }
+
+    /// Remove the window which was not configured yet, returning whether it
+    /// existed.
+    pub(crate) fn remove_pending_window(&mut self, window_id: WindowId) -> bool {
+        let len = self.windows.len();
+        self.windows.retain(|window| window.id() != window_id || window.configured());
+        let removed = self.windows.len() != len;
+        if removed {
+            self.exit_if_no_windows();
+        }
+
+        removed
+    }
+
+    /// Exit once the last window is gone, when the shutdown policy asks for it.
+    fn exit_if_no_windows(&mut self) {
+        if self.windows.is_empty()
+            && self.shutdown_policy == ShutdownPolicy::ExitOnLastWindowDestroyed
+        {
+            self.exit.get_or_insert(0);
+        }
+    }
+
+    /// The closest deadline for the initial configure of the windows.
+    pub(crate) fn next_configure_deadline(&self) -> Option<Instant> {
+        self.windows
+            .iter()
+            .filter(|window| !window.configured())
+            .map(|window| window.requested_at + CONFIGURE_TIMEOUT)
+            .min()
+    }
+
+    /// The windows which didn't get the initial configure in time.
+    pub(crate) fn configure_timed_out(&self, now: Instant) -> Vec<WindowId> {
+        self.windows
+            .iter()
+            .filter(|window| !window.configured() && window.requested_at + CONFIGURE_TIMEOUT <= now)
+            .map(|window| window.id())
+            .collect()
+    }
+
+    /// The closest deadline of the timers.
+    pub(crate) fn next_timer_deadline(&self) -> Option<Instant> {
+        self.timers.iter().map(|timer| timer.deadline).min()
+    }
+
+    /// The ids of the timers expired at `now`, in the order they should fire.
+    pub(crate) fn expired_timers(&self, now: Instant) -> Vec<TimerId> {
+        let mut expired: Vec<_> =
+            self.timers.iter().filter(|timer| timer.deadline <= now).collect();
+        expired.sort_by_key(|timer| timer.deadline);
+        expired.into_iter().map(|timer| timer.timer_id).collect()
+    }
+
+    /// Fire the timer if it's still expired at `now`, rescheduling it when it
+    /// repeats.
+    pub(crate) fn fire_timer(&mut self, timer_id: TimerId, now: Instant) -> bool {
+        let index = match self
+            .timers
+            .iter()
+            .position(|timer| timer.timer_id == timer_id && timer.deadline <= now)
+        {
+            Some(index) => index,
+            None => return false,
+        };
+
+        let timer = &mut self.timers[index];
+        match timer.interval {
+            Some(interval) => timer.deadline += interval,
+            None => {
+                self.timers.remove(index);
+            },
+        }
+
+        true
+    }
+
+    /// Whether the compositor event refers to the window which is gone.
+    pub(crate) fn is_stale(&self, event: &CompositorEvent) -> bool {
+        let window_id = match event {
+            CompositorEvent::Configure { window_id, .. }
+            | CompositorEvent::ScaleFactor { window_id, .. }
+            | CompositorEvent::Close { window_id }
+            | CompositorEvent::Reject { window_id }
+            | CompositorEvent::Focus { window_id, .. }
+            | CompositorEvent::Occluded { window_id, .. } => *window_id,
+            CompositorEvent::TouchDown
+            | CompositorEvent::TouchUp
+            | CompositorEvent::MouseMotion { .. } => return false,
+        };
+
+        self.window(window_id).is_none()
+    }
+}
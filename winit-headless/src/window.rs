@@ -0,0 +1,326 @@
+use std::time::{Duration, Instant};
+
+use raw_window_handle::{HandleError, HasWindowHandle, WindowHandle};
+use raw_window_handle_05::HasRawWindowHandle as HasRawWindowHandle05;
+
+use winit_core::cursor::{Cursor, CursorGrabMode};
+use winit_core::dpi::{PhysicalSize, Position, Size};
+use winit_core::error::RequestError;
+use winit_core::monitor::MonitorId;
+use winit_core::window::{
+    Fullscreen, Icon, Theme, Window as CoreWindow, WindowAttributes, WindowId,
+};
+
+/// How long the compositor has to configure a new window before its creation
+/// is reported as failed.
+pub(crate) const CONFIGURE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The window living only inside the headless compositor.
+#[derive(Debug)]
+pub struct Window {
+    id: WindowId,
+
+    /// The monitor the window is placed on.
+    monitor: Option<MonitorId>,
+
+    /// The latest requested window title.
+    title: String,
+
+    /// Theme variant.
+    theme: Option<Theme>,
+
+    /// The scale factor of the window.
+    scale_factor: f64,
+
+    /// The inner size of the window.
+    size: PhysicalSize<u32>,
+
+    /// The size requested by the client, used when the compositor doesn't
+    /// pick the size on its own.
+    requested_size: Size,
+
+    min_size: Option<Size>,
+
+    max_size: Option<Size>,
+
+    maximized: bool,
+
+    minimized: bool,
+
+    fullscreen: Option<Fullscreen>,
+
+    icon: Option<Icon>,
+
+    cursor: Cursor,
+
+    cursor_visible: bool,
+
+    cursor_grab: CursorGrabMode,
+
+    /// The last position the cursor was moved to by the user.
+    cursor_position: Option<Position>,
+
+    /// Whether the window was configured by the compositor.
+    configured: bool,
+
+    /// Whether the user has requested a redraw.
+    redraw_requested: bool,
+
+    /// Whether the window has focus.
+    has_focus: bool,
+
+    /// Whether the window is occluded.
+    occluded: bool,
+
+    /// When the window was requested by the user.
+    pub(crate) requested_at: Instant,
+}
+
+impl Window {
+    pub(crate) fn new(
+        id: WindowId,
+        monitor: Option<MonitorId>,
+        scale_factor: f64,
+        attributes: &WindowAttributes,
+        requested_at: Instant,
+    ) -> Self {
+        Self {
+            id,
+            monitor,
+            title: attributes.title.clone(),
+            theme: attributes.theme,
+            scale_factor,
+            size: attributes.inner_size.to_physical(scale_factor),
+            requested_size: attributes.inner_size,
+            min_size: attributes.min_inner_size,
+            max_size: attributes.max_inner_size,
+            maximized: attributes.maximized,
+            minimized: false,
+            fullscreen: attributes.fullscreen,
+            icon: attributes.window_icon.clone(),
+            cursor: attributes.cursor,
+            cursor_visible: true,
+            cursor_grab: CursorGrabMode::None,
+            cursor_position: None,
+            configured: false,
+            redraw_requested: false,
+            has_focus: false,
+            occluded: false,
+            requested_at,
+        }
+    }
+
+    /// Whether the window was configured by the compositor.
+    #[inline]
+    pub fn configured(&self) -> bool {
+        self.configured
+    }
+
+    /// Whether the window is maximized.
+    #[inline]
+    pub fn maximized(&self) -> bool {
+        self.maximized
+    }
+
+    /// Whether the window is minimized.
+    #[inline]
+    pub fn minimized(&self) -> bool {
+        self.minimized
+    }
+
+    /// The icon set on the window.
+    #[inline]
+    pub fn icon(&self) -> Option<&Icon> {
+        self.icon.as_ref()
+    }
+
+    /// The cursor set on the window.
+    #[inline]
+    pub fn cursor(&self) -> Cursor {
+        self.cursor
+    }
+
+    /// Whether the cursor is visible over the window.
+    #[inline]
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// How the pointer is held by the window.
+    #[inline]
+    pub fn cursor_grab(&self) -> CursorGrabMode {
+        self.cursor_grab
+    }
+
+    /// The last position the cursor was moved to by the user.
+    #[inline]
+    pub fn cursor_position(&self) -> Option<Position> {
+        self.cursor_position
+    }
+
+    /// Whether the window has focus.
+    #[inline]
+    pub fn has_focus(&self) -> bool {
+        self.has_focus
+    }
+
+    /// Whether the window is occluded.
+    #[inline]
+    pub fn occluded(&self) -> bool {
+        self.occluded
+    }
+
+    /// Configure the window, returning its new size.
+    ///
+    /// When `size` is `None` the size requested by the client is used.
+    pub(crate) fn configure(&mut self, size: Option<Size>) -> PhysicalSize<u32> {
+        let size = size.unwrap_or(self.requested_size);
+        let min_size = self.min_size.unwrap_or(PhysicalSize::new(1, 1).into());
+        let max_size = self.max_size.unwrap_or(PhysicalSize::new(u32::MAX, u32::MAX).into());
+        self.size =
+            Size::clamp(size, min_size, max_size, self.scale_factor).to_physical(self.scale_factor);
+        self.configured = true;
+        self.size
+    }
+
+    /// Set the scale factor, returning the new size when it has changed.
+    ///
+    /// The logical size of the window is preserved, like compositors usually
+    /// do.
+    pub(crate) fn set_scale_factor(&mut self, scale_factor: f64) -> Option<PhysicalSize<u32>> {
+        let logical_size = self.size.to_logical::<f64>(self.scale_factor);
+        self.scale_factor = scale_factor;
+
+        let size = logical_size.to_physical(scale_factor);
+        if size != self.size {
+            self.size = size;
+            Some(size)
+        } else {
+            None
+        }
+    }
+
+    /// Update the focus, returning whether it has changed.
+    pub(crate) fn set_focus(&mut self, focused: bool) -> bool {
+        std::mem::replace(&mut self.has_focus, focused) != focused
+    }
+
+    /// Update the occlusion, returning whether it has changed.
+    pub(crate) fn set_occluded(&mut self, occluded: bool) -> bool {
+        std::mem::replace(&mut self.occluded, occluded) != occluded
+    }
+
+    /// Take the pending redraw request.
+    ///
+    /// Redraws are delivered only to configured windows.
+    pub(crate) fn take_redraw(&mut self) -> bool {
+        self.configured && std::mem::take(&mut self.redraw_requested)
+    }
+
+    /// Whether there's a redraw which could be delivered right away.
+    pub(crate) fn redraw_ready(&self) -> bool {
+        self.configured && self.redraw_requested
+    }
+}
+
+impl CoreWindow for Window {
+    fn id(&self) -> WindowId {
+        self.id
+    }
+
+    fn is_configured(&self) -> bool {
+        self.configured
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.title = title.to_owned();
+    }
+
+    fn theme(&self) -> Option<Theme> {
+        self.theme
+    }
+
+    fn set_theme(&mut self, theme: Option<Theme>) {
+        self.theme = theme;
+    }
+
+    fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    fn request_redraw(&mut self) {
+        self.redraw_requested = true;
+    }
+
+    fn pre_present_notify(&mut self) {
+        // Nothing is presented, thus there's nothing to throttle.
+    }
+
+    fn inner_size(&self) -> PhysicalSize<u32> {
+        self.size
+    }
+
+    fn set_minimized(&mut self, minimize: bool) {
+        self.minimized = minimize;
+    }
+
+    fn set_maximized(&mut self, maximized: bool) {
+        self.maximized = maximized;
+    }
+
+    fn set_fullscreen(&mut self, fullscreen: Option<Fullscreen>) {
+        self.fullscreen = fullscreen;
+    }
+
+    fn fullscreen(&self) -> Option<Fullscreen> {
+        self.fullscreen
+    }
+
+    fn set_window_icon(&mut self, icon: Option<Icon>) {
+        self.icon = icon;
+    }
+
+    fn set_cursor(&mut self, cursor: Cursor) {
+        self.cursor = cursor;
+    }
+
+    fn set_cursor_visible(&mut self, visible: bool) {
+        self.cursor_visible = visible;
+    }
+
+    fn set_cursor_grab(&mut self, mode: CursorGrabMode) -> Result<(), RequestError> {
+        self.cursor_grab = mode;
+        Ok(())
+    }
+
+    fn set_cursor_position(&mut self, position: Position) -> Result<(), RequestError> {
+        self.cursor_position = Some(position);
+        Ok(())
+    }
+
+    fn current_monitor(&self) -> Option<MonitorId> {
+        self.monitor
+    }
+
+    fn primary_monitor(&self) -> Option<MonitorId> {
+        self.monitor
+    }
+}
+
+impl HasWindowHandle for Window {
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        Err(HandleError::NotSupported)
+    }
+}
+
+unsafe impl HasRawWindowHandle05 for Window {
+    fn raw_window_handle(&self) -> raw_window_handle_05::RawWindowHandle {
+        // There's no native window, the web handle is the only one not pointing to
+        // anything.
+        raw_window_handle_05::RawWindowHandle::Web(raw_window_handle_05::WebWindowHandle::empty())
+    }
+}
@@ -1,9 +1,13 @@
+use std::any::Any;
 use std::num::NonZeroU32;
+use std::process::ExitCode;
 use std::time::Duration;
 
 use winit_core::application::{Application, ApplicationWindow, StartCause};
 use winit_core::dpi::PhysicalSize;
-use winit_core::event_loop::{EventLoopHandle, EventLoopRequests};
+use winit_core::error::{EventLoopError, RequestError};
+use winit_core::event_loop::channel::Sender;
+use winit_core::event_loop::{EventLoopHandle, EventLoopRequests, ShutdownPolicy};
 use winit_core::window::WindowId;
 use winit_wayland::event_loop::EventLoop;
 use winit_wayland::MyCoolTrait;
@@ -15,17 +19,9 @@ const DARK_GRAY: u32 = 0xFF181818;
 pub struct State {
     context: Context,
     surface: Option<Surface>,
+    window_id: Option<WindowId>,
 }
 
-// TODO: It's not clear how to do user events with all of that, for example if
-// we add associated type it'll bleed through out the codebase. Maybe what we
-// should do is to develop a way to wake up the loop, thus the actual user
-// events will be squashed when needed, since wakeup is squashed, and how the
-// data is transmitted, etc will be up to users.
-//
-// In general, a generic interface to wakeup the loop and then the user can
-// `poll` the sources looks more appealing.
-
 impl MyCoolTrait for State {
     fn foo(&mut self) {
         println!("Foo");
@@ -37,9 +33,18 @@ impl Application for State {
         println!("Wake up");
     }
 
+    fn user_message(&mut self, _: &mut dyn EventLoopHandle, message: Box<dyn Any + Send>) {
+        if let Ok(tick) = message.downcast::<u32>() {
+            println!("Tick {tick}");
+        }
+    }
+
     fn new_events(&mut self, loop_handle: &mut dyn EventLoopHandle, start_cause: StartCause) {
         println!("Start cause {start_cause:?}");
-        let _ = loop_handle.create_window(&Default::default());
+        if start_cause == StartCause::Init {
+            loop_handle.set_shutdown_policy(ShutdownPolicy::ExitOnLastWindowDestroyed);
+            self.window_id = loop_handle.create_window(&Default::default()).ok();
+        }
     }
 
     fn about_to_wait(&mut self, _: &mut dyn EventLoopHandle) {
@@ -53,12 +58,23 @@ impl Application for State {
 
 impl ApplicationWindow for State {
     fn created(&mut self, loop_handle: &mut dyn EventLoopHandle, window_id: WindowId) {
+        assert_eq!(self.window_id, Some(window_id));
         let window = loop_handle.get_window(window_id).unwrap();
         self.surface = unsafe {
             Some(Surface::new(&self.context, &window).expect("failed to create surface"))
         };
     }
 
+    fn create_failed(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        _: WindowId,
+        error: RequestError,
+    ) {
+        println!("Failed to create the window: {error}");
+        loop_handle.exit_with_code(1);
+    }
+
     fn close_requested(&mut self, _: &mut dyn EventLoopHandle, _: WindowId) -> bool {
         true
     }
@@ -99,33 +115,36 @@ impl ApplicationWindow for State {
             .resize(NonZeroU32::new(size.width).unwrap(), NonZeroU32::new(size.height).unwrap());
         let mut buffer = surface.buffer_mut().unwrap();
         buffer.fill(DARK_GRAY);
-        buffer.present().unwrap();
-    }
 
-    fn destroyed(&mut self, loop_handle: &mut dyn EventLoopHandle, _: WindowId) {
-        if loop_handle.num_windows() == 0 {
-            loop_handle.exit();
+        if let Some(window) = loop_handle.get_window_mut(window_id) {
+            window.pre_present_notify();
         }
+
+        buffer.present().unwrap();
     }
 }
 
-fn main() {
+fn main() -> Result<ExitCode, EventLoopError> {
     // TODO this is ugly.
-    let mut event_loop = <EventLoop<State> as EventLoopRequests<State>>::new().unwrap();
+    let mut event_loop = <EventLoop<State> as EventLoopRequests<State>>::new()?;
 
     event_loop.register_my_cool_trait_handler();
 
     let context =
         unsafe { Context::new(&event_loop).expect("failed to create softbuffer context") };
-    let state = State { context, surface: None };
+    let state = State { context, surface: None, window_id: None };
 
-    let proxy = EventLoopRequests::<State>::proxy(&mut event_loop);
+    let proxy = EventLoopRequests::<State>::proxy(&event_loop);
+    let sender = Sender::new(proxy.clone());
 
     // Test out the proxy.
-    std::thread::spawn(move || loop {
-        proxy.wakeup();
-        std::thread::sleep(Duration::from_millis(500));
+    std::thread::spawn(move || {
+        for tick in 0u32.. {
+            proxy.wakeup();
+            sender.send(tick);
+            std::thread::sleep(Duration::from_millis(500));
+        }
     });
 
-    event_loop.run(state);
+    event_loop.run(state)
 }
@@ -1,9 +1,21 @@
-use std::collections::HashMap;
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
 use std::mem;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use std::panic::{self, AssertUnwindSafe};
+use std::process::ExitCode;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use calloop::ping::Ping;
-use calloop::LoopHandle;
+use calloop::{
+    EventIterator, EventSource, InsertError, LoopHandle, Poll, PostAction, Readiness,
+    RegistrationToken, Token, TokenFactory,
+};
 use raw_window_handle::{DisplayHandle, HandleError, HasDisplayHandle};
 use raw_window_handle_05::HasRawDisplayHandle as HasRawDisplayHandle05;
 
@@ -17,114 +29,402 @@ use sctk::registry::{ProvidesRegistryState, RegistryState};
 use sctk::output::{OutputHandler, OutputState};
 use sctk::seat::{Capability as SeatCapability, SeatHandler, SeatState};
 
-use winit_core::application::Application;
-use winit_core::event_loop::proxy::EventLoopProxy as CoreEventLoopProxy;
-use winit_core::event_loop::{EventLoopHandle, EventLoopRequests};
+use winit_core::application::{Application, StartCause};
+use winit_core::error::{CallbackPanicked, ConnectError, EventLoopError, OsError, TimedOut};
+use winit_core::event_loop::clock::Clock;
+use winit_core::event_loop::proxy::{EventLoopProxy as CoreEventLoopProxy, Message, MessageQueue};
+use winit_core::event_loop::pump_events::{EventLoopPumpEvents, PumpStatus};
+use winit_core::event_loop::{ControlFlow, EventLoopHandle, EventLoopRequests};
 use winit_core::window::{Window as CoreWindow, WindowId};
 
 use crate::state::WinitState;
+use crate::window::{Window, CONFIGURE_TIMEOUT};
 use crate::MyCoolTrait;
 
 pub struct EventLoop<T: Application + 'static> {
     state: RuntimeState<T>,
 
     event_loop: calloop::EventLoop<'static, RuntimeState<T>>,
+
+    /// Whether `StartCause::Init` was already sent.
+    loop_running: bool,
 }
 
 impl<T: Application + 'static> EventLoopRequests<T> for EventLoop<T> {
-    fn new() -> Result<Self, ()> {
-        let connection = Connection::connect_to_env().unwrap();
+    fn new() -> Result<Self, EventLoopError> {
+        let connection = Connection::connect_to_env().map_err(ConnectError::new)?;
+        Self::with_connection(connection)
+    }
+
+    fn run(mut self, mut state: T) -> Result<ExitCode, EventLoopError> {
+        match self.run_on_demand(&mut state) {
+            Err(EventLoopError::CallbackPanicked(panic)) => {
+                // Close the connection before re-raising the panic.
+                drop(self);
+                panic.resume()
+            },
+            result => result,
+        }
+    }
+
+    fn proxy(&self) -> Arc<dyn CoreEventLoopProxy> {
+        self.state.winit.proxy()
+    }
+}
 
-        let (globals, mut event_queue) = globals::registry_queue_init(&connection).unwrap();
+impl<T: Application + 'static> EventLoop<T> {
+    /// Create the event loop on top of the already established connection.
+    pub(crate) fn with_connection(connection: Connection) -> Result<Self, EventLoopError> {
+        let (globals, mut event_queue) =
+            globals::registry_queue_init(&connection).map_err(OsError::new)?;
         let queue_handle = event_queue.handle();
 
-        let event_loop = calloop::EventLoop::<RuntimeState<T>>::try_new().unwrap();
+        let event_loop = calloop::EventLoop::<RuntimeState<T>>::try_new().map_err(OsError::new)?;
 
         // Insert the proxy source.
-        let (ping, ping_source) = calloop::ping::make_ping().unwrap();
+        let (ping, ping_source) = calloop::ping::make_ping().map_err(OsError::new)?;
         let proxy = EventLoopProxy::new(ping);
 
-        let _ =
-            event_loop.handle().insert_source(ping_source, |_, _, state: &mut RuntimeState<T>| {
-                let winit = &mut state.winit;
-                let user = &mut state.user.as_mut().unwrap();
-                user.user_wakeup(winit);
-            });
+        event_loop
+            .handle()
+            .insert_source(ping_source, |_, _, state: &mut RuntimeState<T>| {
+                state.callbacks.push_winit(|user, winit| {
+                    let proxy = winit.proxy.clone();
+                    while let Some(message) = proxy.messages.pop() {
+                        user.user_message(winit, message);
+                    }
+
+                    if proxy.wakeup.swap(false, Ordering::Relaxed) {
+                        user.user_wakeup(winit);
+                    }
+                });
+            })
+            .map_err(|err| OsError::new(err.error))?;
 
         let mut state = RuntimeState {
-            user: None,
-            winit: WinitState::new(connection.clone(), &globals, &queue_handle, proxy).unwrap(),
+            winit: WinitState::new(
+                connection.clone(),
+                &globals,
+                &queue_handle,
+                event_loop.handle(),
+                proxy,
+            )?,
             vtable: Vtable::default(),
+            wait_start: None,
+            callbacks: DeferredCallbacks::default(),
         };
 
-        let _ = event_queue.roundtrip(&mut state);
+        event_queue.roundtrip(&mut state).map_err(OsError::new)?;
 
         let wayland_source = WaylandSource::new(connection, event_queue);
-        wayland_source.insert(event_loop.handle()).unwrap();
+        wayland_source.insert(event_loop.handle()).map_err(|err| OsError::new(err.error))?;
 
-        Ok(Self { event_loop, state })
+        Ok(Self { event_loop, state, loop_running: false })
     }
+}
 
-    fn run(mut self, mut state: T) {
-        // SAFETY: The user state is being used only inside the loop and can't have
-        // Wayland objects in it. Calloop itself allow the state to have a
-        // non-static lifetime attached to it, however wayland-rs forces bound
-        // by static. The wayland objects are stored on a bound by
-        // static `winit` state owned by the event loop, and user application trait has
-        // a lifetime of `'a` allowed by calloop, thus making it sound if you
-        // think that calloop dispatches not one but 2 states at the same time.
-        self.state.user =
-            Some(unsafe { std::mem::transmute::<&mut T, &'static mut T>(&mut state) });
-
-        self.state
-            .user
-            .as_mut()
-            .unwrap()
-            .new_events(&mut self.state.winit, winit_core::application::StartCause::Init);
+impl<T: Application + 'static> EventLoopPumpEvents<T> for EventLoop<T> {
+    /// Pump the events.
+    ///
+    /// When the application callback panics, the loop is torn down and the
+    /// panic is returned as [`EventLoopError::CallbackPanicked`].
+    fn pump_events(
+        &mut self,
+        timeout: Option<Duration>,
+        user: &mut T,
+    ) -> Result<PumpStatus, EventLoopError> {
+        // Don't let the panic go through the dispatch, leaving the state half-updated.
+        match panic::catch_unwind(AssertUnwindSafe(|| self.pump_events_inner(timeout, user))) {
+            Ok(result) => result,
+            Err(payload) => Err(self.shutdown_on_panic(payload)),
+        }
+    }
+}
 
-        let mut redraw = Vec::new();
+impl<T: Application + 'static> EventLoop<T> {
+    fn pump_events_inner(
+        &mut self,
+        timeout: Option<Duration>,
+        user: &mut T,
+    ) -> Result<PumpStatus, EventLoopError> {
+        if !self.loop_running {
+            self.loop_running = true;
+            user.new_events(&mut self.state.winit, StartCause::Init);
+            self.state.winit.flush_destroyed(user);
+            self.finish_iteration(user);
+        }
 
-        loop {
-            if self.state.winit.connection.flush().is_err() {
-                return;
+        if self.state.winit.exit.is_none() {
+            if let Err(error) = self.dispatch_with_timeout(timeout, user) {
+                return Err(self.shutdown_on_error(error, user));
             }
 
-            let winit = &mut self.state.winit;
-            let user = self.state.user.as_mut().unwrap();
+            self.finish_iteration(user);
+        }
 
-            for (window_id, window) in &mut winit.windows {
-                if mem::take(&mut window.redraw) {
-                    redraw.push(*window_id);
+        let code = match self.state.winit.exit.take() {
+            Some(code) => code,
+            None => {
+                if let Err(error) = self.state.winit.connection.flush() {
+                    return Err(self.shutdown_on_error(crate::connection_lost(error).into(), user));
                 }
-            }
 
-            // Issue synthetic redraws issued by users.
-            for window_id in redraw.drain(..) {
-                user.redraw_requested(winit, window_id)
+                return Ok(PumpStatus::Continue);
+            },
+        };
+
+        self.shutdown(user);
+
+        match self.state.winit.connection.flush() {
+            Ok(()) => Ok(PumpStatus::Exit(code)),
+            Err(error) => Err(crate::connection_lost(error).into()),
+        }
+    }
+
+    /// Let the user know that the loop is exiting and tear it down.
+    fn shutdown(&mut self, user: &mut T) {
+        user.loop_exiting(&mut self.state.winit);
+
+        // Tear down the windows only after the user had a chance to use them.
+        self.teardown();
+    }
+
+    fn teardown(&mut self) {
+        let winit = &mut self.state.winit;
+        winit.windows.clear();
+        winit.destroyed.clear();
+        winit.handlers.clear();
+        winit.clear_timers();
+
+        // The callbacks refer to the windows which are gone.
+        self.state.callbacks.clear();
+        self.state.wait_start = None;
+
+        self.loop_running = false;
+    }
+
+    /// Tear the loop down after the panic in the application callback.
+    ///
+    /// The user is not called anymore, the windows are destroyed and the
+    /// connection is flushed, so the display server sees them gone.
+    fn shutdown_on_panic(&mut self, payload: Box<dyn Any + Send + 'static>) -> EventLoopError {
+        self.state.winit.exit = None;
+        self.teardown();
+        let _ = self.state.winit.connection.flush();
+
+        CallbackPanicked::new(payload).into()
+    }
+
+    /// Shut the loop down after the error it can't recover from.
+    ///
+    /// Prefers reporting the connection loss when the connection is dead.
+    fn shutdown_on_error(&mut self, error: EventLoopError, user: &mut T) -> EventLoopError {
+        let error = match self.state.winit.connection.backend().last_error() {
+            Some(wayland_error) => crate::connection_lost(wayland_error).into(),
+            None => error,
+        };
+
+        self.state.winit.exit = None;
+        self.shutdown(user);
+
+        error
+    }
+
+    /// Wait for new events for at most `timeout` and dispatch them.
+    fn dispatch_with_timeout(
+        &mut self,
+        timeout: Option<Duration>,
+        user: &mut T,
+    ) -> Result<(), EventLoopError> {
+        let clock = self.state.winit.clock.clone();
+        let start = clock.now();
+        let control_flow = self.state.winit.control_flow;
+        let control_flow_timeout = match control_flow {
+            ControlFlow::Poll => Some(Duration::ZERO),
+            ControlFlow::Wait => None,
+            ControlFlow::WaitUntil(deadline) => Some(deadline.saturating_duration_since(start)),
+        };
+
+        let mut timeout = match (timeout, control_flow_timeout) {
+            (Some(timeout), Some(control_flow_timeout)) => Some(timeout.min(control_flow_timeout)),
+            (timeout, None) | (None, timeout) => timeout,
+        };
+
+        // Start the cursor animations set since the last iteration, so they have
+        // deadlines to wait for.
+        self.state.winit.animate_cursors(start);
+        let RuntimeState { winit, callbacks, .. } = &mut self.state;
+        winit.wake_expired_timers(callbacks, start);
+
+        let deadlines = [
+            self.state.winit.next_configure_deadline(),
+            self.state.winit.next_cursor_deadline(),
+            self.state.winit.next_polled_timer_deadline(),
+        ];
+        for deadline in deadlines.into_iter().flatten() {
+            let deadline_timeout = deadline.saturating_duration_since(start);
+            timeout =
+                Some(timeout.map_or(deadline_timeout, |timeout| timeout.min(deadline_timeout)));
+        }
+
+        // Don't block when there're redraws we could deliver right away, for example
+        // requested from `about_to_wait`.
+        if self.state.winit.windows.values().any(Window::redraw_ready) {
+            timeout = Some(Duration::ZERO);
+        }
+
+        // Nor when the polled timers have already fired.
+        if !self.state.callbacks.is_empty() {
+            timeout = Some(Duration::ZERO);
+        }
+
+        self.state.wait_start = Some((start, control_flow));
+        self.event_loop.dispatch(timeout, &mut self.state).map_err(OsError::new)?;
+
+        // The handlers only queue the callbacks, so the user gets everything once the
+        // dispatch is over.
+        self.state.dispatch_new_events(user);
+        self.state.winit.flush_destroyed(user);
+        while let Some(callback) = self.state.callbacks.pop() {
+            callback(user, &mut self.state.winit);
+            self.state.winit.flush_destroyed(user);
+        }
+
+        let now = clock.now();
+        for window_id in self.state.winit.configure_timed_out(now) {
+            // The window could be destroyed by the previous callback.
+            if self.state.winit.remove_pending_window(window_id) {
+                let error = TimedOut::new("the initial window configure", CONFIGURE_TIMEOUT);
+                self.state.winit.with_handler(user, window_id, |handler, winit| {
+                    handler.create_failed(winit, window_id, error.into())
+                });
+                self.state.winit.flush_destroyed(user);
             }
+        }
 
-            self.state.user.as_mut().unwrap().about_to_wait(&mut self.state.winit);
+        Ok(())
+    }
 
-            // TODO: we should handle waking up for the next iteration due to
-            // redraw-requested here.
+    /// Issue the pending redraws and let the user know that we're about to
+    /// wait.
+    fn finish_iteration(&mut self, user: &mut T) {
+        let winit = &mut self.state.winit;
 
-            self.event_loop.dispatch(None, &mut self.state).unwrap();
+        // Windows waiting for the frame callback keep their redraw pending.
+        let mut redraw = Vec::new();
+        for (window_id, window) in &mut winit.windows {
+            if window.take_redraw() {
+                redraw.push(*window_id);
+            }
+        }
 
-            if self.state.winit.exit {
-                break;
+        // Issue synthetic redraws issued by users.
+        for window_id in redraw {
+            // The window could be destroyed by the previous callback.
+            if winit.windows.contains_key(&window_id) {
+                winit.with_handler(user, window_id, |handler, winit| {
+                    handler.redraw_requested(winit, window_id)
+                });
+                winit.flush_destroyed(user);
             }
         }
 
-        self.state.user.as_mut().unwrap().loop_exiting(&mut self.state.winit);
+        user.about_to_wait(winit);
+        winit.flush_destroyed(user);
+    }
+}
+
+#[cfg(test)]
+impl<T: Application + 'static> EventLoop<T> {
+    /// Access the backend state to inspect it.
+    pub(crate) fn winit(&mut self) -> &mut WinitState<T> {
+        &mut self.state.winit
     }
+}
 
-    fn proxy(&self) -> Arc<dyn CoreEventLoopProxy> {
-        self.state.winit.proxy()
+impl<T: Application + 'static> AsFd for EventLoop<T> {
+    /// Get the file descriptor of the underlying event loop.
+    ///
+    /// It becomes readable once there're new events to process, so it could be
+    /// registered in an external reactor to call
+    /// [`EventLoopPumpEvents::pump_events`] with `Some(Duration::ZERO)` when
+    /// it's ready.
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.event_loop.as_fd()
+    }
+}
+
+impl<T: Application + 'static> AsRawFd for EventLoop<T> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.event_loop.as_fd().as_raw_fd()
+    }
+}
+
+impl<T: Application + 'static> EventLoop<T> {
+    /// Set the clock used for the control flow and the timers.
+    ///
+    /// Should be set before adding any timers, since their deadlines are
+    /// computed using the clock.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.state.winit.clock = clock;
+    }
+
+    /// Insert a custom calloop event source into the event loop.
+    ///
+    /// The `callback` is invoked from the event loop thread with the
+    /// application state and the loop handle, like any other application
+    /// callback.
+    pub fn insert_source<S, F>(
+        &self,
+        source: S,
+        callback: F,
+    ) -> Result<RegistrationToken, InsertError<S>>
+    where
+        S: EventSource + 'static,
+        F: FnMut(S::Event, &mut S::Metadata, &mut T, &mut dyn EventLoopHandle) -> S::Ret + 'static,
+    {
+        let source = Rc::new(RefCell::new(UserSource { source, callback, token: None }));
+        let weak_source = Rc::downgrade(&source);
+        let ready_source = ReadySource { source };
+
+        let callback_source = weak_source.clone();
+        let result = self.event_loop.handle().insert_source(
+            ready_source,
+            move |(readiness, token), _, state: &mut RuntimeState<T>| {
+                let source = callback_source.clone();
+                state.callbacks.push_winit(move |user, winit| {
+                    // The source could be removed by the previous callback.
+                    if let Some(source) = source.upgrade() {
+                        UserSource::process(&source, readiness, token, user, winit);
+                    }
+                });
+            },
+        );
+
+        match result {
+            Ok(token) => {
+                if let Some(source) = weak_source.upgrade() {
+                    source.borrow_mut().token = Some(token);
+                }
+                Ok(token)
+            },
+            Err(InsertError { inserted, error }) => {
+                let inserted = Rc::into_inner(inserted.source).unwrap().into_inner().source;
+                Err(InsertError { inserted, error })
+            },
+        }
+    }
+
+    /// Remove the event source previously inserted with
+    /// [`EventLoop::insert_source`].
+    pub fn remove_source(&self, token: RegistrationToken) {
+        self.event_loop.handle().remove(token);
     }
 }
 
 impl<T: Application + 'static + MyCoolTrait> EventLoop<T> {
-    /// This sets up handelr for `MyCoolTrait` but doesn't force it through-out the codebase.
+    /// This sets up handelr for `MyCoolTrait` but doesn't force it through-out
+    /// the codebase.
     pub fn register_my_cool_trait_handler(&mut self) {
         self.state.vtable.foo = Some(T::foo);
     }
@@ -143,14 +443,219 @@ unsafe impl<T: Application + 'static> HasRawDisplayHandle05 for EventLoop<T> {
 }
 
 /// Runtime state passed around.
+///
+/// The user state is never stored here, it's passed to the callbacks once
+/// the dispatch is over.
 pub(crate) struct RuntimeState<T: Application + 'static> {
-    /// The user state we're using during the runtime.
-    pub user: Option<&'static mut T>,
-
     /// The state of the winit.
     pub winit: WinitState<T>,
 
     pub vtable: Vtable<T>,
+
+    /// The moment the loop started waiting along with the control flow used
+    /// for it. Taken once `new_events` is sent for the current iteration.
+    pub wait_start: Option<(Instant, ControlFlow)>,
+
+    /// The callbacks queued by the handlers during the dispatch.
+    pub callbacks: DeferredCallbacks<T>,
+}
+
+impl<T: Application + 'static> RuntimeState<T> {
+    /// Send `new_events` to the user if it wasn't sent for the current loop
+    /// iteration yet.
+    pub(crate) fn dispatch_new_events(&mut self, user: &mut T) {
+        let (start, control_flow) = match self.wait_start.take() {
+            Some(wait_start) => wait_start,
+            None => return,
+        };
+
+        let start_cause = match control_flow {
+            ControlFlow::Poll => StartCause::Poll,
+            ControlFlow::Wait => StartCause::WaitCancelled { start, requested_resume: None },
+            ControlFlow::WaitUntil(deadline) if self.winit.clock.now() < deadline => {
+                StartCause::WaitCancelled { start, requested_resume: Some(deadline) }
+            },
+            ControlFlow::WaitUntil(deadline) => {
+                StartCause::ResumeTimeReached { start, requested_resume: deadline }
+            },
+        };
+
+        user.new_events(&mut self.winit, start_cause);
+    }
+}
+
+/// The source inserted by the user along with its callback.
+struct UserSource<S, F> {
+    source: S,
+
+    callback: F,
+
+    /// The registration of the source, set once it's inserted.
+    token: Option<RegistrationToken>,
+}
+
+impl<S: EventSource, F> UserSource<S, F> {
+    /// Process the events the source has reported during the dispatch.
+    fn process<T>(
+        this: &RefCell<Self>,
+        readiness: Readiness,
+        token: Token,
+        user: &mut T,
+        winit: &mut WinitState<T>,
+    ) where
+        T: Application + 'static,
+        F: FnMut(S::Event, &mut S::Metadata, &mut T, &mut dyn EventLoopHandle) -> S::Ret,
+    {
+        let mut this = this.borrow_mut();
+        let UserSource { source, callback, token: registration } = &mut *this;
+        let registration = match registration {
+            Some(registration) => *registration,
+            None => return,
+        };
+
+        let result = source
+            .process_events(readiness, token, |event, metadata| {
+                callback(event, metadata, user, &mut *winit)
+            })
+            .map_err(|error| calloop::Error::OtherError(error.into()));
+
+        // The loop needs the source to update its registration.
+        drop(this);
+
+        let result = match result {
+            Ok(PostAction::Continue) => Ok(()),
+            Ok(PostAction::Reregister) => winit.loop_handle.update(&registration),
+            Ok(PostAction::Disable) => winit.loop_handle.disable(&registration),
+            Ok(PostAction::Remove) => {
+                winit.loop_handle.remove(registration);
+                Ok(())
+            },
+            Err(error) => Err(error),
+        };
+
+        if let Err(error) = result {
+            log::warn!("Failed to process the event source: {error}");
+        }
+    }
+}
+
+/// The part of the user source inserted into calloop.
+///
+/// Calloop dispatches without the application state, so it only learns the
+/// readiness of the source. The source is processed right after the
+/// dispatch, when the application state is at hand.
+struct ReadySource<S, F> {
+    source: Rc<RefCell<UserSource<S, F>>>,
+}
+
+impl<S: EventSource, F> EventSource for ReadySource<S, F> {
+    type Error = Infallible;
+    type Event = (Readiness, Token);
+    type Metadata = ();
+    type Ret = ();
+
+    const NEEDS_EXTRA_LIFECYCLE_EVENTS: bool = S::NEEDS_EXTRA_LIFECYCLE_EVENTS;
+
+    fn process_events<C>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: C,
+    ) -> Result<PostAction, Self::Error>
+    where
+        C: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        callback((readiness, token), &mut ());
+        Ok(PostAction::Continue)
+    }
+
+    fn register(
+        &mut self,
+        poll: &mut Poll,
+        token_factory: &mut TokenFactory,
+    ) -> calloop::Result<()> {
+        self.source.borrow_mut().source.register(poll, token_factory)
+    }
+
+    fn reregister(
+        &mut self,
+        poll: &mut Poll,
+        token_factory: &mut TokenFactory,
+    ) -> calloop::Result<()> {
+        self.source.borrow_mut().source.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.source.borrow_mut().source.unregister(poll)
+    }
+
+    fn before_sleep(&mut self) -> calloop::Result<Option<(Readiness, Token)>> {
+        self.source.borrow_mut().source.before_sleep()
+    }
+
+    fn before_handle_events(&mut self, events: EventIterator<'_>) {
+        self.source.borrow_mut().source.before_handle_events(events)
+    }
+}
+
+type DeferredCallback<T> = Box<dyn FnOnce(&mut T, &mut WinitState<T>)>;
+
+/// The callbacks to the application queued while the loop is dispatching.
+///
+/// The Wayland and calloop handlers only have access to the backend state,
+/// the queued callbacks are run with the application state right after the
+/// dispatch, in the order they were queued.
+pub(crate) struct DeferredCallbacks<T: Application + 'static> {
+    queue: VecDeque<DeferredCallback<T>>,
+}
+
+impl<T: Application + 'static> DeferredCallbacks<T> {
+    /// Queue the `callback` to run with the application state.
+    pub(crate) fn push<F>(&mut self, callback: F)
+    where
+        F: FnOnce(&mut T, &mut dyn EventLoopHandle) + 'static,
+    {
+        self.queue.push_back(Box::new(move |user, winit| callback(user, winit)));
+    }
+
+    /// Queue the `callback` which needs the backend state itself.
+    pub(crate) fn push_winit<F>(&mut self, callback: F)
+    where
+        F: FnOnce(&mut T, &mut WinitState<T>) + 'static,
+    {
+        self.queue.push_back(Box::new(callback));
+    }
+
+    /// Queue the `callback` for the window, dropped if the window is destroyed
+    /// by the time it runs.
+    pub(crate) fn push_window<F>(&mut self, window_id: WindowId, callback: F)
+    where
+        F: FnOnce(&mut T, &mut WinitState<T>) + 'static,
+    {
+        self.push_winit(move |user, winit| {
+            if winit.windows.contains_key(&window_id) {
+                callback(user, winit);
+            }
+        });
+    }
+
+    fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    fn pop(&mut self) -> Option<DeferredCallback<T>> {
+        self.queue.pop_front()
+    }
+
+    fn clear(&mut self) {
+        self.queue.clear();
+    }
+}
+
+impl<T: Application + 'static> Default for DeferredCallbacks<T> {
+    fn default() -> Self {
+        Self { queue: VecDeque::new() }
+    }
 }
 
 pub struct Vtable<T: Application + 'static> {
@@ -165,16 +670,368 @@ impl<T: Application + 'static> Default for Vtable<T> {
 
 pub struct EventLoopProxy {
     ping: Ping,
+
+    /// Whether the user has requested a wakeup.
+    wakeup: AtomicBool,
+
+    /// The messages sent by the user.
+    messages: MessageQueue,
 }
 
 impl EventLoopProxy {
     fn new(ping: Ping) -> Self {
-        Self { ping }
+        Self { ping, wakeup: AtomicBool::new(false), messages: MessageQueue::new() }
     }
 }
 
 impl CoreEventLoopProxy for EventLoopProxy {
     fn wakeup(&self) {
+        self.wakeup.store(true, Ordering::Relaxed);
         self.ping.ping();
     }
+
+    fn send(&self, message: Message) {
+        self.messages.push(message);
+        self.ping.ping();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use calloop::channel;
+    use sctk::reexports::client::Proxy;
+    use sctk::shell::WaylandSurface;
+
+    use std::cell::RefCell;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use winit_core::dpi::PhysicalSize;
+    use winit_core::error::EventLoopError;
+    use winit_core::event_loop::clock::ManualClock;
+    use winit_core::event_loop::pump_events::{EventLoopPumpEvents, PumpStatus};
+    use winit_core::event_loop::{EventLoopHandle, EventLoopRequests, ShutdownPolicy, Timer};
+    use winit_core::test_support::conformance::{self, ConformanceApp, Harness};
+    use winit_core::window::{WindowAttributes, WindowId};
+
+    use crate::test_compositor::{self, Config, TestApp, TestCompositor, TestEvent, TestHandler};
+    use crate::window::CONFIGURE_TIMEOUT;
+
+    use super::EventLoop;
+
+    struct WaylandHarness {
+        event_loop: EventLoop<ConformanceApp>,
+        compositor: TestCompositor,
+    }
+
+    impl Harness for WaylandHarness {
+        type EventLoop = EventLoop<ConformanceApp>;
+
+        fn event_loop(&mut self) -> &mut Self::EventLoop {
+            &mut self.event_loop
+        }
+
+        fn window_id(&mut self, index: usize) -> WindowId {
+            // The surfaces are numbered in the order the windows were created.
+            let mut windows: Vec<_> = self
+                .event_loop
+                .winit()
+                .windows
+                .iter()
+                .map(|(window_id, window)| {
+                    (window.window.wl_surface().id().protocol_id(), *window_id)
+                })
+                .collect();
+            windows.sort_unstable_by_key(|(protocol_id, _)| *protocol_id);
+            windows[index].1
+        }
+
+        fn configure(&mut self, index: usize) {
+            self.compositor.configure(index, None, Vec::new());
+        }
+
+        fn close(&mut self, index: usize) {
+            self.compositor.close(index);
+        }
+    }
+
+    #[test]
+    fn conformance() {
+        let (compositor, event_loop) = test_compositor::spawn_event_loop(Config::default());
+        conformance::run(&mut WaylandHarness { event_loop, compositor });
+    }
+
+    #[test]
+    fn manual_clock() {
+        let (_compositor, mut event_loop) = test_compositor::spawn_event_loop(Config::default());
+        let clock = Arc::new(ManualClock::new());
+        event_loop.set_clock(clock.clone());
+        let mut app = TestApp::default();
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        let timer_id = event_loop.winit().add_timer(Timer::Interval(Duration::from_secs(60)));
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(app.take_events(), []);
+
+        clock.advance(Duration::from_secs(60));
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(app.take_events(), [TestEvent::TimerFired(timer_id)]);
+
+        event_loop.winit().cancel_timer(timer_id);
+        clock.advance(Duration::from_secs(60));
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(app.take_events(), []);
+    }
+
+    #[test]
+    fn custom_source() {
+        let (compositor, mut event_loop) = test_compositor::spawn_event_loop(Config::default());
+        let mut app = TestApp::new(vec![WindowAttributes::default()]);
+        test_compositor::pump(&mut event_loop, &mut app);
+        compositor.configure(0, Some((320, 240)), Vec::new());
+        test_compositor::pump(&mut event_loop, &mut app);
+        let window_id = *event_loop.winit().windows.keys().next().unwrap();
+        app.take_events();
+
+        let (sender, channel) = channel::channel();
+        let token = event_loop
+            .insert_source(channel, move |event, _, app: &mut TestApp, loop_handle| {
+                if let channel::Event::Msg(message) = event {
+                    app.events.push(TestEvent::Message(message));
+                    loop_handle.destroy_window(window_id);
+                }
+            })
+            .unwrap();
+
+        sender.send(1).unwrap();
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(app.take_events(), [TestEvent::Message(1), TestEvent::Destroyed(window_id)]);
+
+        event_loop.remove_source(token);
+        sender.send(2).unwrap_err();
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(app.take_events(), []);
+    }
+
+    #[test]
+    fn configure_timeout() {
+        let (compositor, mut event_loop) = test_compositor::spawn_event_loop(Config::default());
+        let clock = Arc::new(ManualClock::new());
+        event_loop.set_clock(clock.clone());
+        let mut app = TestApp::new(vec![WindowAttributes::default()]);
+        test_compositor::pump(&mut event_loop, &mut app);
+        let window_id = *event_loop.winit().windows.keys().next().unwrap();
+
+        clock.advance(CONFIGURE_TIMEOUT - Duration::from_millis(1));
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(app.take_events(), []);
+
+        clock.advance(Duration::from_millis(1));
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(app.take_events(), [TestEvent::CreateFailed(window_id)]);
+        assert!(event_loop.winit().get_pending_window(window_id).is_none());
+        assert!(compositor.toplevel(0).destroyed);
+
+        // The late configure is ignored.
+        compositor.configure(0, Some((640, 480)), Vec::new());
+        clock.advance(CONFIGURE_TIMEOUT);
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(app.take_events(), []);
+    }
+
+    #[test]
+    fn configure_timeout_exits_on_last_window() {
+        let (_compositor, mut event_loop) = test_compositor::spawn_event_loop(Config::default());
+        let clock = Arc::new(ManualClock::new());
+        event_loop.set_clock(clock.clone());
+        event_loop.winit().set_shutdown_policy(ShutdownPolicy::ExitOnLastWindowDestroyed);
+        let mut app = TestApp::new(vec![WindowAttributes::default()]);
+        assert_eq!(test_compositor::pump(&mut event_loop, &mut app), PumpStatus::Continue);
+        let window_id = *event_loop.winit().windows.keys().next().unwrap();
+
+        clock.advance(CONFIGURE_TIMEOUT);
+        let status = test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(app.take_events(), [TestEvent::CreateFailed(window_id), TestEvent::LoopExiting]);
+        assert_eq!(status, PumpStatus::Exit(0));
+    }
+
+    #[test]
+    fn window_handler() {
+        let (compositor, mut event_loop) = test_compositor::spawn_event_loop(Config::default());
+        let mut app = TestApp::new(vec![WindowAttributes::default()]);
+        test_compositor::pump(&mut event_loop, &mut app);
+        let app_window = *event_loop.winit().windows.keys().next().unwrap();
+
+        let handler = Rc::new(RefCell::new(TestApp::default()));
+        let handler_window = event_loop
+            .winit()
+            .create_window_with_handler(
+                &WindowAttributes::default(),
+                Box::new(TestHandler(handler.clone())),
+            )
+            .unwrap();
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        compositor.configure(0, Some((320, 240)), Vec::new());
+        compositor.configure(1, Some((640, 480)), Vec::new());
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(app.take_events(), [
+            TestEvent::Created(app_window),
+            TestEvent::ScaleFactorChanged(app_window, 1.),
+            TestEvent::Resized(app_window, PhysicalSize::new(320, 240)),
+            TestEvent::RedrawRequested(app_window),
+        ]);
+        assert_eq!(handler.borrow_mut().take_events(), [
+            TestEvent::Created(handler_window),
+            TestEvent::ScaleFactorChanged(handler_window, 1.),
+            TestEvent::Resized(handler_window, PhysicalSize::new(640, 480)),
+            TestEvent::RedrawRequested(handler_window),
+        ]);
+
+        compositor.close(1);
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(app.take_events(), []);
+        assert_eq!(handler.borrow_mut().take_events(), [
+            TestEvent::CloseRequested(handler_window),
+            TestEvent::Destroyed(handler_window),
+        ]);
+
+        // The handler is dropped with its window.
+        assert_eq!(Rc::strong_count(&handler), 1);
+    }
+
+    #[test]
+    fn window_handler_destroys_window() {
+        let (compositor, mut event_loop) = test_compositor::spawn_event_loop(Config::default());
+        let mut app = TestApp::default();
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        let handler = Rc::new(RefCell::new(TestApp::default()));
+        handler.borrow_mut().destroy_on_resized = true;
+        let window_id = event_loop
+            .winit()
+            .create_window_with_handler(
+                &WindowAttributes::default(),
+                Box::new(TestHandler(handler.clone())),
+            )
+            .unwrap();
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        compositor.configure(0, Some((320, 240)), Vec::new());
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(app.take_events(), []);
+        assert_eq!(handler.borrow_mut().take_events(), [
+            TestEvent::Created(window_id),
+            TestEvent::ScaleFactorChanged(window_id, 1.),
+            TestEvent::Resized(window_id, PhysicalSize::new(320, 240)),
+            TestEvent::Destroyed(window_id),
+        ]);
+        assert!(compositor.toplevel(0).destroyed);
+        assert_eq!(Rc::strong_count(&handler), 1);
+    }
+
+    #[test]
+    fn window_handler_configure_timeout() {
+        let (_compositor, mut event_loop) = test_compositor::spawn_event_loop(Config::default());
+        let clock = Arc::new(ManualClock::new());
+        event_loop.set_clock(clock.clone());
+        let mut app = TestApp::default();
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        let handler = Rc::new(RefCell::new(TestApp::default()));
+        let window_id = event_loop
+            .winit()
+            .create_window_with_handler(
+                &WindowAttributes::default(),
+                Box::new(TestHandler(handler.clone())),
+            )
+            .unwrap();
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        clock.advance(CONFIGURE_TIMEOUT);
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(app.take_events(), []);
+        assert_eq!(handler.borrow_mut().take_events(), [TestEvent::CreateFailed(window_id)]);
+        assert_eq!(Rc::strong_count(&handler), 1);
+    }
+
+    #[test]
+    fn zero_interval_timer() {
+        let (_compositor, mut event_loop) = test_compositor::spawn_event_loop(Config::default());
+        let clock = Arc::new(ManualClock::new());
+        event_loop.set_clock(clock.clone());
+        let mut app = TestApp::default();
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        // The interval is clamped instead of firing on every iteration.
+        let timer_id = event_loop.winit().add_timer(Timer::Interval(Duration::ZERO));
+        test_compositor::pump(&mut event_loop, &mut app);
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(app.take_events(), []);
+
+        clock.advance(Timer::MIN_INTERVAL);
+        test_compositor::pump(&mut event_loop, &mut app);
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(app.take_events(), [TestEvent::TimerFired(timer_id)]);
+    }
+
+    #[test]
+    fn timer_wakes_loop() {
+        let (_compositor, mut event_loop) = test_compositor::spawn_event_loop(Config::default());
+        let mut app = TestApp::default();
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        let start = Instant::now();
+        let deadline = start + Duration::from_millis(10);
+        let timer_id = event_loop.winit().add_timer(Timer::Deadline(deadline));
+        event_loop.pump_events(Some(Duration::from_secs(10)), &mut app).unwrap();
+        assert_eq!(app.take_events(), [TestEvent::TimerFired(timer_id)]);
+        assert!(Instant::now() >= deadline);
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn panicking_callback() {
+        let (compositor, mut event_loop) = test_compositor::spawn_event_loop(Config::default());
+        let mut app = TestApp::new(vec![WindowAttributes::default()]);
+        app.panic_on_created = true;
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        compositor.configure(0, Some((320, 240)), Vec::new());
+        let error = event_loop.pump_events(Some(Duration::ZERO), &mut app).unwrap_err();
+        match error {
+            EventLoopError::CallbackPanicked(panic) => {
+                assert!(panic.message().unwrap().starts_with("created"))
+            },
+            error => panic!("unexpected error {error:?}"),
+        }
+
+        // The windows are torn down without calling the user.
+        assert_eq!(app.take_events().len(), 1);
+        assert!(event_loop.winit().windows.is_empty());
+        assert!(compositor.toplevel(0).destroyed);
+        drop(app);
+
+        // The loop keeps nothing of the application which has panicked.
+        let mut app = TestApp::new(vec![WindowAttributes::default()]);
+        test_compositor::pump(&mut event_loop, &mut app);
+        compositor.configure(1, Some((640, 480)), Vec::new());
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert!(matches!(app.take_events().first(), Some(TestEvent::Created(_))));
+    }
+
+    #[test]
+    fn run_resumes_panic() {
+        let (compositor, mut event_loop) = test_compositor::spawn_event_loop(Config::default());
+        let mut app = TestApp::new(vec![WindowAttributes::default()]);
+        app.panic_on_created = true;
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        compositor.configure(0, Some((320, 240)), Vec::new());
+        let payload = panic::catch_unwind(AssertUnwindSafe(|| event_loop.run(app))).unwrap_err();
+        assert!(payload.downcast_ref::<String>().unwrap().starts_with("created"));
+        assert!(compositor.toplevel(0).destroyed);
+    }
 }
@@ -0,0 +1,209 @@
+//! Window icons through `xdg_toplevel_icon_v1`.
+
+use sctk::error::GlobalError;
+use sctk::globals::ProvidesBoundGlobal;
+use sctk::reexports::client::globals::{BindError, GlobalList};
+use sctk::reexports::client::protocol::wl_shm::{Format, WlShm};
+use sctk::reexports::client::{Connection, Dispatch, QueueHandle};
+use sctk::reexports::protocols::xdg::shell::client::xdg_toplevel::XdgToplevel;
+use sctk::shm::slot::{Buffer, SlotPool};
+
+use winit_core::application::Application;
+use winit_core::window::Icon;
+
+use crate::event_loop::RuntimeState;
+
+use self::protocol::client::xdg_toplevel_icon_manager_v1::{self, XdgToplevelIconManagerV1};
+use self::protocol::client::xdg_toplevel_icon_v1::{self, XdgToplevelIconV1};
+
+/// The bindings to `xdg_toplevel_icon_v1`, which the `wayland-protocols` we
+/// depend on doesn't have yet.
+pub(crate) mod protocol {
+    #![allow(dead_code, non_camel_case_types, unused_unsafe, unused_variables)]
+    #![allow(non_upper_case_globals, non_snake_case, unused_imports)]
+    #![allow(missing_docs, clippy::all)]
+
+    pub(crate) mod client {
+        use wayland_client;
+        use wayland_client::protocol::*;
+        use wayland_protocols::xdg::shell::client::*;
+
+        pub mod __interfaces {
+            use wayland_client::protocol::__interfaces::*;
+            use wayland_protocols::xdg::shell::client::__interfaces::*;
+            wayland_scanner::generate_interfaces!("protocols/xdg-toplevel-icon-v1.xml");
+        }
+        use self::__interfaces::*;
+
+        wayland_scanner::generate_client_code!("protocols/xdg-toplevel-icon-v1.xml");
+    }
+
+    #[cfg(test)]
+    pub(crate) mod server {
+        use wayland_protocols::xdg::shell::server::*;
+        use wayland_server;
+        use wayland_server::protocol::*;
+
+        use self::__interfaces::*;
+        pub use super::client::__interfaces;
+
+        wayland_scanner::generate_server_code!("protocols/xdg-toplevel-icon-v1.xml");
+    }
+}
+
+/// The `xdg_toplevel_icon_manager_v1` global.
+#[derive(Debug, Clone)]
+pub(crate) struct IconManager {
+    manager: XdgToplevelIconManagerV1,
+
+    /// The `wl_shm` to allocate the icon buffers from.
+    shm: WlShm,
+}
+
+impl IconManager {
+    pub(crate) fn bind<T: Application + 'static>(
+        globals: &GlobalList,
+        queue_handle: &QueueHandle<RuntimeState<T>>,
+        shm: WlShm,
+    ) -> Result<Self, BindError> {
+        let manager = globals.bind(queue_handle, 1..=1, ())?;
+        Ok(Self { manager, shm })
+    }
+
+    /// Upload the `icon` and set it on the `toplevel`, resetting it with
+    /// `None`.
+    ///
+    /// The returned icon must be kept alive while it's in use.
+    pub(crate) fn set_icon<T: Application + 'static>(
+        &self,
+        queue_handle: &QueueHandle<RuntimeState<T>>,
+        toplevel: &XdgToplevel,
+        icon: Option<&Icon>,
+    ) -> Option<ToplevelIcon> {
+        let icon = match icon {
+            Some(icon) => ToplevelIcon::new(&self.manager, &self.shm, queue_handle, icon),
+            None => {
+                self.manager.set_icon(toplevel, None);
+                return None;
+            },
+        };
+
+        self.manager.set_icon(toplevel, Some(&icon.icon));
+        Some(icon)
+    }
+}
+
+/// The icon uploaded to the compositor.
+pub(crate) struct ToplevelIcon {
+    icon: XdgToplevelIconV1,
+
+    /// The buffers must outlive the icon.
+    _buffers: Vec<Buffer>,
+    _pool: Option<SlotPool>,
+}
+
+impl ToplevelIcon {
+    fn new<T: Application + 'static>(
+        manager: &XdgToplevelIconManagerV1,
+        shm: &WlShm,
+        queue_handle: &QueueHandle<RuntimeState<T>>,
+        icon: &Icon,
+    ) -> Self {
+        let toplevel_icon = manager.create_icon(queue_handle, ());
+        if let Some(name) = icon.name() {
+            toplevel_icon.set_name(name.to_owned());
+        }
+
+        // The protocol accepts only square images, skip the rest.
+        let mut images = Vec::new();
+        for image in icon.images() {
+            if image.width() == image.height() {
+                images.push(image);
+            } else {
+                log::warn!(
+                    "Skipping the {}x{} window icon image, Wayland only accepts square icons",
+                    image.width(),
+                    image.height()
+                );
+            }
+        }
+
+        let len = images.iter().map(|image| image.rgba().len()).sum();
+
+        let mut pool = None;
+        if len > 0 {
+            pool = SlotPool::new(len, &BoundShm(shm.clone())).ok();
+        }
+
+        let mut buffers = Vec::new();
+        if let Some(pool) = pool.as_mut() {
+            for image in images {
+                let size = image.width() as i32;
+                let (buffer, canvas) =
+                    match pool.create_buffer(size, size, size * 4, Format::Argb8888) {
+                        Ok(buffer) => buffer,
+                        Err(_) => continue,
+                    };
+
+                rgba_to_argb8888(image.rgba(), canvas);
+                toplevel_icon.add_buffer(buffer.wl_buffer(), 1);
+                buffers.push(buffer);
+            }
+        }
+
+        Self { icon: toplevel_icon, _buffers: buffers, _pool: pool }
+    }
+}
+
+impl Drop for ToplevelIcon {
+    fn drop(&mut self) {
+        self.icon.destroy();
+    }
+}
+
+/// Convert the RGBA8 pixels into the premultiplied `wl_shm` ARGB8888, which
+/// is little endian.
+pub(crate) fn rgba_to_argb8888(rgba: &[u8], canvas: &mut [u8]) {
+    for (src, dst) in rgba.chunks_exact(4).zip(canvas.chunks_exact_mut(4)) {
+        let alpha = src[3] as u32;
+        let premultiply = |channel: u8| ((channel as u32 * alpha + 127) / 255) as u8;
+        dst[0] = premultiply(src[2]);
+        dst[1] = premultiply(src[1]);
+        dst[2] = premultiply(src[0]);
+        dst[3] = src[3];
+    }
+}
+
+/// The `wl_shm` bound by the state, to create pools away from it.
+pub(crate) struct BoundShm(pub(crate) WlShm);
+
+impl ProvidesBoundGlobal<WlShm, 1> for BoundShm {
+    fn bound_global(&self) -> Result<WlShm, GlobalError> {
+        Ok(self.0.clone())
+    }
+}
+
+impl<T: Application + 'static> Dispatch<XdgToplevelIconManagerV1, ()> for RuntimeState<T> {
+    fn event(
+        _: &mut Self,
+        _: &XdgToplevelIconManagerV1,
+        _: xdg_toplevel_icon_manager_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        // The preferred sizes are only a hint, we upload what the user gave us.
+    }
+}
+
+impl<T: Application + 'static> Dispatch<XdgToplevelIconV1, ()> for RuntimeState<T> {
+    fn event(
+        _: &mut Self,
+        _: &XdgToplevelIconV1,
+        _: xdg_toplevel_icon_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
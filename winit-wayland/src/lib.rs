@@ -2,29 +2,34 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
 
-use sctk::reexports::client::protocol::wl_output::WlOutput;
-use sctk::reexports::client::protocol::wl_surface::WlSurface;
-use sctk::reexports::client::Proxy;
+use wayland_backend::client::WaylandError;
 
 use winit_core::dpi::{LogicalSize, PhysicalSize};
-use winit_core::monitor::MonitorId;
-use winit_core::window::WindowId;
+use winit_core::error::{ConnectionLost, ProtocolError};
+
+pub use calloop;
 
 pub mod event_loop;
+mod icon;
 pub mod monitor;
+mod seat;
 pub mod state;
 pub mod window;
 
-/// Get the WindowId out of the surface.
-#[inline]
-pub(crate) fn make_wid(surface: &WlSurface) -> WindowId {
-    WindowId(surface.id().as_ptr() as u128)
-}
-
-/// Get the WindowId out of the surface.
-#[inline]
-pub(crate) fn make_mid(output: &WlOutput) -> MonitorId {
-    MonitorId(output.id().as_ptr() as u128)
+#[cfg(test)]
+mod test_compositor;
+
+/// Get the reason of the connection loss out of the Wayland error.
+pub(crate) fn connection_lost(error: WaylandError) -> ConnectionLost {
+    match error {
+        WaylandError::Protocol(error) => ConnectionLost::Protocol(ProtocolError {
+            interface: error.object_interface,
+            object_id: error.object_id,
+            code: error.code,
+            message: error.message,
+        }),
+        WaylandError::Io(error) => ConnectionLost::Io(error),
+    }
 }
 
 /// The default routine does floor, but we need round on Wayland.
@@ -1,3 +1,5 @@
+use std::sync::{Arc, Mutex};
+
 use sctk::reexports::client::protocol::wl_output::WlOutput;
 use sctk::reexports::client::Proxy;
 
@@ -12,18 +14,45 @@ use crate::event_loop::RuntimeState;
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Monitor {
+    id: MonitorId,
     pub(crate) output: WlOutput,
 }
 
 impl Monitor {
-    pub(crate) fn new(output: WlOutput) -> Self {
-        Self { output }
+    pub(crate) fn new(id: MonitorId, output: WlOutput) -> Self {
+        Self { id, output }
+    }
+}
+
+/// The ids of the known outputs.
+///
+/// Shared with the windows, so they can tell the monitor their surface is on.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct MonitorIds(Arc<Mutex<Vec<(WlOutput, MonitorId)>>>);
+
+impl MonitorIds {
+    pub(crate) fn insert(&self, output: WlOutput, monitor_id: MonitorId) {
+        self.0.lock().unwrap().push((output, monitor_id));
+    }
+
+    pub(crate) fn remove(&self, output: &WlOutput) {
+        self.0.lock().unwrap().retain(|(known, _)| known != output);
+    }
+
+    pub(crate) fn get(&self, output: &WlOutput) -> Option<MonitorId> {
+        let ids = self.0.lock().unwrap();
+        ids.iter().find(|(known, _)| known == output).map(|(_, monitor_id)| *monitor_id)
+    }
+
+    pub(crate) fn output(&self, monitor_id: MonitorId) -> Option<WlOutput> {
+        let ids = self.0.lock().unwrap();
+        ids.iter().find(|(_, known)| *known == monitor_id).map(|(output, _)| output.clone())
     }
 }
 
 impl CoreMonitor for Monitor {
     fn id(&self) -> MonitorId {
-        crate::make_mid(&self.output)
+        self.id
     }
 
     fn name(&self) -> Option<String> {
@@ -79,7 +108,11 @@ impl<T: Application + 'static> OutputHandler for RuntimeState<T> {
     }
 
     fn new_output(&mut self, _: &Connection, _: &QueueHandle<Self>, output: WlOutput) {
-        self.winit.monitors.push(Monitor::new(output));
+        // The outputs known when the loop is created are announced again once their
+        // info arrives.
+        if self.winit.monitors.iter().all(|monitor| monitor.output != output) {
+            self.winit.add_monitor(output);
+        }
     }
 
     fn update_output(&mut self, _: &Connection, _: &QueueHandle<Self>, updated: WlOutput) {
@@ -89,7 +122,75 @@ impl<T: Application + 'static> OutputHandler for RuntimeState<T> {
 
     fn output_destroyed(&mut self, _: &Connection, _: &QueueHandle<Self>, removed: WlOutput) {
         self.winit.monitors.retain(|monitor| monitor.output != removed);
+        self.winit.monitor_ids.remove(&removed);
     }
 }
 
 sctk::delegate_output!(@<T: Application + 'static> RuntimeState<T>);
+
+#[cfg(test)]
+mod tests {
+    use winit_core::dpi::{PhysicalPosition, PhysicalSize};
+    use winit_core::event_loop::EventLoopHandle;
+
+    use crate::test_compositor::{self, Config, OutputConfig, TestApp};
+
+    #[test]
+    fn output_info() {
+        let output = OutputConfig {
+            name: String::from("DP-1"),
+            position: (100, 200),
+            size: (2560, 1440),
+            refresh_millihertz: 144000,
+            scale: 1,
+        };
+        let config = Config { outputs: vec![output], ..Default::default() };
+        let (_compositor, mut event_loop) = test_compositor::spawn_event_loop::<TestApp>(config);
+
+        let monitors = event_loop.winit().monitors();
+        assert_eq!(monitors.len(), 1);
+
+        let monitor = monitors[0];
+        assert_eq!(monitor.name().as_deref(), Some("DP-1"));
+        assert_eq!(monitor.position(), PhysicalPosition::new(100, 200));
+        assert_eq!(monitor.size(), PhysicalSize::new(2560, 1440));
+        assert_eq!(monitor.refresh_rate_millihertz(), Some(144000));
+        assert_eq!(monitor.scale_factor(), 1.);
+    }
+
+    #[test]
+    fn hotplug() {
+        let (compositor, mut event_loop) = test_compositor::spawn_event_loop(Config::default());
+        let mut app = TestApp::default();
+
+        let output = OutputConfig { name: String::from("HDMI-1"), scale: 2, ..Default::default() };
+        let index = compositor.add_output(output);
+        // The output is bound on the first iteration, and announced once its properties
+        // arrive.
+        test_compositor::pump(&mut event_loop, &mut app);
+        compositor.with_state(|_| ());
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        let monitors = event_loop.winit().monitors();
+        assert_eq!(monitors.len(), 2);
+        assert_eq!(monitors[1].name().as_deref(), Some("HDMI-1"));
+        assert_eq!(monitors[1].scale_factor(), 2.);
+
+        let monitor_id = monitors[1].id();
+        compositor.remove_output(index);
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(event_loop.winit().monitors().len(), 1);
+        assert!(event_loop.winit().get_monitor(monitor_id).is_none());
+
+        // The output plugged back is a new monitor.
+        let output = OutputConfig { name: String::from("HDMI-1"), ..Default::default() };
+        compositor.add_output(output);
+        test_compositor::pump(&mut event_loop, &mut app);
+        compositor.with_state(|_| ());
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        let monitors = event_loop.winit().monitors();
+        assert_eq!(monitors.len(), 2);
+        assert_ne!(monitors[1].id(), monitor_id);
+    }
+}
@@ -0,0 +1,648 @@
+//! The seats and the pointers on them.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use sctk::reexports::client::protocol::wl_pointer::WlPointer;
+use sctk::reexports::client::protocol::wl_shm::{Format, WlShm};
+use sctk::reexports::client::protocol::wl_surface::WlSurface;
+use sctk::reexports::client::{Connection, Proxy, QueueHandle};
+use sctk::reexports::protocols::wp::cursor_shape::v1::client::wp_cursor_shape_device_v1::{
+    Shape, WpCursorShapeDeviceV1,
+};
+use sctk::reexports::protocols::wp::pointer_constraints::zv1::client::zwp_confined_pointer_v1::ZwpConfinedPointerV1;
+use sctk::reexports::protocols::wp::pointer_constraints::zv1::client::zwp_locked_pointer_v1::ZwpLockedPointerV1;
+use sctk::reexports::protocols::wp::pointer_constraints::zv1::client::zwp_pointer_constraints_v1::Lifetime;
+use sctk::reexports::protocols::wp::relative_pointer::zv1::client::zwp_relative_pointer_v1::ZwpRelativePointerV1;
+use sctk::seat::pointer::{PointerData, PointerEvent, PointerEventKind, PointerHandler};
+use sctk::seat::pointer_constraints::{PointerConstraintsHandler, PointerConstraintsState};
+use sctk::seat::relative_pointer::{RelativeMotionEvent, RelativePointerHandler};
+use sctk::shm::slot::{Buffer, SlotPool};
+use wayland_cursor::CursorTheme;
+
+use winit_core::application::Application;
+use winit_core::cursor::{Cursor, CursorGrabMode, CursorIcon, CustomCursor, CustomCursorSource};
+
+use crate::event_loop::RuntimeState;
+use crate::icon::{rgba_to_argb8888, BoundShm};
+
+/// The custom cursors registered with the loop, shared with the pointers.
+pub(crate) type CustomCursors = Arc<Mutex<HashMap<CustomCursor, CustomCursorSource>>>;
+
+/// The state of the seat.
+#[derive(Default)]
+pub(crate) struct WinitSeat {
+    /// The pointer, when the seat has one.
+    pub(crate) pointer: Option<Arc<WinitPointer>>,
+
+    /// The relative motion of the pointer, when the compositor supports
+    /// `zwp_relative_pointer_v1`.
+    pub(crate) relative_pointer: Option<ZwpRelativePointerV1>,
+}
+
+impl WinitSeat {
+    /// Drop the pointer along with its relative motion.
+    pub(crate) fn remove_pointer(&mut self) {
+        if let Some(relative_pointer) = self.relative_pointer.take() {
+            relative_pointer.destroy();
+        }
+
+        self.pointer = None;
+    }
+}
+
+impl Drop for WinitSeat {
+    fn drop(&mut self) {
+        self.remove_pointer();
+    }
+}
+
+/// The pointer of the seat along with the means to draw its cursor.
+pub(crate) struct WinitPointer {
+    pointer: WlPointer,
+
+    /// The device to set the cursor from the compositor theme, when the
+    /// compositor supports `wp_cursor_shape_v1`.
+    shape_device: Option<WpCursorShapeDeviceV1>,
+
+    /// The surface to attach the cursor from the xcursor theme or the custom
+    /// cursor to.
+    surface: WlSurface,
+
+    /// The xcursor themes shared by all the pointers.
+    themes: Arc<Mutex<CursorThemes>>,
+
+    /// The custom cursors registered with the loop.
+    custom_cursors: CustomCursors,
+
+    /// The buffers of the custom cursor currently shown.
+    custom_cursor: Mutex<Option<CustomCursorBuffers>>,
+
+    /// The constraint on the surface the pointer is over.
+    constraint: Mutex<Option<PointerConstraint>>,
+
+    connection: Connection,
+
+    shm: WlShm,
+}
+
+impl WinitPointer {
+    pub(crate) fn new(
+        pointer: WlPointer,
+        shape_device: Option<WpCursorShapeDeviceV1>,
+        surface: WlSurface,
+        themes: Arc<Mutex<CursorThemes>>,
+        custom_cursors: CustomCursors,
+        connection: Connection,
+        shm: WlShm,
+    ) -> Self {
+        Self {
+            pointer,
+            shape_device,
+            surface,
+            themes,
+            custom_cursors,
+            custom_cursor: Mutex::new(None),
+            constraint: Mutex::new(None),
+            connection,
+            shm,
+        }
+    }
+
+    pub(crate) fn wl_pointer(&self) -> &WlPointer {
+        &self.pointer
+    }
+
+    /// The serial of the enter on the surface the pointer is over.
+    fn enter_serial(&self) -> Option<u32> {
+        self.pointer.data::<PointerData>()?.latest_enter_serial()
+    }
+
+    /// Show the `cursor` for the pointer at the `scale_factor` of the window.
+    pub(crate) fn set_cursor(&self, cursor: Cursor, scale_factor: f64) {
+        match cursor {
+            Cursor::Icon(icon) => self.set_cursor_icon(icon, scale_factor),
+            Cursor::Custom(cursor) => self.set_custom_cursor(cursor, scale_factor),
+        }
+    }
+
+    /// Show the `icon` for the pointer, loading the xcursor theme for the
+    /// `scale_factor` of the window when the compositor can't do that.
+    fn set_cursor_icon(&self, icon: CursorIcon, scale_factor: f64) {
+        let serial = match self.enter_serial() {
+            Some(serial) => serial,
+            None => return,
+        };
+
+        *self.custom_cursor.lock().unwrap() = None;
+
+        if let Some(shape_device) = self.shape_device.as_ref() {
+            shape_device.set_shape(serial, cursor_icon_to_shape(icon));
+            return;
+        }
+
+        // NOTE: the buffer scale is integer, thus round up for fractional scales and
+        // let the compositor downscale the cursor.
+        let scale = (scale_factor.ceil() as i32).max(1);
+        let mut themes = self.themes.lock().unwrap();
+        let cursor = match themes.get_cursor(&self.connection, &self.shm, icon, scale as u32) {
+            Some(cursor) => cursor,
+            None => return,
+        };
+
+        let image = &cursor[0];
+        let (width, height) = image.dimensions();
+        let (hotspot_x, hotspot_y) = image.hotspot();
+
+        self.surface.set_buffer_scale(scale);
+        self.surface.attach(Some(image), 0, 0);
+        if self.surface.version() >= 4 {
+            self.surface.damage_buffer(0, 0, width as i32, height as i32);
+        } else {
+            self.surface.damage(0, 0, width as i32 / scale, height as i32 / scale);
+        }
+        self.surface.commit();
+
+        self.pointer.set_cursor(
+            serial,
+            Some(&self.surface),
+            hotspot_x as i32 / scale,
+            hotspot_y as i32 / scale,
+        );
+    }
+
+    /// Show the custom `cursor` for the pointer, rendered for the
+    /// `scale_factor` of the window.
+    ///
+    /// The cursors unknown to the loop are shown as [`CursorIcon::Default`].
+    fn set_custom_cursor(&self, cursor: CustomCursor, scale_factor: f64) {
+        if self.enter_serial().is_none() {
+            return;
+        }
+
+        // NOTE: the buffer scale is integer, thus round up for fractional scales and
+        // let the compositor downscale the cursor.
+        let scale = (scale_factor.ceil() as i32).max(1);
+        let mut custom_cursor = self.custom_cursor.lock().unwrap();
+        let rendered = custom_cursor
+            .as_ref()
+            .is_some_and(|buffers| buffers.cursor == cursor && buffers.scale == scale);
+        if !rendered {
+            let custom_cursors = self.custom_cursors.lock().unwrap();
+            *custom_cursor = custom_cursors
+                .get(&cursor)
+                .and_then(|source| CustomCursorBuffers::new(&self.shm, cursor, source, scale));
+        }
+
+        match custom_cursor.as_mut() {
+            Some(buffers) => {
+                buffers.restart();
+                self.show_frame(buffers);
+            },
+            None => {
+                drop(custom_cursor);
+                self.set_cursor_icon(CursorIcon::Default, scale_factor);
+            },
+        }
+    }
+
+    /// Attach the current frame of the custom cursor to the cursor surface.
+    fn show_frame(&self, buffers: &CustomCursorBuffers) {
+        let serial = match self.enter_serial() {
+            Some(serial) => serial,
+            None => return,
+        };
+
+        let frame = &buffers.frames[buffers.frame];
+        self.surface.set_buffer_scale(buffers.scale);
+        self.surface.attach(Some(frame.buffer.wl_buffer()), 0, 0);
+        if self.surface.version() >= 4 {
+            self.surface.damage_buffer(0, 0, frame.size.0, frame.size.1);
+        } else {
+            self.surface.damage(0, 0, frame.size.0 / buffers.scale, frame.size.1 / buffers.scale);
+        }
+        self.surface.commit();
+
+        // NOTE: the frames could have different hotspots, so set the cursor again.
+        self.pointer.set_cursor(serial, Some(&self.surface), frame.hotspot.0, frame.hotspot.1);
+    }
+
+    /// Hide the cursor while the pointer is over the current surface.
+    pub(crate) fn hide_cursor(&self) {
+        if let Some(serial) = self.enter_serial() {
+            *self.custom_cursor.lock().unwrap() = None;
+            self.pointer.set_cursor(serial, None, 0, 0);
+        }
+    }
+
+    /// The time to show the next frame of the animated custom cursor.
+    pub(crate) fn next_frame_deadline(&self) -> Option<Instant> {
+        self.custom_cursor.lock().unwrap().as_ref().and_then(|buffers| buffers.deadline)
+    }
+
+    /// Advance the animated custom cursor to `now`, starting the animation
+    /// when it was just set.
+    pub(crate) fn animate(&self, now: Instant) {
+        let mut custom_cursor = self.custom_cursor.lock().unwrap();
+        let buffers = match custom_cursor.as_mut() {
+            Some(buffers) if buffers.playing => buffers,
+            _ => return,
+        };
+
+        match buffers.deadline {
+            None => buffers.deadline = Some(now + buffers.frames[buffers.frame].duration),
+            Some(deadline) if deadline <= now => {
+                buffers.frame = (buffers.frame + 1) % buffers.frames.len();
+
+                // Don't try to catch up when the loop was away for too long.
+                let deadline = deadline + buffers.frames[buffers.frame].duration;
+                let deadline = if deadline <= now {
+                    now + buffers.frames[buffers.frame].duration
+                } else {
+                    deadline
+                };
+                buffers.deadline = Some(deadline);
+                self.show_frame(buffers);
+            },
+            Some(_) => (),
+        }
+    }
+
+    /// Confine or lock the pointer to the `surface` it's over according to
+    /// the `mode`, replacing the previous constraint.
+    pub(crate) fn set_grab<T: Application + 'static>(
+        &self,
+        constraints: &PointerConstraintsState,
+        surface: &WlSurface,
+        mode: CursorGrabMode,
+        queue_handle: &QueueHandle<RuntimeState<T>>,
+    ) {
+        let mut constraint = self.constraint.lock().unwrap();
+        if let Some(constraint) = constraint.take() {
+            constraint.destroy();
+        }
+
+        // NOTE: the persistent constraint is reactivated by the compositor once it was
+        // lifted, for example on the focus loss, unlike the oneshot one.
+        *constraint = match mode {
+            CursorGrabMode::None => None,
+            CursorGrabMode::Confined => constraints
+                .confine_pointer(surface, &self.pointer, None, Lifetime::Persistent, queue_handle)
+                .ok()
+                .map(PointerConstraint::Confined),
+            CursorGrabMode::Locked => constraints
+                .lock_pointer(surface, &self.pointer, None, Lifetime::Persistent, queue_handle)
+                .ok()
+                .map(PointerConstraint::Locked),
+        };
+    }
+
+    /// Release the constraint once the pointer leaves the surface.
+    pub(crate) fn release_grab(&self) {
+        if let Some(constraint) = self.constraint.lock().unwrap().take() {
+            constraint.destroy();
+        }
+    }
+
+    /// Hint where to put the locked pointer once it's unlocked, in the surface
+    /// local coordinates.
+    pub(crate) fn set_lock_position_hint(&self, x: f64, y: f64) {
+        if let Some(PointerConstraint::Locked(locked_pointer)) =
+            self.constraint.lock().unwrap().as_ref()
+        {
+            locked_pointer.set_cursor_position_hint(x, y);
+        }
+    }
+
+    /// Stop animating the custom cursor once the pointer leaves the window.
+    pub(crate) fn pause_animation(&self) {
+        if let Some(buffers) = self.custom_cursor.lock().unwrap().as_mut() {
+            buffers.playing = false;
+            buffers.deadline = None;
+        }
+    }
+}
+
+/// The constraint from `zwp_pointer_constraints_v1`.
+enum PointerConstraint {
+    Confined(ZwpConfinedPointerV1),
+    Locked(ZwpLockedPointerV1),
+}
+
+impl PointerConstraint {
+    fn destroy(self) {
+        match self {
+            Self::Confined(confined_pointer) => confined_pointer.destroy(),
+            Self::Locked(locked_pointer) => locked_pointer.destroy(),
+        }
+    }
+}
+
+/// The custom cursor rendered for the buffer scale.
+struct CustomCursorBuffers {
+    cursor: CustomCursor,
+    scale: i32,
+    frames: Vec<CustomCursorFrame>,
+
+    /// The frame currently shown.
+    frame: usize,
+
+    /// Whether the cursor is animated and shown.
+    playing: bool,
+
+    /// The time to show the next frame, `None` until the animation starts.
+    deadline: Option<Instant>,
+
+    /// The buffers must not outlive the pool.
+    _pool: SlotPool,
+}
+
+struct CustomCursorFrame {
+    buffer: Buffer,
+
+    /// The size of the buffer.
+    size: (i32, i32),
+
+    /// The hotspot in the surface local coordinates.
+    hotspot: (i32, i32),
+
+    duration: Duration,
+}
+
+impl CustomCursorBuffers {
+    /// Render the frames of the `source` upscaled for the buffer `scale`.
+    fn new(
+        shm: &WlShm,
+        cursor: CustomCursor,
+        source: &CustomCursorSource,
+        scale: i32,
+    ) -> Option<Self> {
+        let len = source
+            .frames()
+            .iter()
+            .map(|(image, _)| image.rgba().len() * (scale * scale) as usize)
+            .sum();
+        let mut pool = SlotPool::new(len, &BoundShm(shm.clone())).ok()?;
+
+        let mut frames = Vec::new();
+        for (image, duration) in source.frames() {
+            let width = image.width() as i32;
+            let height = image.height() as i32;
+            let size = (width * scale, height * scale);
+            let (buffer, canvas) =
+                pool.create_buffer(size.0, size.1, size.0 * 4, Format::Argb8888).ok()?;
+
+            let rgba = upscale(image.rgba(), width as usize, scale as usize);
+            rgba_to_argb8888(&rgba, canvas);
+
+            let (hotspot_x, hotspot_y) = image.hotspot();
+            frames.push(CustomCursorFrame {
+                buffer,
+                size,
+                hotspot: (hotspot_x as i32, hotspot_y as i32),
+                duration: *duration,
+            });
+        }
+
+        Some(Self { cursor, scale, frames, frame: 0, playing: false, deadline: None, _pool: pool })
+    }
+
+    /// Show the cursor from the first frame.
+    fn restart(&mut self) {
+        self.frame = 0;
+        self.playing = self.frames.len() > 1;
+        self.deadline = None;
+    }
+}
+
+/// Upscale the RGBA8 image `scale` times with the nearest neighbour, which
+/// keeps the cursors crisp.
+fn upscale(rgba: &[u8], width: usize, scale: usize) -> Vec<u8> {
+    if scale == 1 {
+        return rgba.to_vec();
+    }
+
+    let mut scaled = Vec::with_capacity(rgba.len() * scale * scale);
+    for row in rgba.chunks_exact(width * 4) {
+        let mut scaled_row = Vec::with_capacity(row.len() * scale);
+        for pixel in row.chunks_exact(4) {
+            for _ in 0..scale {
+                scaled_row.extend_from_slice(pixel);
+            }
+        }
+
+        for _ in 0..scale {
+            scaled.extend_from_slice(&scaled_row);
+        }
+    }
+
+    scaled
+}
+
+impl Drop for WinitPointer {
+    fn drop(&mut self) {
+        self.release_grab();
+
+        if let Some(shape_device) = self.shape_device.take() {
+            shape_device.destroy();
+        }
+
+        if self.pointer.version() >= 3 {
+            self.pointer.release();
+        }
+
+        self.surface.destroy();
+    }
+}
+
+/// The xcursor theme from `XCURSOR_THEME` and `XCURSOR_SIZE`, loaded for
+/// every buffer scale in use.
+pub(crate) struct CursorThemes {
+    name: String,
+
+    /// The size of the cursor at scale 1.
+    size: u32,
+
+    themes: HashMap<u32, CursorTheme>,
+}
+
+impl CursorThemes {
+    pub(crate) fn from_env() -> Self {
+        let name = env::var("XCURSOR_THEME").unwrap_or_else(|_| String::from("default"));
+        let size = env::var("XCURSOR_SIZE").ok().and_then(|size| size.parse().ok()).unwrap_or(24);
+        Self { name, size, themes: HashMap::new() }
+    }
+
+    /// The cursor for the `icon`, trying its alternative names when the
+    /// theme lacks the CSS one.
+    fn get_cursor(
+        &mut self,
+        connection: &Connection,
+        shm: &WlShm,
+        icon: CursorIcon,
+        scale: u32,
+    ) -> Option<&wayland_cursor::Cursor> {
+        let theme = match self.themes.entry(scale) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let theme = CursorTheme::load_from_name(
+                    connection,
+                    shm.clone(),
+                    &self.name,
+                    self.size * scale,
+                )
+                .ok()?;
+                entry.insert(theme)
+            },
+        };
+
+        let name = [icon.name()]
+            .into_iter()
+            .chain(icon.alt_names().iter().copied())
+            .find(|name| theme.get_cursor(name).is_some())?;
+        theme.get_cursor(name)
+    }
+}
+
+impl<T: Application + 'static> PointerHandler for RuntimeState<T> {
+    fn pointer_frame(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        pointer: &WlPointer,
+        events: &[PointerEvent],
+    ) {
+        let winit = &mut self.winit;
+        let pointer = match winit
+            .seats
+            .values()
+            .filter_map(|seat| seat.pointer.as_ref())
+            .find(|seat_pointer| seat_pointer.wl_pointer() == pointer)
+        {
+            Some(pointer) => pointer.clone(),
+            None => return,
+        };
+
+        for event in events {
+            let window = match winit.window_id(&event.surface) {
+                Some(window_id) => winit.windows.get_mut(&window_id).unwrap(),
+                None => continue,
+            };
+
+            match event.kind {
+                PointerEventKind::Enter { .. } => window.pointer_entered(&pointer),
+                PointerEventKind::Leave { .. } => {
+                    pointer.pause_animation();
+                    window.pointer_left(&pointer);
+                },
+                _ => (),
+            }
+        }
+    }
+}
+
+impl<T: Application + 'static> RelativePointerHandler for RuntimeState<T> {
+    fn relative_pointer_motion(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &ZwpRelativePointerV1,
+        _: &WlPointer,
+        event: RelativeMotionEvent,
+    ) {
+        // The games want the motion as is, without the pointer acceleration.
+        let delta = event.delta_unaccel;
+        self.callbacks.push(move |user, loop_handle| {
+            if let Some(handler) = user.device_events_handelr() {
+                handler.mouse_motion(loop_handle, delta);
+            }
+        });
+    }
+}
+
+// The constraints are only requested, there's nothing to do when the
+// compositor activates them.
+impl<T: Application + 'static> PointerConstraintsHandler for RuntimeState<T> {
+    fn confined(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &ZwpConfinedPointerV1,
+        _: &WlSurface,
+        _: &WlPointer,
+    ) {
+    }
+
+    fn unconfined(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &ZwpConfinedPointerV1,
+        _: &WlSurface,
+        _: &WlPointer,
+    ) {
+    }
+
+    fn locked(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &ZwpLockedPointerV1,
+        _: &WlSurface,
+        _: &WlPointer,
+    ) {
+    }
+
+    fn unlocked(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &ZwpLockedPointerV1,
+        _: &WlSurface,
+        _: &WlPointer,
+    ) {
+    }
+}
+
+fn cursor_icon_to_shape(icon: CursorIcon) -> Shape {
+    match icon {
+        CursorIcon::Default => Shape::Default,
+        CursorIcon::ContextMenu => Shape::ContextMenu,
+        CursorIcon::Help => Shape::Help,
+        CursorIcon::Pointer => Shape::Pointer,
+        CursorIcon::Progress => Shape::Progress,
+        CursorIcon::Wait => Shape::Wait,
+        CursorIcon::Cell => Shape::Cell,
+        CursorIcon::Crosshair => Shape::Crosshair,
+        CursorIcon::Text => Shape::Text,
+        CursorIcon::VerticalText => Shape::VerticalText,
+        CursorIcon::Alias => Shape::Alias,
+        CursorIcon::Copy => Shape::Copy,
+        CursorIcon::Move => Shape::Move,
+        CursorIcon::NoDrop => Shape::NoDrop,
+        CursorIcon::NotAllowed => Shape::NotAllowed,
+        CursorIcon::Grab => Shape::Grab,
+        CursorIcon::Grabbing => Shape::Grabbing,
+        CursorIcon::EResize => Shape::EResize,
+        CursorIcon::NResize => Shape::NResize,
+        CursorIcon::NeResize => Shape::NeResize,
+        CursorIcon::NwResize => Shape::NwResize,
+        CursorIcon::SResize => Shape::SResize,
+        CursorIcon::SeResize => Shape::SeResize,
+        CursorIcon::SwResize => Shape::SwResize,
+        CursorIcon::WResize => Shape::WResize,
+        CursorIcon::EwResize => Shape::EwResize,
+        CursorIcon::NsResize => Shape::NsResize,
+        CursorIcon::NeswResize => Shape::NeswResize,
+        CursorIcon::NwseResize => Shape::NwseResize,
+        CursorIcon::ColResize => Shape::ColResize,
+        CursorIcon::RowResize => Shape::RowResize,
+        CursorIcon::AllScroll => Shape::AllScroll,
+        CursorIcon::ZoomIn => Shape::ZoomIn,
+        CursorIcon::ZoomOut => Shape::ZoomOut,
+        _ => Shape::Default,
+    }
+}
@@ -1,22 +1,31 @@
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use calloop::ping::Ping;
 use raw_window_handle::{DisplayHandle, HandleError, HasDisplayHandle};
 use raw_window_handle_05::HasRawDisplayHandle as HasRawDisplayHandle05;
 
-use sctk::reexports::calloop::LoopHandle;
+use sctk::reexports::calloop::timer::{TimeoutAction, Timer as CalloopTimer};
+use sctk::reexports::calloop::{LoopHandle, RegistrationToken};
 use sctk::reexports::client::backend::ObjectId;
 use sctk::reexports::client::globals::GlobalList;
+use sctk::reexports::client::protocol::wl_compositor::WlCompositor;
 use sctk::reexports::client::protocol::wl_output::{self, WlOutput};
 use sctk::reexports::client::protocol::wl_seat::WlSeat;
+use sctk::reexports::client::protocol::wl_shm::WlShm;
 use sctk::reexports::client::protocol::wl_surface::WlSurface;
 use sctk::reexports::client::{Connection, Proxy, QueueHandle};
 
 use sctk::compositor::{CompositorHandler, CompositorState};
+use sctk::globals::ProvidesBoundGlobal;
 use sctk::output::{OutputHandler, OutputState};
+use sctk::reexports::protocols::xdg::shell::client::xdg_wm_base::XdgWmBase;
 use sctk::registry::{ProvidesRegistryState, RegistryState};
-use sctk::seat::pointer::ThemedPointer;
+use sctk::seat::pointer::cursor_shape::CursorShapeManager;
+use sctk::seat::pointer_constraints::PointerConstraintsState;
+use sctk::seat::relative_pointer::RelativePointerState;
 use sctk::seat::{Capability as SeatCapability, SeatHandler, SeatState};
 use sctk::shell::xdg::window::{Window as XdgWindow, WindowConfigure, WindowHandler};
 use sctk::shell::xdg::XdgShell;
@@ -25,27 +34,49 @@ use sctk::shm::slot::SlotPool;
 use sctk::shm::{Shm, ShmHandler};
 use sctk::subcompositor::SubcompositorState;
 
-use winit_core::application::Application;
+use winit_core::application::{Application, WindowHandler as CoreWindowHandler};
+use winit_core::cursor::{CustomCursor, CustomCursorSource};
+use winit_core::error::{EventLoopError, MissingGlobal, RequestError};
+use winit_core::event_loop::clock::{Clock, SystemClock};
 use winit_core::event_loop::proxy::EventLoopProxy as CoreEventLoopProxy;
-use winit_core::event_loop::EventLoopHandle;
+use winit_core::event_loop::{ControlFlow, EventLoopHandle, ShutdownPolicy, Timer, TimerId};
+use winit_core::id::IdGenerator;
 use winit_core::monitor::{Monitor as CoreMonitor, MonitorId};
 use winit_core::window::{Window as CoreWindow, WindowAttributes, WindowId};
 
-use crate::monitor::Monitor;
-use crate::window::Window;
+use crate::icon::IconManager;
+use crate::monitor::{Monitor, MonitorIds};
+use crate::seat::{CursorThemes, CustomCursors, WinitPointer, WinitSeat};
+use crate::window::{Window, CONFIGURE_TIMEOUT};
 
-use crate::event_loop::{EventLoopProxy, RuntimeState};
+use crate::event_loop::{DeferredCallbacks, EventLoopProxy, RuntimeState};
 
 impl<T: Application + 'static> EventLoopHandle for WinitState<T> {
     fn proxy(&self) -> Arc<dyn CoreEventLoopProxy> {
         self.proxy.clone()
     }
 
-    fn create_window(&mut self, attributes: &WindowAttributes) -> Result<(), ()> {
+    fn create_window(&mut self, attributes: &WindowAttributes) -> Result<WindowId, RequestError> {
         let window = Window::new(self, attributes);
         let window_id = window.id();
         self.windows.insert(window_id, window);
-        Ok(())
+        Ok(window_id)
+    }
+
+    fn create_window_with_handler(
+        &mut self,
+        attributes: &WindowAttributes,
+        handler: Box<dyn CoreWindowHandler>,
+    ) -> Result<WindowId, RequestError> {
+        let window_id = self.create_window(attributes)?;
+        self.handlers.insert(window_id, handler);
+        Ok(window_id)
+    }
+
+    fn destroy_window(&mut self, window_id: WindowId) {
+        if let Some(window) = self.windows.remove(&window_id) {
+            self.destroyed.push(window);
+        }
     }
 
     fn num_windows(&self) -> usize {
@@ -55,24 +86,81 @@ impl<T: Application + 'static> EventLoopHandle for WinitState<T> {
     fn get_window(&self, window_id: WindowId) -> Option<&dyn CoreWindow> {
         let window = self.windows.get(&window_id)?;
 
-        if window.last_configure.is_none() {
-            return None;
-        } else {
+        if window.created {
             Some(window as &dyn CoreWindow)
+        } else {
+            None
         }
     }
 
     fn get_window_mut(&mut self, window_id: WindowId) -> Option<&mut dyn CoreWindow> {
         let window = self.windows.get_mut(&window_id)?;
-        if window.last_configure.is_none() {
-            return None;
-        } else {
+        if window.created {
             Some(window as &mut dyn CoreWindow)
+        } else {
+            None
         }
     }
 
-    fn exit(&mut self) {
-        self.exit = true;
+    fn get_pending_window(&self, window_id: WindowId) -> Option<&dyn CoreWindow> {
+        self.windows.get(&window_id).map(|window| window as &dyn CoreWindow)
+    }
+
+    fn get_pending_window_mut(&mut self, window_id: WindowId) -> Option<&mut dyn CoreWindow> {
+        self.windows.get_mut(&window_id).map(|window| window as &mut dyn CoreWindow)
+    }
+
+    fn set_control_flow(&mut self, control_flow: ControlFlow) {
+        self.control_flow = control_flow;
+    }
+
+    fn control_flow(&self) -> ControlFlow {
+        self.control_flow
+    }
+
+    fn add_timer(&mut self, timer: Timer) -> TimerId {
+        let timer_id = TimerId(self.next_timer_id);
+        self.next_timer_id += 1;
+
+        let (deadline, interval) = match timer {
+            Timer::Deadline(deadline) => (deadline, None),
+            Timer::Interval(interval) => {
+                // The zero interval would never move the deadline past now.
+                let interval = interval.max(Timer::MIN_INTERVAL);
+                (self.clock.now() + interval, Some(interval))
+            },
+        };
+
+        let wakeup = self.wakeup_at(deadline);
+        let token = self.insert_timer_source(timer_id, wakeup);
+        if token.is_none() {
+            log::warn!("Failed to add the calloop timer, the loop will poll the deadline instead");
+        }
+
+        self.timers.insert(timer_id, WaylandTimer { token, deadline, interval, wakeup });
+        timer_id
+    }
+
+    fn cancel_timer(&mut self, timer_id: TimerId) {
+        if let Some(token) = self.timers.remove(&timer_id).and_then(|timer| timer.token) {
+            self.loop_handle.remove(token);
+        }
+    }
+
+    fn set_shutdown_policy(&mut self, policy: ShutdownPolicy) {
+        self.shutdown_policy = policy;
+    }
+
+    fn shutdown_policy(&self) -> ShutdownPolicy {
+        self.shutdown_policy
+    }
+
+    fn now(&self) -> Instant {
+        self.clock.now()
+    }
+
+    fn exit_with_code(&mut self, code: i32) {
+        self.exit = Some(code);
     }
 
     fn get_monitor(&self, monitor_id: MonitorId) -> Option<&dyn CoreMonitor> {
@@ -85,6 +173,12 @@ impl<T: Application + 'static> EventLoopHandle for WinitState<T> {
     fn monitors(&self) -> Vec<&dyn CoreMonitor> {
         self.monitors.iter().map(|monitor| monitor as &dyn CoreMonitor).collect()
     }
+
+    fn create_custom_cursor(&mut self, source: CustomCursorSource) -> CustomCursor {
+        let cursor = self.ids.next_custom_cursor_id();
+        self.custom_cursors.lock().unwrap().insert(cursor, source);
+        cursor
+    }
 }
 
 impl<T: Application + 'static> HasDisplayHandle for WinitState<T> {
@@ -101,6 +195,28 @@ unsafe impl<T: Application + 'static> HasRawDisplayHandle05 for WinitState<T> {
     }
 }
 
+/// The timer added by the user.
+///
+/// Backed by the calloop timer, while the deadline follows the [`Clock`] of
+/// the loop.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct WaylandTimer {
+    /// The calloop timer, gone once the one-shot timer has fired.
+    ///
+    /// Also missing when the calloop timer couldn't be inserted, then the loop
+    /// checks the deadline on every iteration instead.
+    token: Option<RegistrationToken>,
+
+    /// The next time the timer fires, according to the clock.
+    deadline: Instant,
+
+    /// The interval of the repeating timer.
+    interval: Option<Duration>,
+
+    /// The time the calloop timer wakes the loop up at.
+    wakeup: Instant,
+}
+
 /// Winit's Wayland state.
 pub struct WinitState<T: Application + 'static> {
     /// The underlying connection.
@@ -124,40 +240,96 @@ pub struct WinitState<T: Application + 'static> {
     /// The shm for software buffers, such as cursors.
     pub shm: Shm,
 
+    /// The optional `xdg_toplevel_icon_manager_v1` to set the window icons.
+    pub(crate) icon_manager: Option<IconManager>,
+
     /// The XDG shell that is used for widnows.
     pub xdg_shell: XdgShell,
 
     /// Currently handled seats.
-    pub seats: HashMap<ObjectId, ()>,
+    pub(crate) seats: HashMap<ObjectId, WinitSeat>,
+
+    /// The optional `wp_cursor_shape_manager_v1` to set the cursors from the
+    /// compositor theme.
+    pub(crate) cursor_shape_manager: Option<CursorShapeManager>,
+
+    /// The xcursor themes, used when there's no `wp_cursor_shape_manager_v1`.
+    pub(crate) cursor_themes: Arc<Mutex<CursorThemes>>,
+
+    /// The custom cursors registered by the user, rendered by the pointers.
+    pub(crate) custom_cursors: CustomCursors,
+
+    /// The optional `zwp_pointer_constraints_v1` to confine and lock the
+    /// pointer.
+    pub(crate) pointer_constraints: Option<Arc<PointerConstraintsState>>,
+
+    /// The `zwp_relative_pointer_manager_v1`, creating the relative pointers
+    /// fails when the compositor lacks it.
+    pub(crate) relative_pointer: RelativePointerState,
 
     pub windows: HashMap<WindowId, Window<T>>,
 
+    /// The windows destroyed by the user, dropped once the callback requesting
+    /// that returns.
+    pub(crate) destroyed: Vec<Window<T>>,
+
+    /// The handlers of the windows created with one.
+    pub(crate) handlers: HashMap<WindowId, Box<dyn CoreWindowHandler>>,
+
     pub monitors: Vec<Monitor>,
 
+    /// The ids of the monitors, shared with the windows.
+    pub(crate) monitor_ids: MonitorIds,
+
+    /// The generator of the window and monitor ids.
+    pub(crate) ids: IdGenerator,
+
     pub(crate) queue_handle: QueueHandle<RuntimeState<T>>,
 
+    pub(crate) loop_handle: LoopHandle<'static, RuntimeState<T>>,
+
+    /// The timers added by the user.
+    pub(crate) timers: HashMap<TimerId, WaylandTimer>,
+
+    /// The source of time for the timers and the control flow.
+    pub(crate) clock: Arc<dyn Clock>,
+
+    /// The id of the next timer to add.
+    next_timer_id: u64,
+
     pub proxy: Arc<EventLoopProxy>,
 
-    pub exit: bool,
+    /// The control flow requested by the user.
+    pub control_flow: ControlFlow,
+
+    /// What to do once the last window is destroyed.
+    pub shutdown_policy: ShutdownPolicy,
+
+    /// The exit code requested by the user.
+    pub exit: Option<i32>,
 }
 
 impl<T: Application + 'static> WinitState<T> {
+    #[allow(clippy::mutable_key_type)]
     pub(crate) fn new(
         connection: Connection,
         globals: &GlobalList,
         queue_handle: &QueueHandle<RuntimeState<T>>,
+        loop_handle: LoopHandle<'static, RuntimeState<T>>,
         proxy: EventLoopProxy,
-    ) -> Result<Self, ()> {
+    ) -> Result<Self, EventLoopError> {
         let registry_state = RegistryState::new(globals);
         let output_state = OutputState::new(globals, queue_handle);
 
         let seat_state = SeatState::new(globals, queue_handle);
         let mut seats = HashMap::default();
         for seat in seat_state.seats() {
-            seats.insert(seat.id(), ());
+            seats.insert(seat.id(), WinitSeat::default());
         }
 
-        let compositor_state = Arc::new(CompositorState::bind(globals, queue_handle).unwrap());
+        let compositor_state = CompositorState::bind(globals, queue_handle)
+            .map_err(|_| MissingGlobal::new(WlCompositor::interface().name, 1))?;
+        let compositor_state = Arc::new(compositor_state);
         let subcompositor_state = match SubcompositorState::bind(
             compositor_state.wl_compositor().clone(),
             globals,
@@ -170,12 +342,22 @@ impl<T: Application + 'static> WinitState<T> {
             },
         };
 
-        let shm = Shm::bind(globals, queue_handle).unwrap();
-        let monitors = output_state.outputs().map(Monitor::new).collect();
-
-        Ok(Self {
-            xdg_shell: XdgShell::bind(globals, queue_handle).unwrap(),
+        let shm = Shm::bind(globals, queue_handle)
+            .map_err(|_| MissingGlobal::new(WlShm::interface().name, 1))?;
+        let icon_manager = IconManager::bind(globals, queue_handle, shm.wl_shm().clone()).ok();
+        let cursor_shape_manager = CursorShapeManager::bind(globals, queue_handle).ok();
+        let pointer_constraints = Some(PointerConstraintsState::bind(globals, queue_handle))
+            .filter(|constraints| constraints.bound_global().is_ok())
+            .map(Arc::new);
+        let relative_pointer = RelativePointerState::bind(globals, queue_handle);
+        let xdg_shell = XdgShell::bind(globals, queue_handle)
+            .map_err(|_| MissingGlobal::new(XdgWmBase::interface().name, 1))?;
+        let outputs: Vec<_> = output_state.outputs().collect();
+
+        let mut state = Self {
+            xdg_shell,
             queue_handle: queue_handle.clone(),
+            loop_handle,
             subcompositor: subcompositor_state,
             compositor: compositor_state,
             proxy: Arc::new(proxy),
@@ -183,12 +365,278 @@ impl<T: Application + 'static> WinitState<T> {
             output_state,
             seat_state,
             connection,
-            monitors,
+            monitors: Vec::new(),
+            monitor_ids: MonitorIds::default(),
+            ids: IdGenerator::new(),
             seats,
             shm,
+            icon_manager,
+            cursor_shape_manager,
+            cursor_themes: Arc::new(Mutex::new(CursorThemes::from_env())),
+            custom_cursors: Default::default(),
+            pointer_constraints,
+            relative_pointer,
             windows: Default::default(),
+            destroyed: Default::default(),
+            handlers: Default::default(),
+            timers: Default::default(),
+            clock: Arc::new(SystemClock),
+            next_timer_id: 0,
+            control_flow: Default::default(),
+            shutdown_policy: Default::default(),
             exit: Default::default(),
-        })
+        };
+
+        for output in outputs {
+            state.add_monitor(output);
+        }
+
+        Ok(state)
+    }
+
+    /// Track the new output as a monitor with the fresh id.
+    pub(crate) fn add_monitor(&mut self, output: WlOutput) {
+        let monitor_id = self.ids.next_monitor_id();
+        self.monitor_ids.insert(output.clone(), monitor_id);
+        self.monitors.push(Monitor::new(monitor_id, output));
+    }
+
+    /// Run `f` with the handler of the window, or the user when the window has
+    /// none.
+    ///
+    /// The handler is dropped once the window is gone.
+    pub(crate) fn with_handler<F, R>(&mut self, user: &mut T, window_id: WindowId, f: F) -> R
+    where
+        F: FnOnce(&mut dyn CoreWindowHandler, &mut Self) -> R,
+    {
+        let mut handler = match self.handlers.remove(&window_id) {
+            Some(handler) => handler,
+            None => return f(user, self),
+        };
+
+        let ret = f(handler.as_mut(), self);
+        let destroyed = self.destroyed.iter().any(|window| window.id() == window_id);
+        if self.windows.contains_key(&window_id) || destroyed {
+            self.handlers.insert(window_id, handler);
+        }
+
+        ret
+    }
+
+    /// Drop the windows destroyed by the user and let it know about them.
+    pub(crate) fn flush_destroyed(&mut self, user: &mut T) {
+        if self.destroyed.is_empty() {
+            return;
+        }
+
+        // The user could destroy more windows from the `destroyed`.
+        while !self.destroyed.is_empty() {
+            for window in mem::take(&mut self.destroyed) {
+                let window_id = window.id();
+                let created = window.created;
+                drop(window);
+
+                if created {
+                    self.with_handler(user, window_id, |handler, winit| {
+                        handler.destroyed(winit, window_id)
+                    });
+                } else {
+                    self.handlers.remove(&window_id);
+                }
+            }
+        }
+
+        self.exit_if_no_windows();
+    }
+
+    /// Remove the window which was not configured yet, returning whether it
+    /// existed.
+    pub(crate) fn remove_pending_window(&mut self, window_id: WindowId) -> bool {
+        let removed = self.windows.remove(&window_id).is_some();
+        if removed {
+            self.exit_if_no_windows();
+        }
+
+        removed
+    }
+
+    /// Exit once the last window is gone, when the shutdown policy asks for it.
+    fn exit_if_no_windows(&mut self) {
+        if self.windows.is_empty()
+            && self.shutdown_policy == ShutdownPolicy::ExitOnLastWindowDestroyed
+        {
+            self.exit.get_or_insert(0);
+        }
+    }
+
+    /// The id of the window the `surface` belongs to.
+    pub(crate) fn window_id(&self, surface: &WlSurface) -> Option<WindowId> {
+        self.windows
+            .iter()
+            .find(|(_, window)| window.window.wl_surface() == surface)
+            .map(|(window_id, _)| *window_id)
+    }
+
+    /// The closest deadline for the initial configure of the windows.
+    pub(crate) fn next_configure_deadline(&self) -> Option<Instant> {
+        self.windows
+            .values()
+            .filter(|window| !window.configured())
+            .map(|window| window.requested_at + CONFIGURE_TIMEOUT)
+            .min()
+    }
+
+    /// The windows which didn't get the initial configure in time.
+    pub(crate) fn configure_timed_out(&self, now: Instant) -> Vec<WindowId> {
+        self.windows
+            .iter()
+            .filter(|(_, window)| {
+                !window.configured() && window.requested_at + CONFIGURE_TIMEOUT <= now
+            })
+            .map(|(window_id, _)| *window_id)
+            .collect()
+    }
+
+    /// The pointers of all the seats.
+    fn pointers(&self) -> impl Iterator<Item = &Arc<WinitPointer>> {
+        self.seats.values().filter_map(|seat| seat.pointer.as_ref())
+    }
+
+    /// The closest time to show the next frame of the animated cursors.
+    pub(crate) fn next_cursor_deadline(&self) -> Option<Instant> {
+        self.pointers().filter_map(|pointer| pointer.next_frame_deadline()).min()
+    }
+
+    /// Advance the animated cursors to `now`.
+    pub(crate) fn animate_cursors(&self, now: Instant) {
+        for pointer in self.pointers() {
+            pointer.animate(now);
+        }
+    }
+
+    /// The real time the loop should wake up at to see the clock reach
+    /// `deadline`.
+    fn wakeup_at(&self, deadline: Instant) -> Instant {
+        Instant::now() + deadline.saturating_duration_since(self.clock.now())
+    }
+
+    /// Insert the calloop timer waking the loop up for the timer at `wakeup`.
+    fn insert_timer_source(&self, timer_id: TimerId, wakeup: Instant) -> Option<RegistrationToken> {
+        let source = CalloopTimer::from_deadline(wakeup);
+        self.loop_handle
+            .insert_source(source, move |_, _, state| {
+                let RuntimeState { winit, callbacks, .. } = state;
+                winit.timer_expired(callbacks, timer_id)
+            })
+            .ok()
+    }
+
+    /// Handle the wakeup of the calloop timer, firing the timer once the clock
+    /// has reached its deadline.
+    fn timer_expired(
+        &mut self,
+        callbacks: &mut DeferredCallbacks<T>,
+        timer_id: TimerId,
+    ) -> TimeoutAction {
+        let now = self.clock.now();
+        let timer = match self.timers.get_mut(&timer_id) {
+            Some(timer) => timer,
+            None => return TimeoutAction::Drop,
+        };
+
+        if now < timer.deadline {
+            // The clock is behind the real time, wait for it to reach the deadline.
+            timer.wakeup = Instant::now() + (timer.deadline - now);
+            return TimeoutAction::ToInstant(timer.wakeup);
+        }
+
+        callbacks.push_winit(move |user, winit| {
+            // The timer could be cancelled by the previous callback.
+            let timer = match winit.timers.get(&timer_id) {
+                Some(timer) => *timer,
+                None => return,
+            };
+
+            if timer.interval.is_none() {
+                winit.timers.remove(&timer_id);
+            }
+
+            user.timer_fired(winit, timer_id);
+        });
+
+        match timer.interval {
+            Some(interval) => {
+                // Skip the ticks the loop has missed instead of firing them in a row.
+                timer.deadline += interval;
+                if timer.deadline <= now {
+                    timer.deadline = now + interval;
+                }
+
+                timer.wakeup = Instant::now() + (timer.deadline - now);
+                TimeoutAction::ToInstant(timer.wakeup)
+            },
+            None => {
+                timer.token = None;
+                TimeoutAction::Drop
+            },
+        }
+    }
+
+    /// The closest deadline of the timers without the calloop timer.
+    pub(crate) fn next_polled_timer_deadline(&self) -> Option<Instant> {
+        self.timers.values().filter(|timer| timer.token.is_none()).map(|timer| timer.deadline).min()
+    }
+
+    /// Wake the loop up right away for the timers whose deadline the clock has
+    /// reached ahead of the real time, and fire the expired timers without the
+    /// calloop timer.
+    pub(crate) fn wake_expired_timers(
+        &mut self,
+        callbacks: &mut DeferredCallbacks<T>,
+        now: Instant,
+    ) {
+        let polled: Vec<_> = self
+            .timers
+            .iter()
+            .filter(|(_, timer)| timer.token.is_none() && timer.deadline <= now)
+            .map(|(timer_id, _)| *timer_id)
+            .collect();
+
+        for timer_id in polled {
+            self.timer_expired(callbacks, timer_id);
+        }
+
+        let real_now = Instant::now();
+        let expired: Vec<_> = self
+            .timers
+            .iter()
+            .filter(|(_, timer)| {
+                timer.token.is_some() && timer.deadline <= now && real_now < timer.wakeup
+            })
+            .map(|(timer_id, _)| *timer_id)
+            .collect();
+
+        for timer_id in expired {
+            let token = match self.insert_timer_source(timer_id, real_now) {
+                Some(token) => token,
+                None => continue,
+            };
+
+            let timer = self.timers.get_mut(&timer_id).unwrap();
+            if let Some(token) = timer.token.replace(token) {
+                self.loop_handle.remove(token);
+            }
+            timer.wakeup = real_now;
+        }
+    }
+
+    /// Cancel all the timers.
+    pub(crate) fn clear_timers(&mut self) {
+        for (_, timer) in self.timers.drain() {
+            if let Some(token) = timer.token {
+                self.loop_handle.remove(token);
+            }
+        }
     }
 
     pub(crate) fn scale_factor_changed(
@@ -197,21 +645,24 @@ impl<T: Application + 'static> WinitState<T> {
         scale_factor: f64,
         legacy: bool,
     ) {
-        let winit = &mut state.winit;
-        let window_id = crate::make_wid(surface);
-        let window = match winit.windows.get_mut(&window_id) {
-            Some(window) => window,
+        let window_id = match state.winit.window_id(surface) {
+            Some(window_id) => window_id,
             None => return,
         };
 
-        window.set_scale_factor(scale_factor);
-
-        let user_state = &mut state.user.as_mut().unwrap();
-
-        // Only send scale for configured windows.
-        if window.configured() {
-            user_state.scale_factor_changed(winit, window_id, scale_factor);
-        }
+        // Apply the scale along with the callback, so the callbacks queued before
+        // it still see the old one.
+        state.callbacks.push_window(window_id, move |user, winit| {
+            let window = winit.windows.get_mut(&window_id).unwrap();
+            window.set_scale_factor(scale_factor);
+
+            // Only send scale for configured windows.
+            if window.configured() {
+                winit.with_handler(user, window_id, |handler, winit| {
+                    handler.scale_factor_changed(winit, window_id, scale_factor)
+                });
+            }
+        });
     }
 }
 
@@ -235,6 +686,32 @@ impl<T: Application + 'static> SeatHandler for RuntimeState<T> {
         seat: WlSeat,
         capability: SeatCapability,
     ) {
+        let winit = &mut self.winit;
+        let seat_state = winit.seats.entry(seat.id()).or_default();
+
+        if capability == SeatCapability::Pointer && seat_state.pointer.is_none() {
+            let pointer = match winit.seat_state.get_pointer(queue_handle, &seat) {
+                Ok(pointer) => pointer,
+                Err(_) => return,
+            };
+
+            let shape_device = winit
+                .cursor_shape_manager
+                .as_ref()
+                .map(|manager| manager.get_shape_device(&pointer, queue_handle));
+            let surface = winit.compositor.create_surface(queue_handle);
+            seat_state.relative_pointer =
+                winit.relative_pointer.get_relative_pointer(&pointer, queue_handle).ok();
+            seat_state.pointer = Some(Arc::new(WinitPointer::new(
+                pointer,
+                shape_device,
+                surface,
+                winit.cursor_themes.clone(),
+                winit.custom_cursors.clone(),
+                winit.connection.clone(),
+                winit.shm.wl_shm().clone(),
+            )));
+        }
     }
 
     fn remove_capability(
@@ -244,6 +721,14 @@ impl<T: Application + 'static> SeatHandler for RuntimeState<T> {
         seat: WlSeat,
         capability: SeatCapability,
     ) {
+        let seat_state = match self.winit.seats.get_mut(&seat.id()) {
+            Some(seat_state) => seat_state,
+            None => return,
+        };
+
+        if capability == SeatCapability::Pointer {
+            seat_state.remove_pointer();
+        }
     }
 
     fn new_seat(
@@ -252,6 +737,7 @@ impl<T: Application + 'static> SeatHandler for RuntimeState<T> {
         _queue_handle: &QueueHandle<Self>,
         seat: WlSeat,
     ) {
+        self.winit.seats.entry(seat.id()).or_default();
     }
 
     fn remove_seat(
@@ -260,6 +746,7 @@ impl<T: Application + 'static> SeatHandler for RuntimeState<T> {
         _queue_handle: &QueueHandle<Self>,
         seat: WlSeat,
     ) {
+        self.winit.seats.remove(&seat.id());
     }
 }
 
@@ -285,7 +772,11 @@ impl<T: Application + 'static> CompositorHandler for RuntimeState<T> {
     }
 
     fn frame(&mut self, conn: &Connection, qh: &QueueHandle<Self>, surface: &WlSurface, time: u32) {
-        todo!()
+        let window_id = self.winit.window_id(surface);
+        if let Some(window) = window_id.and_then(|id| self.winit.windows.get_mut(&id)) {
+            // NOTE: the pending redraw is delivered once the dispatch is done.
+            window.frame_callback_received();
+        }
     }
 }
 
@@ -297,8 +788,70 @@ impl<T: Application + 'static> ShmHandler for RuntimeState<T> {
 
 sctk::delegate_registry!(@<T: Application + 'static> RuntimeState<T>);
 sctk::delegate_seat!(@<T: Application + 'static> RuntimeState<T>);
+sctk::delegate_pointer!(@<T: Application + 'static> RuntimeState<T>);
+sctk::delegate_pointer_constraints!(@<T: Application + 'static> RuntimeState<T>);
+sctk::delegate_relative_pointer!(@<T: Application + 'static> RuntimeState<T>);
 sctk::delegate_subcompositor!(@<T: Application + 'static> RuntimeState<T>);
 sctk::delegate_shm!(@<T: Application + 'static> RuntimeState<T>);
 sctk::delegate_compositor!(@<T: Application + 'static> RuntimeState<T>);
 sctk::delegate_xdg_shell!(@<T: Application + 'static> RuntimeState<T>);
 sctk::delegate_xdg_window!(@<T: Application + 'static> RuntimeState<T>);
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use winit_core::error::EventLoopError;
+    use winit_core::event_loop::clock::ManualClock;
+    use winit_core::event_loop::{EventLoopHandle, Timer};
+
+    use crate::event_loop::EventLoop;
+    use crate::test_compositor::{self, Config, TestApp, TestCompositor, TestEvent};
+
+    #[test]
+    fn missing_xdg_wm_base() {
+        let config = Config { xdg_wm_base: false, ..Default::default() };
+        let (_compositor, connection) = TestCompositor::spawn(config).unwrap();
+
+        match EventLoop::<TestApp>::with_connection(connection) {
+            Err(EventLoopError::MissingGlobal(missing)) => {
+                assert_eq!(missing.interface, "xdg_wm_base")
+            },
+            Err(err) => panic!("unexpected error {err}"),
+            Ok(_) => panic!("event loop created without xdg_wm_base"),
+        }
+    }
+
+    #[test]
+    fn polled_timer() {
+        let (_compositor, mut event_loop) = test_compositor::spawn_event_loop(Config::default());
+        let clock = Arc::new(ManualClock::new());
+        event_loop.set_clock(clock.clone());
+        let mut app = TestApp::default();
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        // Pretend that the calloop timer couldn't be inserted.
+        let timer_id = event_loop.winit().add_timer(Timer::Interval(Duration::from_secs(1)));
+        let winit = event_loop.winit();
+        let token = winit.timers.get_mut(&timer_id).unwrap().token.take().unwrap();
+        winit.loop_handle.remove(token);
+
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(app.take_events(), []);
+
+        clock.advance(Duration::from_secs(1));
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(app.take_events(), [TestEvent::TimerFired(timer_id)]);
+
+        clock.advance(Duration::from_secs(1));
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(app.take_events(), [TestEvent::TimerFired(timer_id)]);
+
+        event_loop.winit().cancel_timer(timer_id);
+        clock.advance(Duration::from_secs(1));
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(app.take_events(), []);
+        assert!(event_loop.winit().timers.is_empty());
+    }
+}
@@ -0,0 +1,589 @@
+//! In-process compositor used to test the backend without a display.
+//!
+//! The compositor runs on its own thread, since the client blocks on
+//! roundtrips, and talks to the client over a socket pair. Every scripting
+//! method waits for the compositor to process the requests the client has
+//! already flushed, so the recorded state is up to date once it returns.
+
+use std::cell::RefCell;
+use std::io;
+use std::os::unix::net::UnixStream;
+use std::rc::Rc;
+use std::sync::{mpsc, Arc};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use calloop::channel::{self, Sender};
+use calloop::generic::Generic;
+use calloop::{Interest, Mode, PostAction};
+
+use wayland_client::Connection;
+pub(crate) use wayland_protocols::wp::cursor_shape::v1::server::wp_cursor_shape_device_v1::Shape as CursorShape;
+use wayland_protocols::xdg::decoration::zv1::server::zxdg_toplevel_decoration_v1::Mode as DecorationMode;
+pub(crate) use wayland_protocols::xdg::shell::server::xdg_toplevel::State as ToplevelState;
+use wayland_server::backend::ClientData;
+use wayland_server::Display;
+
+use winit_core::application::{Application, ApplicationWindow, DeviceEventsHandler, StartCause};
+use winit_core::dpi::PhysicalSize;
+use winit_core::error::RequestError;
+use winit_core::event_loop::pump_events::{EventLoopPumpEvents, PumpStatus};
+use winit_core::event_loop::{EventLoopHandle, TimerId};
+use winit_core::window::WindowId;
+
+use crate::event_loop::EventLoop;
+
+mod server;
+
+use self::server::ServerState;
+
+/// The globals advertised by the compositor.
+#[derive(Debug, Clone)]
+pub(crate) struct Config {
+    /// Advertise `xdg_wm_base`.
+    pub xdg_wm_base: bool,
+
+    /// Advertise `wl_seat`.
+    pub seat: bool,
+
+    /// Give the seat the pointer capability.
+    pub pointer: bool,
+
+    /// Advertise `wp_cursor_shape_manager_v1`.
+    pub cursor_shape: bool,
+
+    /// Advertise `zwp_pointer_constraints_v1`.
+    pub pointer_constraints: bool,
+
+    /// Advertise `zwp_relative_pointer_manager_v1`.
+    pub relative_pointer: bool,
+
+    /// Advertise `wp_fractional_scale_manager_v1`.
+    pub fractional_scale: bool,
+
+    /// Advertise `zxdg_decoration_manager_v1`.
+    pub xdg_decoration: bool,
+
+    /// Advertise `xdg_toplevel_icon_manager_v1`.
+    pub toplevel_icon: bool,
+
+    /// The outputs advertised from the start.
+    pub outputs: Vec<OutputConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            xdg_wm_base: true,
+            seat: true,
+            pointer: false,
+            cursor_shape: false,
+            pointer_constraints: false,
+            relative_pointer: false,
+            fractional_scale: false,
+            xdg_decoration: false,
+            toplevel_icon: false,
+            outputs: vec![OutputConfig::default()],
+        }
+    }
+}
+
+/// The `wl_output` advertised by the compositor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct OutputConfig {
+    pub name: String,
+    pub position: (i32, i32),
+    pub size: (i32, i32),
+    pub refresh_millihertz: i32,
+    pub scale: i32,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            name: String::from("TEST-1"),
+            position: (0, 0),
+            size: (1920, 1080),
+            refresh_millihertz: 60000,
+            scale: 1,
+        }
+    }
+}
+
+/// The state of the toplevel recorded from the client requests.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Toplevel {
+    pub title: Option<String>,
+    pub app_id: Option<String>,
+
+    /// The minimum size, `(0, 0)` when unset.
+    pub min_size: (i32, i32),
+
+    /// The maximum size, `(0, 0)` when unset.
+    pub max_size: (i32, i32),
+
+    /// The last `xdg_surface.set_window_geometry`.
+    pub window_geometry: Option<(i32, i32, i32, i32)>,
+
+    pub maximized: bool,
+    pub fullscreen: bool,
+
+    /// The index of the output requested with `xdg_toplevel.set_fullscreen`.
+    pub fullscreen_output: Option<usize>,
+    pub minimize_requests: usize,
+
+    /// The icon set with `xdg_toplevel_icon_manager_v1.set_icon`.
+    pub icon: Option<ToplevelIcon>,
+
+    /// The decoration mode requested by the client.
+    pub decoration_mode: Option<DecorationMode>,
+
+    /// The last serial acked by the client.
+    pub acked_serial: Option<u32>,
+
+    /// The buffer scale of the surface.
+    pub buffer_scale: i32,
+
+    /// The number of `wl_surface.commit` requests.
+    pub commits: usize,
+
+    /// The number of pending `wl_surface.frame` callbacks.
+    pub frame_callbacks: usize,
+
+    /// Whether the client has destroyed the toplevel.
+    pub destroyed: bool,
+}
+
+/// The `xdg_toplevel_icon_v1` recorded from the client requests.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ToplevelIcon {
+    pub name: Option<String>,
+
+    /// The width, height and scale of the added buffers.
+    pub buffers: Vec<(i32, i32, i32)>,
+}
+
+/// The cursor set by the client for the pointer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PointerCursor {
+    /// `wl_pointer.set_cursor` without the surface.
+    Hidden,
+
+    /// `wl_pointer.set_cursor` with the surface.
+    Surface {
+        /// The size of the attached buffer.
+        size: (i32, i32),
+        buffer_scale: i32,
+        hotspot: (i32, i32),
+    },
+
+    /// `wp_cursor_shape_device_v1.set_shape`.
+    Shape(CursorShape),
+}
+
+/// The constraint requested by the client for the pointer.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PointerConstraint {
+    /// `zwp_pointer_constraints_v1.confine_pointer`.
+    Confined,
+
+    /// `zwp_pointer_constraints_v1.lock_pointer`, along with the last
+    /// position hint, which is not waiting for the commit.
+    Locked { position_hint: Option<(f64, f64)> },
+}
+
+/// The request to the compositor thread.
+enum Request {
+    Run(Box<dyn FnOnce(&mut Server) + Send>),
+    Stop,
+}
+
+/// Handle to the compositor thread.
+pub(crate) struct TestCompositor {
+    sender: Sender<Request>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl TestCompositor {
+    /// Spawn the compositor, returning it with the connection of the client.
+    pub fn spawn(config: Config) -> io::Result<(Self, Connection)> {
+        let (server_socket, client_socket) = UnixStream::pair()?;
+
+        let display = Display::<ServerState>::new().map_err(io::Error::other)?;
+        display.handle().insert_client(server_socket, Arc::new(TestClient))?;
+        let state = ServerState::new(display.handle(), config);
+
+        let (sender, channel) = channel::channel::<Request>();
+        let thread = std::thread::spawn(move || run_server(display, state, channel));
+
+        let connection = Connection::from_socket(client_socket).map_err(io::Error::other)?;
+
+        Ok((Self { sender, thread: Some(thread) }, connection))
+    }
+
+    /// Run `f` on the compositor thread once it has processed the requests
+    /// flushed by the client.
+    pub fn with_state<R, F>(&self, f: F) -> R
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut ServerState) -> R + Send + 'static,
+    {
+        let (result_sender, result) = mpsc::channel();
+        let command = Box::new(move |server: &mut Server| {
+            // Process everything the client has flushed before the command was sent, and
+            // flush the events before replying.
+            server.dispatch();
+            let value = f(&mut server.state);
+            server.dispatch();
+            let _ = result_sender.send(value);
+        });
+
+        self.sender.send(Request::Run(command)).expect("compositor thread is gone");
+        result.recv().expect("compositor thread has panicked")
+    }
+
+    /// The toplevels in the order of their creation, including destroyed ones.
+    pub fn toplevels(&self) -> Vec<Toplevel> {
+        self.with_state(|state| state.toplevels())
+    }
+
+    /// The toplevel with the given index.
+    pub fn toplevel(&self, index: usize) -> Toplevel {
+        self.toplevels().swap_remove(index)
+    }
+
+    /// Send `xdg_toplevel.configure` followed by `xdg_surface.configure`,
+    /// returning the serial.
+    pub fn configure(
+        &self,
+        index: usize,
+        size: Option<(i32, i32)>,
+        states: Vec<ToplevelState>,
+    ) -> u32 {
+        self.with_state(move |state| state.configure(index, size, &states))
+    }
+
+    /// Send `xdg_toplevel.close`.
+    pub fn close(&self, index: usize) {
+        self.with_state(move |state| state.close(index))
+    }
+
+    /// Send `wl_surface.preferred_buffer_scale`.
+    pub fn set_buffer_scale(&self, index: usize, scale: i32) {
+        self.with_state(move |state| state.set_buffer_scale(index, scale))
+    }
+
+    /// Send `wp_fractional_scale_v1.preferred_scale`, returning whether the
+    /// client has requested fractional scaling for the surface.
+    pub fn set_fractional_scale(&self, index: usize, scale: f64) -> bool {
+        self.with_state(move |state| state.set_fractional_scale(index, scale))
+    }
+
+    /// Send `wl_callback.done` for the pending frame callbacks.
+    pub fn frame_done(&self, index: usize) {
+        self.with_state(move |state| state.frame_done(index))
+    }
+
+    /// Advertise a new output, returning its index.
+    pub fn add_output(&self, output: OutputConfig) -> usize {
+        self.with_state(move |state| state.add_output(output))
+    }
+
+    /// Remove the output global.
+    pub fn remove_output(&self, index: usize) {
+        self.with_state(move |state| state.remove_output(index))
+    }
+
+    /// Send `wl_surface.enter` for the output.
+    pub fn enter_output(&self, index: usize, output: usize) {
+        self.with_state(move |state| state.enter_output(index, output))
+    }
+
+    /// Send `wl_surface.leave` for the output.
+    pub fn leave_output(&self, index: usize, output: usize) {
+        self.with_state(move |state| state.leave_output(index, output))
+    }
+
+    /// Send `wl_pointer.enter` for the toplevel, returning the serial.
+    pub fn pointer_enter(&self, index: usize) -> u32 {
+        self.with_state(move |state| state.pointer_enter(index))
+    }
+
+    /// Send `wl_pointer.leave` for the toplevel.
+    pub fn pointer_leave(&self, index: usize) {
+        self.with_state(move |state| state.pointer_leave(index))
+    }
+
+    /// The last cursor set by the client along with the serial it used.
+    pub fn cursor(&self) -> Option<(u32, PointerCursor)> {
+        self.with_state(|state| state.cursor())
+    }
+
+    /// The constraint of the pointer, `None` once destroyed.
+    pub fn pointer_constraint(&self) -> Option<PointerConstraint> {
+        self.with_state(|state| state.pointer_constraint())
+    }
+
+    /// Send `zwp_relative_pointer_v1.relative_motion`, with the accelerated
+    /// motion being twice the `delta`.
+    pub fn relative_motion(&self, delta: (f64, f64)) {
+        self.with_state(move |state| state.relative_motion(delta))
+    }
+}
+
+impl Drop for TestCompositor {
+    fn drop(&mut self) {
+        // NOTE: don't rely on the channel closure, it could be missed by the loop.
+        let _ = self.sender.send(Request::Stop);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// The data of the only client.
+struct TestClient;
+
+impl ClientData for TestClient {}
+
+/// The state of the compositor thread.
+struct Server {
+    display: Display<ServerState>,
+    state: ServerState,
+    running: bool,
+}
+
+impl Server {
+    fn dispatch(&mut self) {
+        let _ = self.display.dispatch_clients(&mut self.state);
+        let _ = self.display.flush_clients();
+    }
+}
+
+fn run_server(
+    display: Display<ServerState>,
+    state: ServerState,
+    channel: channel::Channel<Request>,
+) {
+    let mut event_loop = calloop::EventLoop::<Server>::try_new().unwrap();
+    let mut server = Server { display, state, running: true };
+
+    let poll_fd = server.display.backend().poll_fd().try_clone_to_owned().unwrap();
+    event_loop
+        .handle()
+        .insert_source(Generic::new(poll_fd, Interest::READ, Mode::Level), |_, _, server| {
+            server.dispatch();
+            Ok(PostAction::Continue)
+        })
+        .unwrap();
+
+    event_loop
+        .handle()
+        .insert_source(channel, |event, _, server| match event {
+            channel::Event::Msg(Request::Run(command)) => command(server),
+            channel::Event::Msg(Request::Stop) | channel::Event::Closed => server.running = false,
+        })
+        .unwrap();
+
+    while server.running {
+        event_loop.dispatch(None, &mut server).unwrap();
+    }
+}
+
+/// The application recording the callbacks it receives.
+#[derive(Debug, Default)]
+pub(crate) struct TestApp {
+    pub events: Vec<TestEvent>,
+
+    /// Windows to create on `StartCause::Init`.
+    pub initial_windows: Vec<winit_core::window::WindowAttributes>,
+
+    /// Panic once notified about the created window.
+    pub panic_on_created: bool,
+
+    /// Destroy the window from its `resized`.
+    pub destroy_on_resized: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TestEvent {
+    Created(WindowId),
+    CreateFailed(WindowId),
+    Resized(WindowId, PhysicalSize<u32>),
+    ScaleFactorChanged(WindowId, f64),
+    RedrawRequested(WindowId),
+    CloseRequested(WindowId),
+    Destroyed(WindowId),
+    TimerFired(TimerId),
+    Message(u32),
+    MouseMotion((f64, f64)),
+    LoopExiting,
+}
+
+impl TestApp {
+    pub fn new(initial_windows: Vec<winit_core::window::WindowAttributes>) -> Self {
+        Self { initial_windows, ..Default::default() }
+    }
+
+    /// Take the events received so far.
+    pub fn take_events(&mut self) -> Vec<TestEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+impl Application for TestApp {
+    fn user_wakeup(&mut self, _: &mut dyn EventLoopHandle) {}
+
+    fn new_events(&mut self, loop_handle: &mut dyn EventLoopHandle, start_cause: StartCause) {
+        if start_cause == StartCause::Init {
+            for attributes in &self.initial_windows {
+                loop_handle.create_window(attributes).unwrap();
+            }
+        }
+    }
+
+    fn about_to_wait(&mut self, _: &mut dyn EventLoopHandle) {}
+
+    fn timer_fired(&mut self, _: &mut dyn EventLoopHandle, timer_id: TimerId) {
+        self.events.push(TestEvent::TimerFired(timer_id));
+    }
+
+    fn loop_exiting(&mut self, _: &mut dyn EventLoopHandle) {
+        self.events.push(TestEvent::LoopExiting);
+    }
+
+    fn device_events_handelr(&mut self) -> Option<&mut dyn DeviceEventsHandler> {
+        Some(self)
+    }
+}
+
+impl DeviceEventsHandler for TestApp {
+    fn mouse_motion(&mut self, _: &mut dyn EventLoopHandle, delta: (f64, f64)) {
+        self.events.push(TestEvent::MouseMotion(delta));
+    }
+}
+
+impl ApplicationWindow for TestApp {
+    fn created(&mut self, _: &mut dyn EventLoopHandle, window_id: WindowId) {
+        self.events.push(TestEvent::Created(window_id));
+        if self.panic_on_created {
+            panic!("created {window_id:?}");
+        }
+    }
+
+    fn create_failed(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        window_id: WindowId,
+        error: RequestError,
+    ) {
+        // The window fails only when the compositor doesn't configure it.
+        assert!(matches!(error, RequestError::TimedOut(_)));
+        assert!(loop_handle.get_pending_window(window_id).is_none());
+        self.events.push(TestEvent::CreateFailed(window_id));
+    }
+
+    fn resized(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        window_id: WindowId,
+        size: PhysicalSize<u32>,
+    ) {
+        self.events.push(TestEvent::Resized(window_id, size));
+        assert_eq!(loop_handle.get_window(window_id).unwrap().inner_size(), size);
+
+        if self.destroy_on_resized {
+            loop_handle.destroy_window(window_id);
+            assert!(loop_handle.get_window(window_id).is_none());
+        }
+    }
+
+    fn scale_factor_changed(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        window_id: WindowId,
+        scale_factor: f64,
+    ) {
+        self.events.push(TestEvent::ScaleFactorChanged(window_id, scale_factor));
+        assert_eq!(loop_handle.get_window(window_id).unwrap().scale_factor(), scale_factor);
+    }
+
+    fn redraw_requested(&mut self, _: &mut dyn EventLoopHandle, window_id: WindowId) {
+        self.events.push(TestEvent::RedrawRequested(window_id));
+    }
+
+    fn close_requested(&mut self, _: &mut dyn EventLoopHandle, window_id: WindowId) -> bool {
+        self.events.push(TestEvent::CloseRequested(window_id));
+        true
+    }
+
+    fn destroyed(&mut self, _: &mut dyn EventLoopHandle, window_id: WindowId) {
+        self.events.push(TestEvent::Destroyed(window_id));
+    }
+}
+
+/// The window handler recording the callbacks into the shared [`TestApp`].
+pub(crate) struct TestHandler(pub Rc<RefCell<TestApp>>);
+
+impl ApplicationWindow for TestHandler {
+    fn created(&mut self, loop_handle: &mut dyn EventLoopHandle, window_id: WindowId) {
+        self.0.borrow_mut().created(loop_handle, window_id)
+    }
+
+    fn create_failed(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        window_id: WindowId,
+        error: RequestError,
+    ) {
+        self.0.borrow_mut().create_failed(loop_handle, window_id, error)
+    }
+
+    fn resized(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        window_id: WindowId,
+        size: PhysicalSize<u32>,
+    ) {
+        self.0.borrow_mut().resized(loop_handle, window_id, size)
+    }
+
+    fn scale_factor_changed(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        window_id: WindowId,
+        scale_factor: f64,
+    ) {
+        self.0.borrow_mut().scale_factor_changed(loop_handle, window_id, scale_factor)
+    }
+
+    fn redraw_requested(&mut self, loop_handle: &mut dyn EventLoopHandle, window_id: WindowId) {
+        self.0.borrow_mut().redraw_requested(loop_handle, window_id)
+    }
+
+    fn close_requested(
+        &mut self,
+        loop_handle: &mut dyn EventLoopHandle,
+        window_id: WindowId,
+    ) -> bool {
+        self.0.borrow_mut().close_requested(loop_handle, window_id)
+    }
+
+    fn destroyed(&mut self, loop_handle: &mut dyn EventLoopHandle, window_id: WindowId) {
+        self.0.borrow_mut().destroyed(loop_handle, window_id)
+    }
+}
+
+/// Spawn the compositor along with the event loop connected to it.
+pub(crate) fn spawn_event_loop<T: Application + 'static>(
+    config: Config,
+) -> (TestCompositor, EventLoop<T>) {
+    let (compositor, connection) = TestCompositor::spawn(config).unwrap();
+    let event_loop = EventLoop::with_connection(connection).unwrap();
+    (compositor, event_loop)
+}
+
+/// Pump the event loop once without blocking.
+pub(crate) fn pump(event_loop: &mut EventLoop<TestApp>, app: &mut TestApp) -> PumpStatus {
+    event_loop.pump_events(Some(Duration::ZERO), app).unwrap()
+}
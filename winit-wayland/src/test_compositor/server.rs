@@ -0,0 +1,977 @@
+//! The protocol implementation of the test compositor.
+
+use wayland_protocols::wp::cursor_shape::v1::server::wp_cursor_shape_device_v1::{
+    self, WpCursorShapeDeviceV1,
+};
+use wayland_protocols::wp::cursor_shape::v1::server::wp_cursor_shape_manager_v1::{
+    self, WpCursorShapeManagerV1,
+};
+use wayland_protocols::wp::fractional_scale::v1::server::wp_fractional_scale_manager_v1::{
+    self, WpFractionalScaleManagerV1,
+};
+use wayland_protocols::wp::fractional_scale::v1::server::wp_fractional_scale_v1::{
+    self, WpFractionalScaleV1,
+};
+use wayland_protocols::wp::pointer_constraints::zv1::server::zwp_confined_pointer_v1::{
+    self, ZwpConfinedPointerV1,
+};
+use wayland_protocols::wp::pointer_constraints::zv1::server::zwp_locked_pointer_v1::{
+    self, ZwpLockedPointerV1,
+};
+use wayland_protocols::wp::pointer_constraints::zv1::server::zwp_pointer_constraints_v1::{
+    self, ZwpPointerConstraintsV1,
+};
+use wayland_protocols::wp::relative_pointer::zv1::server::zwp_relative_pointer_manager_v1::{
+    self, ZwpRelativePointerManagerV1,
+};
+use wayland_protocols::wp::relative_pointer::zv1::server::zwp_relative_pointer_v1::{
+    self, ZwpRelativePointerV1,
+};
+use wayland_protocols::xdg::decoration::zv1::server::zxdg_decoration_manager_v1::{
+    self, ZxdgDecorationManagerV1,
+};
+use wayland_protocols::xdg::decoration::zv1::server::zxdg_toplevel_decoration_v1::{
+    self, Mode as DecorationMode, ZxdgToplevelDecorationV1,
+};
+use wayland_protocols::xdg::shell::server::xdg_popup::{self, XdgPopup};
+use wayland_protocols::xdg::shell::server::xdg_positioner::{self, XdgPositioner};
+use wayland_protocols::xdg::shell::server::xdg_surface::{self, XdgSurface};
+use wayland_protocols::xdg::shell::server::xdg_toplevel::{self, XdgToplevel};
+use wayland_protocols::xdg::shell::server::xdg_wm_base::{self, XdgWmBase};
+use wayland_server::backend::GlobalId;
+use wayland_server::protocol::wl_buffer::{self, WlBuffer};
+use wayland_server::protocol::wl_callback::{self, WlCallback};
+use wayland_server::protocol::wl_compositor::{self, WlCompositor};
+use wayland_server::protocol::wl_keyboard::{self, WlKeyboard};
+use wayland_server::protocol::wl_output::{self, WlOutput};
+use wayland_server::protocol::wl_pointer::{self, WlPointer};
+use wayland_server::protocol::wl_region::{self, WlRegion};
+use wayland_server::protocol::wl_seat::{self, WlSeat};
+use wayland_server::protocol::wl_shm::{self, WlShm};
+use wayland_server::protocol::wl_shm_pool::{self, WlShmPool};
+use wayland_server::protocol::wl_surface::{self, WlSurface};
+use wayland_server::protocol::wl_touch::{self, WlTouch};
+use wayland_server::{
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, WEnum,
+};
+
+use crate::icon::protocol::server::xdg_toplevel_icon_manager_v1::{self, XdgToplevelIconManagerV1};
+use crate::icon::protocol::server::xdg_toplevel_icon_v1::{self, XdgToplevelIconV1};
+
+use super::{Config, OutputConfig, PointerConstraint, PointerCursor, Toplevel, ToplevelIcon};
+
+/// The state of the compositor.
+pub(crate) struct ServerState {
+    display_handle: DisplayHandle,
+
+    /// The last serial sent to the client.
+    serial: u32,
+
+    /// All the surfaces created by the client, indexed by their user data.
+    surfaces: Vec<Surface>,
+
+    /// All the toplevels created by the client, indexed by their user data.
+    toplevels: Vec<ToplevelSlot>,
+
+    /// The outputs, `None` once removed.
+    outputs: Vec<Option<Output>>,
+
+    /// All the toplevel icons created by the client, indexed by their user
+    /// data.
+    icons: Vec<ToplevelIcon>,
+
+    /// Whether the seat has the pointer capability.
+    has_pointer: bool,
+
+    /// The pointers created by the client.
+    pointers: Vec<WlPointer>,
+
+    /// The last cursor set by the client with its serial.
+    cursor: Option<(u32, PointerCursor)>,
+
+    /// The constraint of the pointer.
+    pointer_constraint: Option<PointerConstraint>,
+
+    /// The relative pointers created by the client.
+    relative_pointers: Vec<ZwpRelativePointerV1>,
+}
+
+struct Surface {
+    wl_surface: WlSurface,
+    buffer_scale: i32,
+
+    /// The size of the attached buffer.
+    buffer: Option<(i32, i32)>,
+    commits: usize,
+    frame_callbacks: Vec<WlCallback>,
+    fractional_scale: Option<WpFractionalScaleV1>,
+}
+
+struct ToplevelSlot {
+    /// The index of the surface.
+    surface: usize,
+    xdg_surface: XdgSurface,
+    xdg_toplevel: XdgToplevel,
+    record: Toplevel,
+}
+
+struct Output {
+    config: OutputConfig,
+    global: GlobalId,
+    resources: Vec<WlOutput>,
+}
+
+impl ServerState {
+    pub(crate) fn new(display_handle: DisplayHandle, config: Config) -> Self {
+        display_handle.create_global::<Self, WlCompositor, ()>(6, ());
+        display_handle.create_global::<Self, WlShm, ()>(1, ());
+
+        if config.xdg_wm_base {
+            display_handle.create_global::<Self, XdgWmBase, ()>(6, ());
+        }
+
+        if config.seat {
+            display_handle.create_global::<Self, WlSeat, ()>(7, ());
+        }
+
+        if config.cursor_shape {
+            display_handle.create_global::<Self, WpCursorShapeManagerV1, ()>(1, ());
+        }
+
+        if config.pointer_constraints {
+            display_handle.create_global::<Self, ZwpPointerConstraintsV1, ()>(1, ());
+        }
+
+        if config.relative_pointer {
+            display_handle.create_global::<Self, ZwpRelativePointerManagerV1, ()>(1, ());
+        }
+
+        if config.fractional_scale {
+            display_handle.create_global::<Self, WpFractionalScaleManagerV1, ()>(1, ());
+        }
+
+        if config.xdg_decoration {
+            display_handle.create_global::<Self, ZxdgDecorationManagerV1, ()>(1, ());
+        }
+
+        if config.toplevel_icon {
+            display_handle.create_global::<Self, XdgToplevelIconManagerV1, ()>(1, ());
+        }
+
+        let mut state = Self {
+            display_handle,
+            serial: 0,
+            surfaces: Vec::new(),
+            toplevels: Vec::new(),
+            outputs: Vec::new(),
+            icons: Vec::new(),
+            has_pointer: config.pointer,
+            pointers: Vec::new(),
+            cursor: None,
+            pointer_constraint: None,
+            relative_pointers: Vec::new(),
+        };
+
+        for output in config.outputs {
+            state.add_output(output);
+        }
+
+        state
+    }
+
+    /// The record of the toplevel with the given `xdg_surface`.
+    fn record_mut(&mut self, xdg_surface: &XdgSurface) -> Option<&mut Toplevel> {
+        let toplevel =
+            self.toplevels.iter_mut().find(|toplevel| toplevel.xdg_surface == *xdg_surface)?;
+        Some(&mut toplevel.record)
+    }
+
+    fn next_serial(&mut self) -> u32 {
+        self.serial += 1;
+        self.serial
+    }
+
+    pub(crate) fn toplevels(&self) -> Vec<Toplevel> {
+        self.toplevels
+            .iter()
+            .map(|toplevel| {
+                let surface = &self.surfaces[toplevel.surface];
+                Toplevel {
+                    buffer_scale: surface.buffer_scale,
+                    commits: surface.commits,
+                    frame_callbacks: surface.frame_callbacks.len(),
+                    ..toplevel.record.clone()
+                }
+            })
+            .collect()
+    }
+
+    pub(crate) fn configure(
+        &mut self,
+        index: usize,
+        size: Option<(i32, i32)>,
+        states: &[xdg_toplevel::State],
+    ) -> u32 {
+        let serial = self.next_serial();
+        let toplevel = &self.toplevels[index];
+        let (width, height) = size.unwrap_or((0, 0));
+        let states = states.iter().flat_map(|state| (*state as u32).to_ne_bytes()).collect();
+        toplevel.xdg_toplevel.configure(width, height, states);
+        toplevel.xdg_surface.configure(serial);
+        serial
+    }
+
+    pub(crate) fn close(&mut self, index: usize) {
+        self.toplevels[index].xdg_toplevel.close();
+    }
+
+    pub(crate) fn set_buffer_scale(&mut self, index: usize, scale: i32) {
+        let surface = &self.surfaces[self.toplevels[index].surface];
+        surface.wl_surface.preferred_buffer_scale(scale);
+    }
+
+    pub(crate) fn set_fractional_scale(&mut self, index: usize, scale: f64) -> bool {
+        let surface = &self.surfaces[self.toplevels[index].surface];
+        match surface.fractional_scale.as_ref() {
+            Some(fractional_scale) => {
+                fractional_scale.preferred_scale((scale * 120.).round() as u32);
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub(crate) fn frame_done(&mut self, index: usize) {
+        let surface = &mut self.surfaces[self.toplevels[index].surface];
+        for callback in surface.frame_callbacks.drain(..) {
+            callback.done(0);
+        }
+    }
+
+    pub(crate) fn add_output(&mut self, config: OutputConfig) -> usize {
+        let index = self.outputs.len();
+        let global = self.display_handle.create_global::<Self, WlOutput, usize>(4, index);
+        self.outputs.push(Some(Output { config, global, resources: Vec::new() }));
+        index
+    }
+
+    pub(crate) fn remove_output(&mut self, index: usize) {
+        if let Some(output) = self.outputs[index].take() {
+            self.display_handle.remove_global::<Self>(output.global);
+        }
+    }
+
+    pub(crate) fn enter_output(&mut self, index: usize, output: usize) {
+        let surface = &self.surfaces[self.toplevels[index].surface];
+        for resource in &self.outputs[output].as_ref().unwrap().resources {
+            surface.wl_surface.enter(resource);
+        }
+    }
+
+    pub(crate) fn leave_output(&mut self, index: usize, output: usize) {
+        let surface = &self.surfaces[self.toplevels[index].surface];
+        for resource in &self.outputs[output].as_ref().unwrap().resources {
+            surface.wl_surface.leave(resource);
+        }
+    }
+
+    pub(crate) fn pointer_enter(&mut self, index: usize) -> u32 {
+        let serial = self.next_serial();
+        let surface = &self.surfaces[self.toplevels[index].surface];
+        for pointer in &self.pointers {
+            pointer.enter(serial, &surface.wl_surface, 0., 0.);
+            pointer.frame();
+        }
+        serial
+    }
+
+    pub(crate) fn pointer_leave(&mut self, index: usize) {
+        let serial = self.next_serial();
+        let surface = &self.surfaces[self.toplevels[index].surface];
+        for pointer in &self.pointers {
+            pointer.leave(serial, &surface.wl_surface);
+            pointer.frame();
+        }
+    }
+
+    pub(crate) fn cursor(&self) -> Option<(u32, PointerCursor)> {
+        self.cursor.clone()
+    }
+
+    pub(crate) fn pointer_constraint(&self) -> Option<PointerConstraint> {
+        self.pointer_constraint.clone()
+    }
+
+    pub(crate) fn relative_motion(&mut self, (dx, dy): (f64, f64)) {
+        for relative_pointer in &self.relative_pointers {
+            relative_pointer.relative_motion(0, 0, dx * 2., dy * 2., dx, dy);
+        }
+    }
+}
+
+/// Bind the global without any events sent on bind.
+macro_rules! bind_plain {
+    ($($interface:ty),*) => {
+        $(
+            impl GlobalDispatch<$interface, ()> for ServerState {
+                fn bind(
+                    _: &mut Self,
+                    _: &DisplayHandle,
+                    _: &Client,
+                    resource: New<$interface>,
+                    _: &(),
+                    data_init: &mut DataInit<'_, Self>,
+                ) {
+                    data_init.init(resource, ());
+                }
+            }
+        )*
+    };
+}
+
+/// Ignore all the requests of the interface, none of which create objects.
+macro_rules! ignore_requests {
+    ($($interface:ty),*) => {
+        $(
+            impl Dispatch<$interface, ()> for ServerState {
+                fn request(
+                    _: &mut Self,
+                    _: &Client,
+                    _: &$interface,
+                    _: <$interface as Resource>::Request,
+                    _: &(),
+                    _: &DisplayHandle,
+                    _: &mut DataInit<'_, Self>,
+                ) {
+                }
+            }
+        )*
+    };
+}
+
+bind_plain!(
+    WlCompositor,
+    XdgWmBase,
+    WpFractionalScaleManagerV1,
+    ZxdgDecorationManagerV1,
+    XdgToplevelIconManagerV1,
+    WpCursorShapeManagerV1,
+    ZwpPointerConstraintsV1,
+    ZwpRelativePointerManagerV1
+);
+
+ignore_requests!(WlRegion, WlCallback, WlKeyboard, WlTouch, XdgPositioner, XdgPopup);
+
+impl Dispatch<WlCompositor, ()> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &WlCompositor,
+        request: wl_compositor::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            wl_compositor::Request::CreateSurface { id } => {
+                let index = state.surfaces.len();
+                let wl_surface = data_init.init(id, index);
+                state.surfaces.push(Surface {
+                    wl_surface,
+                    buffer_scale: 1,
+                    buffer: None,
+                    commits: 0,
+                    frame_callbacks: Vec::new(),
+                    fractional_scale: None,
+                });
+            },
+            wl_compositor::Request::CreateRegion { id } => {
+                data_init.init(id, ());
+            },
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<WlSurface, usize> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &WlSurface,
+        request: wl_surface::Request,
+        index: &usize,
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let surface = &mut state.surfaces[*index];
+        match request {
+            wl_surface::Request::Frame { callback } => {
+                let callback = data_init.init(callback, ());
+                surface.frame_callbacks.push(callback);
+            },
+            wl_surface::Request::SetBufferScale { scale } => surface.buffer_scale = scale,
+            wl_surface::Request::Attach { buffer, .. } => {
+                surface.buffer = buffer.and_then(|buffer| buffer.data::<(i32, i32)>().copied());
+            },
+            wl_surface::Request::Commit => surface.commits += 1,
+            _ => (),
+        }
+    }
+}
+
+impl GlobalDispatch<WlShm, ()> for ServerState {
+    fn bind(
+        _: &mut Self,
+        _: &DisplayHandle,
+        _: &Client,
+        resource: New<WlShm>,
+        _: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let shm = data_init.init(resource, ());
+        shm.format(wl_shm::Format::Argb8888);
+        shm.format(wl_shm::Format::Xrgb8888);
+    }
+}
+
+impl Dispatch<WlShm, ()> for ServerState {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &WlShm,
+        request: wl_shm::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let wl_shm::Request::CreatePool { id, .. } = request {
+            data_init.init(id, ());
+        }
+    }
+}
+
+impl Dispatch<WlShmPool, ()> for ServerState {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &WlShmPool,
+        request: wl_shm_pool::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let wl_shm_pool::Request::CreateBuffer { id, width, height, .. } = request {
+            data_init.init(id, (width, height));
+        }
+    }
+}
+
+impl Dispatch<WlBuffer, (i32, i32)> for ServerState {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &WlBuffer,
+        _: wl_buffer::Request,
+        _: &(i32, i32),
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+    }
+}
+
+impl Dispatch<XdgToplevelIconManagerV1, ()> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &XdgToplevelIconManagerV1,
+        request: xdg_toplevel_icon_manager_v1::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            xdg_toplevel_icon_manager_v1::Request::CreateIcon { id } => {
+                data_init.init(id, state.icons.len());
+                state.icons.push(ToplevelIcon::default());
+            },
+            xdg_toplevel_icon_manager_v1::Request::SetIcon { toplevel, icon } => {
+                let icon = icon.map(|icon| state.icons[*icon.data::<usize>().unwrap()].clone());
+                state.toplevels[*toplevel.data::<usize>().unwrap()].record.icon = icon;
+            },
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<XdgToplevelIconV1, usize> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &XdgToplevelIconV1,
+        request: xdg_toplevel_icon_v1::Request,
+        index: &usize,
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+        let icon = &mut state.icons[*index];
+        match request {
+            xdg_toplevel_icon_v1::Request::SetName { icon_name } => icon.name = Some(icon_name),
+            xdg_toplevel_icon_v1::Request::AddBuffer { buffer, scale } => {
+                let (width, height) = *buffer.data::<(i32, i32)>().unwrap();
+                icon.buffers.push((width, height, scale));
+            },
+            _ => (),
+        }
+    }
+}
+
+impl GlobalDispatch<WlOutput, usize> for ServerState {
+    fn bind(
+        state: &mut Self,
+        _: &DisplayHandle,
+        _: &Client,
+        resource: New<WlOutput>,
+        index: &usize,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let wl_output = data_init.init(resource, *index);
+        let output = match state.outputs[*index].as_mut() {
+            Some(output) => output,
+            None => return,
+        };
+
+        let config = &output.config;
+        wl_output.geometry(
+            config.position.0,
+            config.position.1,
+            0,
+            0,
+            wl_output::Subpixel::Unknown,
+            String::from("winit"),
+            String::from("test"),
+            wl_output::Transform::Normal,
+        );
+        wl_output.mode(
+            wl_output::Mode::Current | wl_output::Mode::Preferred,
+            config.size.0,
+            config.size.1,
+            config.refresh_millihertz,
+        );
+        if wl_output.version() >= 2 {
+            wl_output.scale(config.scale);
+        }
+        if wl_output.version() >= 4 {
+            wl_output.name(config.name.clone());
+        }
+        if wl_output.version() >= 2 {
+            wl_output.done();
+        }
+
+        output.resources.push(wl_output);
+    }
+}
+
+impl Dispatch<WlOutput, usize> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        wl_output: &WlOutput,
+        request: wl_output::Request,
+        index: &usize,
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+        if let (wl_output::Request::Release, Some(output)) = (request, &mut state.outputs[*index]) {
+            output.resources.retain(|resource| resource != wl_output);
+        }
+    }
+}
+
+impl GlobalDispatch<WlSeat, ()> for ServerState {
+    fn bind(
+        state: &mut Self,
+        _: &DisplayHandle,
+        _: &Client,
+        resource: New<WlSeat>,
+        _: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let seat = data_init.init(resource, ());
+        let mut capabilities = wl_seat::Capability::empty();
+        if state.has_pointer {
+            capabilities |= wl_seat::Capability::Pointer;
+        }
+        seat.capabilities(capabilities);
+        if seat.version() >= 2 {
+            seat.name(String::from("seat0"));
+        }
+    }
+}
+
+impl Dispatch<WlSeat, ()> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &WlSeat,
+        request: wl_seat::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            wl_seat::Request::GetPointer { id } => {
+                let pointer = data_init.init(id, ());
+                state.pointers.push(pointer);
+            },
+            wl_seat::Request::GetKeyboard { id } => {
+                data_init.init(id, ());
+            },
+            wl_seat::Request::GetTouch { id } => {
+                data_init.init(id, ());
+            },
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<WlPointer, ()> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        wl_pointer: &WlPointer,
+        request: wl_pointer::Request,
+        _: &(),
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            wl_pointer::Request::SetCursor { serial, surface, hotspot_x, hotspot_y } => {
+                let cursor = match surface {
+                    Some(surface) => {
+                        let surface = &state.surfaces[*surface.data::<usize>().unwrap()];
+                        PointerCursor::Surface {
+                            size: surface.buffer.unwrap_or_default(),
+                            buffer_scale: surface.buffer_scale,
+                            hotspot: (hotspot_x, hotspot_y),
+                        }
+                    },
+                    None => PointerCursor::Hidden,
+                };
+                state.cursor = Some((serial, cursor));
+            },
+            wl_pointer::Request::Release => {
+                state.pointers.retain(|pointer| pointer != wl_pointer);
+            },
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<WpCursorShapeManagerV1, ()> for ServerState {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &WpCursorShapeManagerV1,
+        request: wp_cursor_shape_manager_v1::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let wp_cursor_shape_manager_v1::Request::GetPointer { cursor_shape_device, .. } = request
+        {
+            data_init.init(cursor_shape_device, ());
+        }
+    }
+}
+
+impl Dispatch<WpCursorShapeDeviceV1, ()> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &WpCursorShapeDeviceV1,
+        request: wp_cursor_shape_device_v1::Request,
+        _: &(),
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+        if let wp_cursor_shape_device_v1::Request::SetShape { serial, shape: WEnum::Value(shape) } =
+            request
+        {
+            state.cursor = Some((serial, PointerCursor::Shape(shape)));
+        }
+    }
+}
+
+impl Dispatch<XdgWmBase, ()> for ServerState {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &XdgWmBase,
+        request: xdg_wm_base::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            xdg_wm_base::Request::CreatePositioner { id } => {
+                data_init.init(id, ());
+            },
+            xdg_wm_base::Request::GetXdgSurface { id, surface } => {
+                let index = *surface.data::<usize>().unwrap();
+                data_init.init(id, index);
+            },
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<XdgSurface, usize> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        xdg_surface: &XdgSurface,
+        request: xdg_surface::Request,
+        surface: &usize,
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            xdg_surface::Request::GetToplevel { id } => {
+                let index = state.toplevels.len();
+                let xdg_toplevel = data_init.init(id, index);
+                state.toplevels.push(ToplevelSlot {
+                    surface: *surface,
+                    xdg_surface: xdg_surface.clone(),
+                    xdg_toplevel,
+                    record: Toplevel::default(),
+                });
+            },
+            xdg_surface::Request::GetPopup { id, .. } => {
+                data_init.init(id, ());
+            },
+            xdg_surface::Request::SetWindowGeometry { x, y, width, height } => {
+                if let Some(record) = state.record_mut(xdg_surface) {
+                    record.window_geometry = Some((x, y, width, height));
+                }
+            },
+            xdg_surface::Request::AckConfigure { serial } => {
+                if let Some(record) = state.record_mut(xdg_surface) {
+                    record.acked_serial = Some(serial);
+                }
+            },
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<XdgToplevel, usize> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &XdgToplevel,
+        request: xdg_toplevel::Request,
+        index: &usize,
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+        let record = &mut state.toplevels[*index].record;
+        match request {
+            xdg_toplevel::Request::SetTitle { title } => record.title = Some(title),
+            xdg_toplevel::Request::SetAppId { app_id } => record.app_id = Some(app_id),
+            xdg_toplevel::Request::SetMinSize { width, height } => {
+                record.min_size = (width, height)
+            },
+            xdg_toplevel::Request::SetMaxSize { width, height } => {
+                record.max_size = (width, height)
+            },
+            xdg_toplevel::Request::SetMaximized => record.maximized = true,
+            xdg_toplevel::Request::UnsetMaximized => record.maximized = false,
+            xdg_toplevel::Request::SetFullscreen { output } => {
+                record.fullscreen = true;
+                record.fullscreen_output = output.and_then(|output| output.data().copied());
+            },
+            xdg_toplevel::Request::UnsetFullscreen => {
+                record.fullscreen = false;
+                record.fullscreen_output = None;
+            },
+            xdg_toplevel::Request::SetMinimized => record.minimize_requests += 1,
+            xdg_toplevel::Request::Destroy => record.destroyed = true,
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<ZxdgDecorationManagerV1, ()> for ServerState {
+    fn request(
+        _: &mut Self,
+        _: &Client,
+        _: &ZxdgDecorationManagerV1,
+        request: zxdg_decoration_manager_v1::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let zxdg_decoration_manager_v1::Request::GetToplevelDecoration { id, toplevel } = request
+        {
+            let index = *toplevel.data::<usize>().unwrap();
+            data_init.init(id, index);
+        }
+    }
+}
+
+impl Dispatch<ZxdgToplevelDecorationV1, usize> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        decoration: &ZxdgToplevelDecorationV1,
+        request: zxdg_toplevel_decoration_v1::Request,
+        index: &usize,
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+        let record = &mut state.toplevels[*index].record;
+        match request {
+            zxdg_toplevel_decoration_v1::Request::SetMode { mode: WEnum::Value(mode) } => {
+                record.decoration_mode = Some(mode);
+                decoration.configure(mode);
+            },
+            zxdg_toplevel_decoration_v1::Request::UnsetMode => {
+                record.decoration_mode = None;
+                decoration.configure(DecorationMode::ServerSide);
+            },
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<WpFractionalScaleManagerV1, ()> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &WpFractionalScaleManagerV1,
+        request: wp_fractional_scale_manager_v1::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let wp_fractional_scale_manager_v1::Request::GetFractionalScale { id, surface } = request
+        {
+            let index = *surface.data::<usize>().unwrap();
+            let fractional_scale = data_init.init(id, index);
+            state.surfaces[index].fractional_scale = Some(fractional_scale);
+        }
+    }
+}
+
+impl Dispatch<WpFractionalScaleV1, usize> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &WpFractionalScaleV1,
+        request: wp_fractional_scale_v1::Request,
+        index: &usize,
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+        if let wp_fractional_scale_v1::Request::Destroy = request {
+            state.surfaces[*index].fractional_scale = None;
+        }
+    }
+}
+
+impl Dispatch<ZwpPointerConstraintsV1, ()> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &ZwpPointerConstraintsV1,
+        request: zwp_pointer_constraints_v1::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwp_pointer_constraints_v1::Request::ConfinePointer { id, .. } => {
+                data_init.init(id, ());
+                state.pointer_constraint = Some(PointerConstraint::Confined);
+            },
+            zwp_pointer_constraints_v1::Request::LockPointer { id, .. } => {
+                data_init.init(id, ());
+                state.pointer_constraint = Some(PointerConstraint::Locked { position_hint: None });
+            },
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<ZwpConfinedPointerV1, ()> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &ZwpConfinedPointerV1,
+        request: zwp_confined_pointer_v1::Request,
+        _: &(),
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+        if let zwp_confined_pointer_v1::Request::Destroy = request {
+            state.pointer_constraint = None;
+        }
+    }
+}
+
+impl Dispatch<ZwpLockedPointerV1, ()> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &ZwpLockedPointerV1,
+        request: zwp_locked_pointer_v1::Request,
+        _: &(),
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwp_locked_pointer_v1::Request::SetCursorPositionHint { surface_x, surface_y } => {
+                if let Some(PointerConstraint::Locked { position_hint }) =
+                    state.pointer_constraint.as_mut()
+                {
+                    *position_hint = Some((surface_x, surface_y));
+                }
+            },
+            zwp_locked_pointer_v1::Request::Destroy => state.pointer_constraint = None,
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<ZwpRelativePointerManagerV1, ()> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        _: &ZwpRelativePointerManagerV1,
+        request: zwp_relative_pointer_manager_v1::Request,
+        _: &(),
+        _: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let zwp_relative_pointer_manager_v1::Request::GetRelativePointer { id, .. } = request {
+            let relative_pointer = data_init.init(id, ());
+            state.relative_pointers.push(relative_pointer);
+        }
+    }
+}
+
+impl Dispatch<ZwpRelativePointerV1, ()> for ServerState {
+    fn request(
+        state: &mut Self,
+        _: &Client,
+        relative_pointer: &ZwpRelativePointerV1,
+        request: zwp_relative_pointer_v1::Request,
+        _: &(),
+        _: &DisplayHandle,
+        _: &mut DataInit<'_, Self>,
+    ) {
+        if let zwp_relative_pointer_v1::Request::Destroy = request {
+            state.relative_pointers.retain(|other| other != relative_pointer);
+        }
+    }
+}
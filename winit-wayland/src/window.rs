@@ -1,5 +1,6 @@
 use std::num::NonZeroU32;
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
 
 use raw_window_handle::{HandleError, HasWindowHandle, WaylandWindowHandle, WindowHandle};
 use raw_window_handle_05::HasRawWindowHandle as HasRawWindowHandle05;
@@ -13,6 +14,7 @@ use sctk::reexports::protocols::wp::fractional_scale::v1::client::wp_fractional_
 use sctk::reexports::protocols::wp::text_input::zv3::client::zwp_text_input_v3::ZwpTextInputV3;
 use sctk::reexports::protocols::wp::viewporter::client::wp_viewport::WpViewport;
 use sctk::reexports::protocols::xdg::shell::client::xdg_toplevel::ResizeEdge as XdgResizeEdge;
+use sctk::seat::pointer_constraints::PointerConstraintsState;
 use sctk::shell::xdg::window::{
     DecorationMode, Window as XdgWindow, WindowConfigure, WindowDecorations, WindowHandler,
 };
@@ -21,13 +23,20 @@ use sctk::shell::WaylandSurface;
 
 use wayland_client::{Connection, QueueHandle};
 use winit_core::application::Application;
-use winit_core::dpi::{LogicalSize, PhysicalSize, Size};
+use winit_core::cursor::{Cursor, CursorGrabMode};
+use winit_core::dpi::{LogicalSize, PhysicalSize, Position, Size};
+use winit_core::error::{NotSupported, RequestError};
+use winit_core::event_loop::EventLoopHandle;
 use winit_core::monitor::MonitorId;
-use winit_core::window::{Theme, Window as CoreWindow, WindowAttributes, WindowId};
+use winit_core::window::{
+    Fullscreen, Icon, Theme, Window as CoreWindow, WindowAttributes, WindowId,
+};
 
 use crate::event_loop::RuntimeState;
+use crate::icon::{IconManager, ToplevelIcon};
 use crate::logical_to_physical_rounded;
-use crate::monitor::Monitor;
+use crate::monitor::{Monitor, MonitorIds};
+use crate::seat::WinitPointer;
 use crate::state::WinitState;
 
 // Minimum window inner size.
@@ -38,10 +47,26 @@ type WinitFrame<T> = sctk_adwaita::AdwaitaFrame<RuntimeState<T>>;
 #[cfg(not(feature = "sctk-adwaita"))]
 type WinitFrame = sctk::shell::xdg::fallback_frame::FallbackFrame<RuntimeState>;
 
+/// How long to wait for the initial configure before giving up on the
+/// window.
+pub(crate) const CONFIGURE_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub struct Window<T: Application + 'static> {
+    /// The id of the window.
+    id: WindowId,
+
+    /// When the window was requested by the user.
+    pub(crate) requested_at: Instant,
+
+    /// The ids of the monitors to resolve the output the window is on.
+    monitor_ids: MonitorIds,
+
     /// The last received configure.
     pub last_configure: Option<WindowConfigure>,
 
+    /// Whether the user was notified about the window creation.
+    pub created: bool,
+
     pub viewport: Option<WpViewport>,
     fractional_scale: Option<WpFractionalScaleV1>,
 
@@ -92,8 +117,35 @@ pub struct Window<T: Application + 'static> {
     /// iteration.
     csd_fails: bool,
 
+    /// The manager to set the icon with, when the compositor supports it.
+    icon_manager: Option<IconManager>,
+
+    /// The icon set on the window.
+    icon: Option<ToplevelIcon>,
+
+    /// The cursor to show over the window.
+    cursor: Cursor,
+
+    /// Whether the cursor is visible over the window.
+    cursor_visible: bool,
+
+    /// The pointers over the window.
+    pointers: Vec<Weak<WinitPointer>>,
+
+    /// The constraints to grab the pointer with, when the compositor supports
+    /// them.
+    pointer_constraints: Option<Arc<PointerConstraintsState>>,
+
+    /// How the pointers over the window are held.
+    cursor_grab: CursorGrabMode,
+
     pub redraw: bool,
 
+    /// The state of the frame callback.
+    pub(crate) frame_callback_state: FrameCallbackState,
+
+    queue_handle: QueueHandle<RuntimeState<T>>,
+
     // Note, should be the last since it drops the surface.
     pub window: XdgWindow,
 }
@@ -120,6 +172,9 @@ impl<T: Application + 'static> Window<T> {
 
         // Create the window with some defaults.
         let mut window = Self {
+            id: winit.ids.next_window_id(),
+            requested_at: winit.clock.now(),
+            monitor_ids: winit.monitor_ids.clone(),
             decorate: attributes.decorations(),
             min_inner_size: MIN_WINDOW_SIZE,
             stateless_size: size.to_logical(1.),
@@ -127,6 +182,7 @@ impl<T: Application + 'static> Window<T> {
             initial_size: Some(size),
             max_inner_size: None,
             last_configure: None,
+            created: false,
             transparent: true,
             fractional_scale,
             scale_factor: 1.,
@@ -134,7 +190,16 @@ impl<T: Application + 'static> Window<T> {
             csd_fails: false,
             resizable: true,
             redraw: false,
+            frame_callback_state: FrameCallbackState::None,
+            queue_handle: winit.queue_handle.clone(),
             frame: None,
+            icon_manager: winit.icon_manager.clone(),
+            icon: None,
+            cursor: attributes.cursor(),
+            cursor_visible: true,
+            pointers: Vec::new(),
+            pointer_constraints: winit.pointer_constraints.clone(),
+            cursor_grab: CursorGrabMode::None,
             theme: None,
             compositor,
             viewport,
@@ -152,12 +217,20 @@ impl<T: Application + 'static> Window<T> {
 
         window.set_resizable(attributes.resizable());
 
+        if let Some(icon) = attributes.window_icon() {
+            window.set_window_icon(Some(icon.clone()));
+        }
+
         // window.set_
 
         if attributes.maximized() {
             window.set_maximized(true);
         }
 
+        if let Some(fullscreen) = attributes.fullscreen() {
+            window.set_fullscreen(Some(fullscreen));
+        }
+
         // TODO: platform attributes.
 
         // NOTE: initial commit for the window.
@@ -197,7 +270,7 @@ impl<T: Application + 'static> Window<T> {
     pub fn set_min_inner_size(&mut self, size: Option<Size>) {
         let mut size =
             size.map(|size| size.to_logical(self.scale_factor)).unwrap_or(MIN_WINDOW_SIZE);
-        size.height = size.width.max(MIN_WINDOW_SIZE.width);
+        size.height = size.height.max(MIN_WINDOW_SIZE.height);
         size.width = size.width.max(MIN_WINDOW_SIZE.width);
         // TODO borders
         self.window.set_min_size(Some(size.into()));
@@ -268,6 +341,52 @@ impl<T: Application + 'static> Window<T> {
         if let Some(frame) = self.frame.as_mut() {
             frame.set_scaling_factor(scale_factor);
         }
+
+        // The cursor from the xcursor theme is loaded for the scale of the window.
+        self.reload_cursor();
+    }
+
+    /// The `pointer` has entered the window.
+    pub(crate) fn pointer_entered(&mut self, pointer: &Arc<WinitPointer>) {
+        let weak = Arc::downgrade(pointer);
+        if !self.pointers.iter().any(|other| other.ptr_eq(&weak)) {
+            self.pointers.push(weak);
+        }
+
+        // NOTE: the cursor must be set again on every enter.
+        self.apply_cursor(pointer);
+        self.apply_cursor_grab(pointer);
+    }
+
+    /// The `pointer` has left the window.
+    pub(crate) fn pointer_left(&mut self, pointer: &Arc<WinitPointer>) {
+        pointer.release_grab();
+        let weak = Arc::downgrade(pointer);
+        self.pointers.retain(|other| !other.ptr_eq(&weak) && other.strong_count() > 0);
+    }
+
+    /// Set the cursor of the window for the `pointer` over it.
+    fn apply_cursor(&self, pointer: &WinitPointer) {
+        if self.cursor_visible {
+            pointer.set_cursor(self.cursor, self.scale_factor);
+        } else {
+            pointer.hide_cursor();
+        }
+    }
+
+    /// Set the cursor of the window for all the pointers over it.
+    fn reload_cursor(&self) {
+        for pointer in self.pointers.iter().filter_map(Weak::upgrade) {
+            self.apply_cursor(&pointer);
+        }
+    }
+
+    /// Grab the `pointer` over the window.
+    fn apply_cursor_grab(&self, pointer: &WinitPointer) {
+        if let Some(constraints) = self.pointer_constraints.as_ref() {
+            let surface = self.window.wl_surface();
+            pointer.set_grab(constraints, surface, self.cursor_grab, &self.queue_handle);
+        }
     }
 
     /// Reissue the transparency hint to the compositor.
@@ -293,17 +412,69 @@ impl<T: Application + 'static> Window<T> {
     pub(crate) fn configured(&self) -> bool {
         self.last_configure.is_some()
     }
+
+    /// Whether the pending redraw could be delivered to the user.
+    ///
+    /// The redraw is throttled until the compositor signals that the previous
+    /// frame was consumed.
+    pub(crate) fn redraw_ready(&self) -> bool {
+        self.redraw && self.frame_callback_state != FrameCallbackState::Requested
+    }
+
+    /// Take the pending redraw if it could be delivered to the user.
+    pub(crate) fn take_redraw(&mut self) -> bool {
+        if !self.redraw_ready() {
+            return false;
+        }
+
+        self.redraw = false;
+        self.frame_callback_state = FrameCallbackState::None;
+        true
+    }
+
+    /// The frame callback requested with `pre_present_notify` has arrived.
+    pub(crate) fn frame_callback_received(&mut self) {
+        self.frame_callback_state = FrameCallbackState::Received;
+    }
+}
+
+/// The state of the `wl_surface.frame` callback.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FrameCallbackState {
+    /// No frame callback was requested.
+    #[default]
+    None,
+
+    /// The frame callback was requested, but not yet received.
+    Requested,
+
+    /// The frame callback was received, but not yet handled.
+    Received,
 }
 
 impl<T: Application + 'static> CoreWindow for Window<T> {
     fn id(&self) -> WindowId {
-        crate::make_wid(&self.window.wl_surface())
+        self.id
+    }
+
+    fn is_configured(&self) -> bool {
+        self.created
     }
 
     fn request_redraw(&mut self) {
         self.redraw = true;
     }
 
+    fn pre_present_notify(&mut self) {
+        if self.frame_callback_state == FrameCallbackState::Requested {
+            return;
+        }
+
+        self.frame_callback_state = FrameCallbackState::Requested;
+        let surface = self.window.wl_surface();
+        surface.frame(&self.queue_handle, surface.clone());
+    }
+
     fn title(&self) -> &str {
         &self.title
     }
@@ -363,9 +534,81 @@ impl<T: Application + 'static> CoreWindow for Window<T> {
         }
     }
 
+    fn set_fullscreen(&mut self, fullscreen: Option<Fullscreen>) {
+        match fullscreen {
+            Some(Fullscreen::Borderless(monitor_id)) => {
+                // Let the compositor pick the output when the monitor is gone.
+                let output = monitor_id.and_then(|monitor_id| self.monitor_ids.output(monitor_id));
+                self.window.set_fullscreen(output.as_ref());
+            },
+            None => self.window.unset_fullscreen(),
+        }
+    }
+
+    fn fullscreen(&self) -> Option<Fullscreen> {
+        let configure = self.last_configure.as_ref()?;
+        configure.is_fullscreen().then(|| Fullscreen::Borderless(self.current_monitor()))
+    }
+
+    fn set_window_icon(&mut self, icon: Option<Icon>) {
+        let icon_manager = match self.icon_manager.as_ref() {
+            Some(icon_manager) => icon_manager,
+            None => return,
+        };
+
+        // NOTE: the icon is double buffered, it's applied with the next frame.
+        let toplevel = self.window.xdg_toplevel();
+        self.icon = icon_manager.set_icon(&self.queue_handle, toplevel, icon.as_ref());
+    }
+
+    fn set_cursor(&mut self, cursor: Cursor) {
+        self.cursor = cursor;
+        self.reload_cursor();
+    }
+
+    fn set_cursor_visible(&mut self, visible: bool) {
+        self.cursor_visible = visible;
+        self.reload_cursor();
+    }
+
+    fn set_cursor_grab(&mut self, mode: CursorGrabMode) -> Result<(), RequestError> {
+        if mode == self.cursor_grab {
+            return Ok(());
+        }
+
+        if mode != CursorGrabMode::None && self.pointer_constraints.is_none() {
+            return Err(NotSupported::new("zwp_pointer_constraints_v1 is not available").into());
+        }
+
+        self.cursor_grab = mode;
+        for pointer in self.pointers.iter().filter_map(Weak::upgrade) {
+            self.apply_cursor_grab(&pointer);
+        }
+
+        Ok(())
+    }
+
+    fn set_cursor_position(&mut self, position: Position) -> Result<(), RequestError> {
+        if self.cursor_grab != CursorGrabMode::Locked {
+            return Err(
+                NotSupported::new("the pointer could be positioned only when locked").into()
+            );
+        }
+
+        let position = position.to_logical::<f64>(self.scale_factor);
+        for pointer in self.pointers.iter().filter_map(Weak::upgrade) {
+            pointer.set_lock_position_hint(position.x, position.y);
+        }
+
+        // NOTE: the hint is double buffered, it's applied with the next commit.
+        self.window.wl_surface().commit();
+
+        Ok(())
+    }
+
     fn current_monitor(&self) -> Option<MonitorId> {
         let data = self.window.wl_surface().data::<SurfaceData>()?;
-        data.outputs().next().as_ref().map(crate::make_mid)
+        data.outputs().next().and_then(|output| self.monitor_ids.get(&output))
     }
 
     fn primary_monitor(&self) -> Option<MonitorId> {
@@ -386,15 +629,21 @@ impl<T: Application + 'static> HasWindowHandle for Window<T> {
 
 impl<T: Application + 'static> WindowHandler for RuntimeState<T> {
     fn request_close(&mut self, _: &Connection, _: &QueueHandle<Self>, window: &XdgWindow) {
-        let window_id = crate::make_wid(window.wl_surface());
-        let user_state = self.user.as_mut().unwrap();
-        if user_state.close_requested(&mut self.winit, window_id) {
-            // Instantly drop the window.
-            drop(self.winit.windows.remove(&window_id));
-            user_state.destroyed(&mut self.winit, window_id);
-        }
+        let window_id = match self.winit.window_id(window.wl_surface()) {
+            Some(window_id) => window_id,
+            None => return,
+        };
+        self.callbacks.push_window(window_id, move |user, winit| {
+            let close = winit.with_handler(user, window_id, |handler, winit| {
+                handler.close_requested(winit, window_id)
+            });
+            if close {
+                winit.destroy_window(window_id);
+            }
+        });
     }
 
+    #[allow(clippy::disallowed_names)]
     fn configure(
         &mut self,
         _: &Connection,
@@ -403,13 +652,35 @@ impl<T: Application + 'static> WindowHandler for RuntimeState<T> {
         configure: WindowConfigure,
         _serial: u32,
     ) {
-        let winit = &mut self.winit;
-        let window_id = crate::make_wid(window.wl_surface());
-        let window = match winit.windows.get_mut(&window_id) {
-            Some(window) => window,
+        let window_id = match self.winit.window_id(window.wl_surface()) {
+            Some(window_id) => window_id,
             None => return,
         };
 
+        // Apply the configure along with the callbacks, so they see the window in
+        // the state they report even when more configures follow in the same
+        // dispatch.
+        let foo = self.vtable.foo;
+        let queue_handle = queue_handle.clone();
+        self.callbacks.push_window(window_id, move |user, winit| {
+            Self::apply_configure(user, winit, &queue_handle, window_id, configure, foo)
+        });
+    }
+}
+
+impl<T: Application + 'static> RuntimeState<T> {
+    /// Apply the `configure` to the window and let the user know about it.
+    #[allow(clippy::disallowed_names)]
+    fn apply_configure(
+        user: &mut T,
+        winit: &mut WinitState<T>,
+        queue_handle: &QueueHandle<Self>,
+        window_id: WindowId,
+        configure: WindowConfigure,
+        foo: Option<fn(&mut T)>,
+    ) {
+        let window = winit.windows.get_mut(&window_id).unwrap();
+
         let scale_factor = window.scale_factor;
 
         // NOTE: when using fractional scaling or wl_compositor@v6 the scaling
@@ -457,29 +728,48 @@ impl<T: Application + 'static> WindowHandler for RuntimeState<T> {
             _ => (window.size, true),
         };
 
-        let user = self.user.as_mut().unwrap();
         let initial_configue = window.last_configure.is_none();
         window.last_configure = Some(configure);
 
         window.resize(new_size);
 
-        if let Some(foo) = self.vtable.foo {
-            println!("Calling optional method!");
+        let size = logical_to_physical_rounded(new_size, scale_factor);
+        if let Some(foo) = foo {
             foo(user);
         }
 
-        // NOTE: we consider window as created when its initial configure arrives, until
-        // then it's considered as not created and attempt to get it will result in
-        // error.
+        // NOTE: we consider window as created when its initial configure arrives,
+        // until then it's considered as not created and attempt to get it will
+        // result in error.
+        // The user could destroy the window from any of the callbacks.
+        let live = |winit: &WinitState<T>| winit.windows.contains_key(&window_id);
         if initial_configue {
-            user.created(winit, window_id);
-            user.scale_factor_changed(winit, window_id, scale_factor);
+            match winit.windows.get_mut(&window_id) {
+                Some(window) => window.created = true,
+                None => return,
+            }
+
+            winit.with_handler(user, window_id, |handler, winit| handler.created(winit, window_id));
+            if !live(winit) {
+                return;
+            }
+
+            winit.with_handler(user, window_id, |handler, winit| {
+                handler.scale_factor_changed(winit, window_id, scale_factor)
+            });
+            if !live(winit) {
+                return;
+            }
         }
 
-        user.resized(winit, window_id, logical_to_physical_rounded(new_size, scale_factor));
+        winit.with_handler(user, window_id, |handler, winit| {
+            handler.resized(winit, window_id, size)
+        });
 
-        if initial_configue {
-            user.redraw_requested(winit, window_id);
+        if initial_configue && live(winit) {
+            winit.with_handler(user, window_id, |handler, winit| {
+                handler.redraw_requested(winit, window_id)
+            });
         }
     }
 }
@@ -500,3 +790,424 @@ fn into_sctk_adwaita_config(theme: Option<Theme>) -> sctk_adwaita::FrameConfig {
         None => sctk_adwaita::FrameConfig::auto(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use winit_core::cursor::{CursorGrabMode, CursorIcon, CursorImage, CustomCursor};
+    use winit_core::dpi::{LogicalPosition, LogicalSize, PhysicalSize};
+    use winit_core::error::RequestError;
+    use winit_core::event_loop::clock::ManualClock;
+    use winit_core::event_loop::EventLoopHandle;
+    use winit_core::monitor::Monitor as CoreMonitor;
+    use winit_core::window::{Fullscreen, Icon, Window as CoreWindow, WindowAttributes, WindowId};
+
+    use crate::event_loop::EventLoop;
+    use crate::test_compositor::{
+        self, Config, CursorShape, PointerConstraint, PointerCursor, TestApp, TestCompositor,
+        TestEvent, ToplevelIcon, ToplevelState,
+    };
+
+    /// Spawn the loop with a single window and configure it.
+    fn configured_window() -> (TestCompositor, EventLoop<TestApp>, TestApp, WindowId) {
+        configured_window_with(Config::default())
+    }
+
+    /// Spawn the loop with a single window on the compositor with `config`
+    /// and configure it.
+    fn configured_window_with(
+        config: Config,
+    ) -> (TestCompositor, EventLoop<TestApp>, TestApp, WindowId) {
+        let (compositor, mut event_loop) = test_compositor::spawn_event_loop(config);
+        let mut app = TestApp::new(vec![WindowAttributes::default()]);
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        compositor.configure(0, Some((320, 240)), vec![]);
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        let window_id = match app.take_events().first() {
+            Some(TestEvent::Created(window_id)) => *window_id,
+            events => panic!("unexpected events {events:?}"),
+        };
+
+        (compositor, event_loop, app, window_id)
+    }
+
+    #[test]
+    fn initial_attributes() {
+        let (compositor, mut event_loop) = test_compositor::spawn_event_loop(Config::default());
+        let attributes = WindowAttributes::default()
+            .with_title("Hello")
+            .with_min_inner_size(LogicalSize::new(100, 50))
+            .with_max_inner_size(LogicalSize::new(800, 600))
+            .with_maximized(true);
+        let mut app = TestApp::new(vec![attributes]);
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        let toplevel = compositor.toplevel(0);
+        assert_eq!(toplevel.title.as_deref(), Some("Hello"));
+        assert_eq!(toplevel.min_size, (100, 50));
+        assert_eq!(toplevel.max_size, (800, 600));
+        assert!(toplevel.maximized);
+        assert!(toplevel.commits > 0);
+
+        // The window is not created until the initial configure.
+        assert_eq!(app.take_events(), []);
+    }
+
+    #[test]
+    fn initial_configure() {
+        let (compositor, mut event_loop) = test_compositor::spawn_event_loop(Config::default());
+        let mut app = TestApp::new(vec![WindowAttributes::default()]);
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        let serial = compositor.configure(0, Some((320, 240)), vec![]);
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        let events = app.take_events();
+        let window_id = match events.first() {
+            Some(TestEvent::Created(window_id)) => *window_id,
+            _ => panic!("unexpected events {events:?}"),
+        };
+        assert_eq!(events, [
+            TestEvent::Created(window_id),
+            TestEvent::ScaleFactorChanged(window_id, 1.),
+            TestEvent::Resized(window_id, PhysicalSize::new(320, 240)),
+            TestEvent::RedrawRequested(window_id),
+        ]);
+
+        let toplevel = compositor.toplevel(0);
+        assert_eq!(toplevel.acked_serial, Some(serial));
+        assert_eq!(toplevel.window_geometry, Some((0, 0, 320, 240)));
+    }
+
+    #[test]
+    fn configures_in_one_dispatch() {
+        let (compositor, mut event_loop, mut app, window_id) = configured_window();
+
+        compositor.configure(0, Some((640, 480)), vec![]);
+        compositor.configure(0, Some((800, 600)), vec![]);
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        // The application checks that the window has the size it's told about.
+        assert_eq!(app.take_events(), [
+            TestEvent::Resized(window_id, PhysicalSize::new(640, 480)),
+            TestEvent::Resized(window_id, PhysicalSize::new(800, 600)),
+        ]);
+    }
+
+    #[test]
+    fn close() {
+        let (compositor, mut event_loop, mut app, window_id) = configured_window();
+
+        compositor.close(0);
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(app.take_events(), [
+            TestEvent::CloseRequested(window_id),
+            TestEvent::Destroyed(window_id)
+        ]);
+        assert!(compositor.toplevel(0).destroyed);
+    }
+
+    #[test]
+    fn destroy_running_window() {
+        let (compositor, mut event_loop, mut app, window_id) = configured_window();
+
+        app.destroy_on_resized = true;
+        compositor.configure(0, Some((640, 480)), vec![]);
+        compositor.close(0);
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        // The close request is dropped along with the window.
+        assert_eq!(app.take_events(), [
+            TestEvent::Resized(window_id, PhysicalSize::new(640, 480)),
+            TestEvent::Destroyed(window_id)
+        ]);
+        assert!(event_loop.winit().windows.is_empty());
+        assert!(compositor.toplevel(0).destroyed);
+    }
+
+    #[test]
+    fn window_id_not_reused() {
+        let (compositor, mut event_loop, mut app, window_id) = configured_window();
+
+        compositor.close(0);
+        test_compositor::pump(&mut event_loop, &mut app);
+        app.take_events();
+
+        event_loop.winit().create_window(&WindowAttributes::default()).unwrap();
+        test_compositor::pump(&mut event_loop, &mut app);
+        compositor.configure(1, Some((320, 240)), vec![]);
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        match app.take_events().first() {
+            Some(TestEvent::Created(new_window_id)) => assert_ne!(*new_window_id, window_id),
+            events => panic!("unexpected events {events:?}"),
+        }
+    }
+
+    #[test]
+    fn fullscreen() {
+        let (compositor, mut event_loop, mut app, window_id) = configured_window();
+        compositor.enter_output(0, 0);
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        let monitor_id = event_loop.winit().monitors[0].id();
+        let fullscreen = Some(Fullscreen::Borderless(Some(monitor_id)));
+        let window = event_loop.winit().get_window_mut(window_id).unwrap();
+        window.set_fullscreen(fullscreen);
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        let toplevel = compositor.toplevel(0);
+        assert!(toplevel.fullscreen);
+        assert_eq!(toplevel.fullscreen_output, Some(0));
+
+        // Not fullscreen until the compositor says so.
+        assert_eq!(event_loop.winit().get_window(window_id).unwrap().fullscreen(), None);
+        compositor.configure(0, Some((1920, 1080)), vec![ToplevelState::Fullscreen]);
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(event_loop.winit().get_window(window_id).unwrap().fullscreen(), fullscreen);
+
+        let window = event_loop.winit().get_window_mut(window_id).unwrap();
+        window.set_fullscreen(None);
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert!(!compositor.toplevel(0).fullscreen);
+
+        compositor.configure(0, None, vec![]);
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(event_loop.winit().get_window(window_id).unwrap().fullscreen(), None);
+    }
+
+    #[test]
+    fn window_icon() {
+        let config = Config { toplevel_icon: true, ..Default::default() };
+        let (compositor, mut event_loop) = test_compositor::spawn_event_loop(config);
+        let icon = Icon::from_name("text-editor")
+            .with_rgba(vec![255; 16 * 16 * 4], 16, 16)
+            .and_then(|icon| icon.with_rgba(vec![255; 32 * 16 * 4], 32, 16))
+            .and_then(|icon| icon.with_rgba(vec![255; 32 * 32 * 4], 32, 32))
+            .unwrap();
+        let attributes = WindowAttributes::default().with_window_icon(Some(icon));
+        let mut app = TestApp::new(vec![attributes]);
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        // The image which is not square is skipped.
+        assert_eq!(
+            compositor.toplevel(0).icon,
+            Some(ToplevelIcon {
+                name: Some(String::from("text-editor")),
+                buffers: vec![(16, 16, 1), (32, 32, 1)],
+            })
+        );
+
+        let window = event_loop.winit().windows.values_mut().next().unwrap();
+        window.set_window_icon(None);
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(compositor.toplevel(0).icon, None);
+    }
+
+    #[test]
+    fn window_icon_not_square() {
+        let config = Config { toplevel_icon: true, ..Default::default() };
+        let (compositor, mut event_loop) = test_compositor::spawn_event_loop(config);
+        let icon = Icon::from_rgba(vec![255; 32 * 16 * 4], 32, 16).unwrap();
+        let attributes = WindowAttributes::default().with_window_icon(Some(icon));
+        let mut app = TestApp::new(vec![attributes]);
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        // Without any images left the compositor falls back to the default icon.
+        let icon = compositor.toplevel(0).icon.unwrap();
+        assert_eq!(icon.name, None);
+        assert_eq!(icon.buffers, []);
+    }
+
+    #[test]
+    fn window_icon_unsupported() {
+        let (compositor, mut event_loop) = test_compositor::spawn_event_loop(Config::default());
+        let icon = Icon::from_name("text-editor");
+        let attributes = WindowAttributes::default().with_window_icon(Some(icon));
+        let mut app = TestApp::new(vec![attributes]);
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        assert_eq!(compositor.toplevel(0).icon, None);
+        assert!(!compositor.toplevel(0).destroyed);
+    }
+
+    #[test]
+    fn cursor_shape() {
+        let config = Config { pointer: true, cursor_shape: true, ..Default::default() };
+        let (compositor, mut event_loop, mut app, window_id) = configured_window_with(config);
+
+        let serial = compositor.pointer_enter(0);
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(compositor.cursor(), Some((serial, PointerCursor::Shape(CursorShape::Default))));
+
+        let window = event_loop.winit().get_window_mut(window_id).unwrap();
+        window.set_cursor(CursorIcon::Text.into());
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(compositor.cursor(), Some((serial, PointerCursor::Shape(CursorShape::Text))));
+
+        let window = event_loop.winit().get_window_mut(window_id).unwrap();
+        window.set_cursor_visible(false);
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(compositor.cursor(), Some((serial, PointerCursor::Hidden)));
+
+        // The cursor is applied only for the pointers over the window.
+        compositor.pointer_leave(0);
+        test_compositor::pump(&mut event_loop, &mut app);
+        let window = event_loop.winit().get_window_mut(window_id).unwrap();
+        window.set_cursor_visible(true);
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(compositor.cursor(), Some((serial, PointerCursor::Hidden)));
+
+        let serial = compositor.pointer_enter(0);
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(compositor.cursor(), Some((serial, PointerCursor::Shape(CursorShape::Text))));
+    }
+
+    #[test]
+    fn cursor_hidden_without_shape() {
+        let config = Config { pointer: true, ..Default::default() };
+        let (compositor, mut event_loop, mut app, window_id) = configured_window_with(config);
+
+        let window = event_loop.winit().get_window_mut(window_id).unwrap();
+        window.set_cursor_visible(false);
+        let serial = compositor.pointer_enter(0);
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(compositor.cursor(), Some((serial, PointerCursor::Hidden)));
+    }
+
+    #[test]
+    fn custom_cursor() {
+        let config = Config { pointer: true, cursor_shape: true, ..Default::default() };
+        let (compositor, mut event_loop, mut app, window_id) = configured_window_with(config);
+
+        let source = CustomCursor::from_rgba(4, 2, (1, 1), vec![255; 4 * 2 * 4]).unwrap();
+        let cursor = event_loop.winit().create_custom_cursor(source);
+        let serial = compositor.pointer_enter(0);
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        let window = event_loop.winit().get_window_mut(window_id).unwrap();
+        window.set_cursor(cursor.into());
+        test_compositor::pump(&mut event_loop, &mut app);
+        let expected = PointerCursor::Surface { size: (4, 2), buffer_scale: 1, hotspot: (1, 1) };
+        assert_eq!(compositor.cursor(), Some((serial, expected)));
+
+        // The cursor is upscaled for the HiDPI outputs, keeping its logical size.
+        compositor.set_buffer_scale(0, 2);
+        test_compositor::pump(&mut event_loop, &mut app);
+        let expected = PointerCursor::Surface { size: (8, 4), buffer_scale: 2, hotspot: (1, 1) };
+        assert_eq!(compositor.cursor(), Some((serial, expected)));
+
+        // The cursors unknown to the loop fall back to the default one.
+        let window = event_loop.winit().get_window_mut(window_id).unwrap();
+        window.set_cursor(CustomCursor(u128::MAX).into());
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(compositor.cursor(), Some((serial, PointerCursor::Shape(CursorShape::Default))));
+    }
+
+    #[test]
+    fn animated_custom_cursor() {
+        let config = Config { pointer: true, ..Default::default() };
+        let (compositor, mut event_loop, mut app, window_id) = configured_window_with(config);
+        let clock = Arc::new(ManualClock::new());
+        event_loop.set_clock(clock.clone());
+
+        let small = CursorImage::from_rgba(1, 1, (0, 0), vec![255; 4]).unwrap();
+        let large = CursorImage::from_rgba(2, 2, (1, 1), vec![255; 2 * 2 * 4]).unwrap();
+        let frames = vec![(small, Duration::from_millis(50)), (large, Duration::from_millis(100))];
+        let source = CustomCursor::from_animation(frames).unwrap();
+        let cursor = event_loop.winit().create_custom_cursor(source);
+        let serial = compositor.pointer_enter(0);
+        test_compositor::pump(&mut event_loop, &mut app);
+
+        let small = PointerCursor::Surface { size: (1, 1), buffer_scale: 1, hotspot: (0, 0) };
+        let large = PointerCursor::Surface { size: (2, 2), buffer_scale: 1, hotspot: (1, 1) };
+        let window = event_loop.winit().get_window_mut(window_id).unwrap();
+        window.set_cursor(cursor.into());
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(compositor.cursor(), Some((serial, small.clone())));
+
+        clock.advance(Duration::from_millis(50));
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(compositor.cursor(), Some((serial, large)));
+
+        clock.advance(Duration::from_millis(100));
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(compositor.cursor(), Some((serial, small.clone())));
+
+        // The animation stops once the pointer leaves the window.
+        compositor.pointer_leave(0);
+        test_compositor::pump(&mut event_loop, &mut app);
+        clock.advance(Duration::from_millis(50));
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(compositor.cursor(), Some((serial, small)));
+    }
+
+    #[test]
+    fn cursor_grab() {
+        let config = Config {
+            pointer: true,
+            pointer_constraints: true,
+            relative_pointer: true,
+            ..Default::default()
+        };
+        let (compositor, mut event_loop, mut app, window_id) = configured_window_with(config);
+
+        // The pointer is grabbed only once it's over the window.
+        let window = event_loop.winit().get_window_mut(window_id).unwrap();
+        window.set_cursor_grab(CursorGrabMode::Locked).unwrap();
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(compositor.pointer_constraint(), None);
+
+        compositor.pointer_enter(0);
+        test_compositor::pump(&mut event_loop, &mut app);
+        let locked = PointerConstraint::Locked { position_hint: None };
+        assert_eq!(compositor.pointer_constraint(), Some(locked));
+
+        let window = event_loop.winit().get_window_mut(window_id).unwrap();
+        window.set_cursor_position(LogicalPosition::new(10., 20.).into()).unwrap();
+        test_compositor::pump(&mut event_loop, &mut app);
+        let locked = PointerConstraint::Locked { position_hint: Some((10., 20.)) };
+        assert_eq!(compositor.pointer_constraint(), Some(locked));
+
+        // The motion is reported without the acceleration.
+        compositor.relative_motion((3., -4.));
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(app.take_events(), [TestEvent::MouseMotion((3., -4.))]);
+
+        let window = event_loop.winit().get_window_mut(window_id).unwrap();
+        window.set_cursor_grab(CursorGrabMode::Confined).unwrap();
+        let position = LogicalPosition::new(10., 20.).into();
+        assert!(matches!(window.set_cursor_position(position), Err(RequestError::NotSupported(_))));
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(compositor.pointer_constraint(), Some(PointerConstraint::Confined));
+
+        compositor.pointer_leave(0);
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(compositor.pointer_constraint(), None);
+    }
+
+    #[test]
+    fn cursor_grab_unsupported() {
+        let config = Config { pointer: true, ..Default::default() };
+        let (_compositor, mut event_loop, _app, window_id) = configured_window_with(config);
+
+        let window = event_loop.winit().get_window_mut(window_id).unwrap();
+        let result = window.set_cursor_grab(CursorGrabMode::Locked);
+        assert!(matches!(result, Err(RequestError::NotSupported(_))));
+        window.set_cursor_grab(CursorGrabMode::None).unwrap();
+    }
+
+    #[test]
+    fn buffer_scale() {
+        let (compositor, mut event_loop, mut app, window_id) = configured_window();
+
+        compositor.set_buffer_scale(0, 2);
+        test_compositor::pump(&mut event_loop, &mut app);
+        assert_eq!(app.take_events(), [TestEvent::ScaleFactorChanged(window_id, 2.)]);
+        assert_eq!(compositor.toplevel(0).buffer_scale, 2);
+    }
+}